@@ -0,0 +1,317 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Rust ARM PrimeCell PL061 GPIO controller
+//!
+//! The 8 lines of the controller are offered through [`kernel::gpio`]: consumers get them
+//! with `GpioDesc::get`, and `GpioDesc::request_irq` installs a handler for the edge or
+//! level interrupt of a line. The controller is also the interrupt domain of the devices
+//! whose `interrupt-parent` it is, they request their interrupt like any other.
+
+#![no_std]
+
+use kernel::{
+    devres,
+    driver,
+    gpio::{self, Direction, GpioChip, GpioIrqHandler},
+    io::IoMem,
+    irq,
+    module_platform_driver, of, platform,
+    prelude::*,
+    property::FwNode,
+    sync::{Arc, SpinNoIrq},
+};
+
+module_platform_driver! {
+      type: Pl061Driver,
+      name: "gpio_pl061",
+      license: "GPL",
+      initcall: "subsys",
+}
+
+// Linux Raw id table
+kernel::module_of_id_table!(PL061_MOD_TABLE, PL061_OF_MATCH_TABLE);
+// R4L IdArray table
+kernel::define_of_id_table! {PL061_OF_MATCH_TABLE, (), [
+    (of::DeviceId::Compatible("arm,pl061"),None),
+]}
+
+const PL061_REG_SIZE: usize = 0x1000;
+const PL061_GPIO_NR: u32 = 8;
+
+const GPIODIR: usize = 0x400;
+const GPIOIS: usize = 0x404;
+const GPIOIBE: usize = 0x408;
+const GPIOIEV: usize = 0x40c;
+const GPIOIE: usize = 0x410;
+const GPIOMIS: usize = 0x418;
+const GPIOIC: usize = 0x41c;
+
+/// The data register of a line: address bits [9:2] mask the lines an access touches.
+const fn gpiodata(offset: u32) -> usize {
+    1 << (offset + 2)
+}
+
+struct Pl061Chip {
+    base: IoMem<PL061_REG_SIZE>,
+    // Serializes the read-modify-write of the registers.
+    lock: SpinNoIrq<()>,
+    handlers: SpinNoIrq<[Option<Arc<dyn Fn(u32) + Send + Sync>>; PL061_GPIO_NR as usize]>,
+    has_irq: bool,
+}
+
+impl Pl061Chip {
+    fn update_bits(&self, reg: usize, mask: u8, value: u8) -> Result {
+        let old = self.base.try_readb(reg)?;
+        self.base.try_writeb((old & !mask) | (value & mask), reg)
+    }
+
+    fn check_offset(offset: u32) -> Result<u8> {
+        if offset >= PL061_GPIO_NR {
+            return Err(EINVAL);
+        }
+        Ok(1 << offset)
+    }
+
+    /// Programs the trigger of the line interrupt, see `pl061_irq_type()` in Linux.
+    fn set_irq_type(&self, bit: u8, trigger: irq::Flags) -> Result {
+        let level = trigger.intersects(irq::Flags::TRIGGER_HIGH | irq::Flags::TRIGGER_LOW);
+        let rising = trigger.contains(irq::Flags::TRIGGER_RISING);
+        let falling = trigger.contains(irq::Flags::TRIGGER_FALLING);
+        if level && (rising || falling) {
+            return Err(EINVAL);
+        }
+        if trigger.contains(irq::Flags::TRIGGER_HIGH | irq::Flags::TRIGGER_LOW) {
+            return Err(EINVAL);
+        }
+
+        let _guard = self.lock.lock();
+        if level {
+            let high = trigger.contains(irq::Flags::TRIGGER_HIGH);
+            self.update_bits(GPIOIS, bit, bit)?;
+            self.update_bits(GPIOIBE, bit, 0)?;
+            self.update_bits(GPIOIEV, bit, if high { bit } else { 0 })?;
+        } else if rising && falling {
+            self.update_bits(GPIOIS, bit, 0)?;
+            self.update_bits(GPIOIBE, bit, bit)?;
+        } else if rising || falling {
+            self.update_bits(GPIOIS, bit, 0)?;
+            self.update_bits(GPIOIBE, bit, 0)?;
+            self.update_bits(GPIOIEV, bit, if rising { bit } else { 0 })?;
+        }
+        Ok(())
+    }
+}
+
+impl GpioChip for Pl061Chip {
+    fn ngpio(&self) -> u32 {
+        PL061_GPIO_NR
+    }
+
+    fn get_direction(&self, offset: u32) -> Result<Direction> {
+        let bit = Self::check_offset(offset)?;
+        if self.base.readb(GPIODIR) & bit != 0 {
+            Ok(Direction::Out)
+        } else {
+            Ok(Direction::In)
+        }
+    }
+
+    fn direction_input(&self, offset: u32) -> Result {
+        let bit = Self::check_offset(offset)?;
+        let _guard = self.lock.lock();
+        self.update_bits(GPIODIR, bit, 0)
+    }
+
+    fn direction_output(&self, offset: u32, value: bool) -> Result {
+        let bit = Self::check_offset(offset)?;
+        let _guard = self.lock.lock();
+        let data = if value { bit } else { 0 };
+        self.base.try_writeb(data, gpiodata(offset))?;
+        self.update_bits(GPIODIR, bit, bit)?;
+        // The value is only latched once the line is an output, on some implementations.
+        self.base.try_writeb(data, gpiodata(offset))
+    }
+
+    fn get(&self, offset: u32) -> Result<bool> {
+        Self::check_offset(offset)?;
+        Ok(self.base.try_readb(gpiodata(offset))? != 0)
+    }
+
+    fn set(&self, offset: u32, value: bool) {
+        if let Ok(bit) = Self::check_offset(offset) {
+            let _ = self.base.try_writeb(if value { bit } else { 0 }, gpiodata(offset));
+        }
+    }
+
+    fn irq_request(&self, offset: u32, trigger: irq::Flags, handler: GpioIrqHandler) -> Result {
+        let bit = Self::check_offset(offset)?;
+        if !self.has_irq {
+            return Err(ENOTSUPP);
+        }
+        {
+            let mut handlers = self.handlers.lock();
+            if handlers[offset as usize].is_some() {
+                return Err(EBUSY);
+            }
+            handlers[offset as usize] = Some(Arc::from(handler));
+        }
+        if let Err(e) = self.set_irq_type(bit, trigger) {
+            self.handlers.lock()[offset as usize] = None;
+            return Err(e);
+        }
+        let _guard = self.lock.lock();
+        // Drop an edge latched before the handler was there, then unmask.
+        self.base.writeb(bit, GPIOIC);
+        self.update_bits(GPIOIE, bit, bit)
+    }
+
+    fn irq_free(&self, offset: u32) {
+        let Ok(bit) = Self::check_offset(offset) else {
+            return;
+        };
+        {
+            let _guard = self.lock.lock();
+            let _ = self.update_bits(GPIOIE, bit, 0);
+        }
+        self.handlers.lock()[offset as usize] = None;
+    }
+}
+
+/// The handler of the controller interrupt, dispatching to the line handlers.
+struct Pl061IrqHandler;
+impl irq::Handler for Pl061IrqHandler {
+    type Data = Arc<Pl061Chip>;
+
+    fn handle_irq(chip: &Arc<Pl061Chip>) -> irq::Return {
+        let pending = chip.base.readb(GPIOMIS);
+        if pending == 0 {
+            return irq::Return::None;
+        }
+        // Acknowledge the edges first, an edge during the handler raises the irq again.
+        chip.base.writeb(pending, GPIOIC);
+        // Call the handlers unlocked, they may free their line.
+        let handlers = chip.handlers.lock().clone();
+        for offset in 0..PL061_GPIO_NR {
+            if pending & (1 << offset) == 0 {
+                continue;
+            }
+            match &handlers[offset as usize] {
+                Some(handler) => handler(offset),
+                None => pr_warn!("pl061: spurious irq on line {}", offset),
+            }
+        }
+        irq::Return::Handled
+    }
+}
+
+/// The interrupt domain of the lines, for the consumers that use a line as their interrupt.
+///
+/// The specifier is `<offset trigger>`.
+struct Pl061Domain(Arc<Pl061Chip>);
+
+impl irq::IrqDomainOps for Pl061Domain {
+    fn xlate(&self, intspec: &[u32]) -> Result<(u32, irq::Flags)> {
+        match intspec {
+            [offset, trigger, ..] if *offset < PL061_GPIO_NR => {
+                Ok((*offset, irq::Flags::from_dt_trigger(*trigger)))
+            }
+            _ => Err(EINVAL),
+        }
+    }
+
+    fn is_chained(&self) -> bool {
+        true
+    }
+
+    fn request_line(
+        &self,
+        hwirq: u32,
+        trigger: irq::Flags,
+        virq: u32,
+        handler: irq::IrqHandler,
+    ) -> Result {
+        self.0.irq_request(hwirq, trigger, Box::new(move |_| handler(virq)))
+    }
+
+    fn free_line(&self, hwirq: u32) {
+        self.0.irq_free(hwirq);
+    }
+
+    fn set_line_enabled(&self, hwirq: u32, enabled: bool) {
+        if let Ok(bit) = Pl061Chip::check_offset(hwirq) {
+            let _guard = self.0.lock.lock();
+            let _ = self.0.update_bits(GPIOIE, bit, if enabled { bit } else { 0 });
+        }
+    }
+}
+
+// The clock and the interrupt are managed resources of the device.
+struct Pl061Data {
+    fwnode: FwNode,
+}
+
+impl driver::DeviceRemoval for Pl061Data {
+    fn device_remove(&self) {
+        irq::irq_domain_remove(self.fwnode.of_node());
+        if gpio::gpiochip_remove(self.fwnode.of_node()).is_err() {
+            pr_warn!("pl061: lines still in use on remove");
+        }
+    }
+}
+
+/// Maps the registers and masks every line interrupt.
+fn pl061_init(pdev: &platform::Device) -> Result<IoMem<PL061_REG_SIZE>> {
+    // SAFETY: The registers of the controller belong to this driver only.
+    let base = unsafe { IoMem::<PL061_REG_SIZE>::try_new(pdev.io_resource(0)?)? };
+    base.writeb(0, GPIOIE);
+    base.writeb(0xff, GPIOIC);
+    Ok(base)
+}
+
+struct Pl061Driver;
+impl platform::Driver for Pl061Driver {
+    type Data = Arc<Pl061Data>;
+    // Linux Raw id table
+    kernel::driver_of_id_table!(PL061_OF_MATCH_TABLE);
+
+    fn probe(pdev: &mut platform::Device, _id_info: Option<&Self::IdInfo>,
+    ) -> Result<Self::Data> {
+        devres::devm_clk_get_optional_enabled(pdev.device(), None)?;
+
+        let base = pl061_init(pdev)?;
+        // The controller works without interrupt, only line interrupts are lost.
+        let irq = match pdev.irq_resource(0) {
+            Ok(irq) => Some(irq),
+            Err(ENOENT) | Err(EINVAL) => None,
+            Err(e) => return Err(e),
+        };
+        let chip = Arc::new(Pl061Chip {
+            base,
+            lock: SpinNoIrq::new(()),
+            handlers: SpinNoIrq::new(Default::default()),
+            has_irq: irq.is_some(),
+        });
+
+        if let Some(irq) = irq {
+            devres::devm_request_irq::<Pl061IrqHandler>(
+                pdev.device(),
+                irq,
+                chip.clone(),
+                irq::Flags::empty(),
+                format_args!("pl061_{}", pdev.name()),
+            )?;
+        }
+
+        let fwnode = pdev.fwnode();
+        gpio::gpiochip_add(fwnode.of_node(), chip.clone())?;
+        if irq.is_some() {
+            let domain = Arc::new(Pl061Domain(chip));
+            if let Err(e) = irq::irq_domain_add(fwnode.of_node(), domain) {
+                let _ = gpio::gpiochip_remove(fwnode.of_node());
+                return Err(e);
+            }
+        }
+        pr_info!("pl061 {}: {} lines, irq {:?}", pdev.name(), PL061_GPIO_NR, irq);
+        Ok(Arc::new(Pl061Data { fwnode }))
+    }
+}
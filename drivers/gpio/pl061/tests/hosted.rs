@@ -0,0 +1,116 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Runs the PL061 driver on the hosted backend, against a fake register block.
+
+#![cfg(feature = "hosted")]
+
+use core::ffi::c_int;
+use core::sync::atomic::{AtomicU32, Ordering};
+use kernel::init::driver_framework_init;
+use kernel::os::hosted::{self, fdt::FdtBuilder, trigger_irq};
+use kernel::{c_str, define_of_id_table, devres, irq, of, platform, prelude::*, ThisModule};
+
+extern "C" {
+    // The initcall of the module, see `module!`.
+    fn __gpio_pl061_init() -> c_int;
+}
+
+const GPIOIE: usize = 0x410;
+const GPIOMIS: usize = 0x418;
+
+const PL061_IRQ: u32 = 5;
+const BUTTON_LINE: u32 = 3;
+
+// The registers of the controller, the hosted `ioremap` maps them at their own address.
+#[repr(C, align(4096))]
+struct Regs([u8; 0x1000]);
+
+static THIS_MODULE: ThisModule = ThisModule();
+
+// The virq the button handler was called for, plus one.
+static BUTTON_IRQS: AtomicU32 = AtomicU32::new(0);
+
+struct ButtonIrqHandler;
+impl irq::Handler for ButtonIrqHandler {
+    type Data = u32;
+
+    fn handle_irq(irq: &u32) -> irq::Return {
+        BUTTON_IRQS.store(irq + 1, Ordering::SeqCst);
+        irq::Return::Handled
+    }
+}
+
+// A device whose interrupt is a line of the PL061.
+struct ButtonDriver;
+
+define_of_id_table! {BUTTON_OF_MATCH_TABLE, (), [
+    (of::DeviceId::Compatible("test,gpio-button"), None),
+]}
+
+impl platform::Driver for ButtonDriver {
+    type Data = ();
+    kernel::driver_of_id_table!(BUTTON_OF_MATCH_TABLE);
+
+    fn probe(pdev: &mut platform::Device, _id_info: Option<&Self::IdInfo>) -> Result {
+        let irq = pdev.irq_resource(0)?;
+        devres::devm_request_irq::<ButtonIrqHandler>(
+            pdev.device(),
+            irq,
+            irq,
+            irq::Flags::empty(),
+            format_args!("button"),
+        )
+    }
+}
+
+#[test]
+fn line_irq_reaches_its_consumer() {
+    let base = Box::into_raw(Box::new(Regs([0; 0x1000]))) as usize;
+    let reg = |offset: usize| (base + offset) as *mut u8;
+
+    let mut fdt = FdtBuilder::new();
+    fdt.begin_node("")
+        .property_u32("#address-cells", 2)
+        .property_u32("#size-cells", 2)
+        .property_u32("interrupt-parent", 1)
+        .begin_node("intc")
+        .property_u32("phandle", 1)
+        .property_empty("interrupt-controller")
+        .property_u32("#interrupt-cells", 1)
+        .end_node()
+        .begin_node(&format!("gpio@{:x}", base))
+        .property_strings("compatible", &["arm,pl061"])
+        .property_cells("reg", &[(base >> 32) as u32, base as u32, 0, 0x1000])
+        .property_u32("interrupts", PL061_IRQ)
+        .property_empty("gpio-controller")
+        .property_u32("#gpio-cells", 2)
+        .property_empty("interrupt-controller")
+        .property_u32("#interrupt-cells", 2)
+        .property_u32("phandle", 2)
+        .end_node()
+        .begin_node("button")
+        .property_strings("compatible", &["test,gpio-button"])
+        .property_u32("interrupt-parent", 2)
+        // Rising edge.
+        .property_cells("interrupts", &[BUTTON_LINE, 1])
+        .end_node()
+        .end_node();
+    hosted::load_device_tree(fdt.finish());
+    driver_framework_init();
+
+    // SAFETY: The module is initialized once, by this test only.
+    assert_eq!(unsafe { __gpio_pl061_init() }, 0);
+    let _button =
+        platform::Registration::<ButtonDriver>::new_pinned(c_str!("button"), &THIS_MODULE)
+            .unwrap();
+
+    // The consumer unmasked its line.
+    // SAFETY: The register block is leaked, the driver only accesses it volatile too.
+    assert_ne!(unsafe { reg(GPIOIE).read_volatile() } & (1 << BUTTON_LINE), 0);
+
+    // The line is pending, the controller interrupt dispatches it to the consumer.
+    // SAFETY: See above.
+    unsafe { reg(GPIOMIS).write_volatile(1 << BUTTON_LINE) };
+    assert!(trigger_irq(PL061_IRQ));
+    assert_eq!(BUTTON_IRQS.load(Ordering::SeqCst), irq::IRQ_CHAINED_BASE + 1);
+}
@@ -5,12 +5,15 @@
 #![no_std]
 
 use kernel::{
+    devres,
     driver,
     irq,
     device::Device,
+    io::IoMem,
     module_platform_driver, of, platform,
     sync::Arc,
     prelude::*,
+    reset::ResetControl,
 };
 
 module_platform_driver! {
@@ -27,12 +30,23 @@ kernel::define_of_id_table! {DW_I2C_OF_MATCH_TABLE, (), [
     (of::DeviceId::Compatible("snps,designware-i2c"),None),
 ]}
 
-struct DwI2cData();
+/// Size of the register block of the controller.
+const DW_IC_REG_SIZE: usize = 0x100;
+/// Component type register, reads as [`DW_IC_COMP_TYPE_VALUE`].
+const DW_IC_COMP_TYPE: usize = 0xfc;
+const DW_IC_COMP_TYPE_VALUE: u32 = 0x44570140;
+
+/// Default bus speed when the device tree does not set `clock-frequency`.
+const I2C_MAX_STANDARD_MODE_FREQ: u32 = 100_000;
+
+// The reset line and the clock are managed resources of the device.
+struct DwI2cData {
+    _base: Arc<IoMem<DW_IC_REG_SIZE>>,
+    _bus_freq_hz: u32,
+}
 
 impl driver::DeviceRemoval for DwI2cData {
-    fn device_remove(&self) {
-        pr_info!("unimplement DwI2cData Remove");
-    }
+    fn device_remove(&self) {}
 }
 
 struct DwI2cIrqHandler;
@@ -45,6 +59,18 @@ impl irq::Handler for DwI2cIrqHandler {
     }
 }
 
+/// Maps the registers of the controller and checks that it is a DesignWare I2C.
+fn map_registers(pdev: &platform::Device) -> Result<Arc<IoMem<DW_IC_REG_SIZE>>> {
+    // SAFETY: The registers of the controller belong to this driver only.
+    let base = unsafe { devres::devm_ioremap::<DW_IC_REG_SIZE>(pdev.device(), 0)? };
+    let comp_type = base.readl(DW_IC_COMP_TYPE);
+    if comp_type != DW_IC_COMP_TYPE_VALUE {
+        pr_err!("unknown Synopsys component type: {:#x}", comp_type);
+        return Err(ENODEV);
+    }
+    Ok(base)
+}
+
 struct DwI2cDriver;
 impl platform::Driver for DwI2cDriver {
     type Data = Arc<DwI2cData>;
@@ -54,7 +80,33 @@ impl platform::Driver for DwI2cDriver {
     fn probe(pdev: &mut platform::Device,_id_info: Option<&Self::IdInfo>,
     ) -> Result<Self::Data> {
         let irq = pdev.irq_resource(0)?;
-        pr_err!("enter i2c platform probe func, get irq {}",irq);
-        Ok(Arc::new(DwI2cData()))
+        let bus_freq_hz = match pdev.fwnode().property_read_u32("clock-frequency") {
+            Ok(freq) => freq,
+            Err(ENOENT) => I2C_MAX_STANDARD_MODE_FREQ,
+            Err(e) => return Err(e),
+        };
+        pr_info!("i2c bus frequency {} Hz", bus_freq_hz);
+
+        if let Some(rst) = ResetControl::get_optional_exclusive(pdev.device(), None)? {
+            rst.deassert()?;
+            pdev.device().devm_add_action_or_reset(move || {
+                let _ = rst.assert();
+            })?;
+        }
+
+        // The input clock drives the SCL timings, the controller may run without one.
+        if let Some(clk) = devres::devm_clk_get_optional_enabled(pdev.device(), None)? {
+            pr_info!("i2c input clock {} Hz", clk.get_rate());
+        }
+
+        let base = map_registers(pdev)?;
+        devres::devm_request_irq::<DwI2cIrqHandler>(
+            pdev.device(),
+            irq,
+            irq as i32,
+            irq::Flags::empty(),
+            format_args!("i2c_designware"),
+        )?;
+        Ok(Arc::new(DwI2cData { _base: base, _bus_freq_hz: bus_freq_hz }))
     }
 }
@@ -0,0 +1,83 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Runs the DesignWare I2C driver on the hosted backend, against fake register blocks.
+
+#![cfg(feature = "hosted")]
+
+use core::ffi::c_int;
+use kernel::init::driver_framework_init;
+use kernel::os::hosted::{self, fdt::FdtBuilder, take_log_records, trigger_irq};
+
+extern "C" {
+    // The initcall of the module, see `module!`.
+    fn __i2c_designware_init() -> c_int;
+}
+
+const DW_IC_COMP_TYPE: usize = 0xfc;
+const DW_IC_COMP_TYPE_VALUE: u32 = 0x44570140;
+
+// The registers of a controller, the hosted `ioremap` maps them at their own address.
+#[repr(C, align(4096))]
+struct Regs([u32; 0x40]);
+
+fn regs(comp_type: u32) -> usize {
+    let mut regs = Box::new(Regs([0; 0x40]));
+    regs.0[DW_IC_COMP_TYPE / 4] = comp_type;
+    Box::leak(regs) as *mut Regs as usize
+}
+
+fn i2c_node(fdt: &mut FdtBuilder, base: usize, irq: u32, freq: Option<u32>) {
+    fdt.begin_node(&format!("i2c@{:x}", base))
+        .property_strings("compatible", &["snps,designware-i2c"])
+        .property_cells(
+            "reg",
+            &[(base >> 32) as u32, base as u32, 0, core::mem::size_of::<Regs>() as u32],
+        )
+        .property_u32("interrupts", irq);
+    if let Some(freq) = freq {
+        fdt.property_u32("clock-frequency", freq);
+    }
+    fdt.end_node();
+}
+
+fn logged(messages: &[String], text: &str) -> bool {
+    messages.iter().any(|m| m.contains(text))
+}
+
+#[test]
+fn probe_and_irq() {
+    let good = regs(DW_IC_COMP_TYPE_VALUE);
+    let bad = regs(0);
+
+    let mut fdt = FdtBuilder::new();
+    fdt.begin_node("")
+        .property_u32("#address-cells", 2)
+        .property_u32("#size-cells", 2)
+        .property_u32("interrupt-parent", 1)
+        .begin_node("intc")
+        .property_u32("phandle", 1)
+        .property_empty("interrupt-controller")
+        .property_u32("#interrupt-cells", 1)
+        .end_node();
+    i2c_node(&mut fdt, good, 17, Some(400_000));
+    i2c_node(&mut fdt, bad, 18, None);
+    fdt.end_node();
+    hosted::load_device_tree(fdt.finish());
+    driver_framework_init();
+    take_log_records();
+
+    // SAFETY: The module is initialized once, by this test only.
+    assert_eq!(unsafe { __i2c_designware_init() }, 0);
+    let messages: Vec<_> = take_log_records().into_iter().map(|r| r.message).collect();
+    assert!(logged(&messages, "i2c bus frequency 400000 Hz"));
+    assert!(logged(&messages, "i2c bus frequency 100000 Hz"));
+    assert!(logged(&messages, "unknown Synopsys component type: 0x0"));
+
+    // The bound controller handles its interrupt.
+    assert!(trigger_irq(17));
+    let messages: Vec<_> = take_log_records().into_iter().map(|r| r.message).collect();
+    assert!(logged(&messages, "handled i2c irq get data 17"));
+
+    // The probe of the other one failed, nothing is left registered.
+    assert!(!trigger_irq(18));
+}
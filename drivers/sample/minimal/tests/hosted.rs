@@ -0,0 +1,22 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Runs the minimal sample on the hosted backend.
+
+#![cfg(feature = "hosted")]
+
+use core::ffi::c_int;
+use kernel::os::hosted::take_log_records;
+
+extern "C" {
+    // The initcall of the module, see `module!`.
+    fn __rust_minimal_init() -> c_int;
+}
+
+#[test]
+fn init_logs() {
+    // SAFETY: The module is initialized once, by this test only.
+    assert_eq!(unsafe { __rust_minimal_init() }, 0);
+    let messages: Vec<_> = take_log_records().into_iter().map(|r| r.message).collect();
+    assert!(messages.iter().any(|m| m.contains("Rust minimal sample (init)")));
+    assert!(messages.iter().any(|m| m.contains("Am I built-in? true")));
+}
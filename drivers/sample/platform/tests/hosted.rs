@@ -0,0 +1,41 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Runs the platform sample on the hosted backend.
+
+#![cfg(feature = "hosted")]
+
+use core::ffi::c_int;
+use kernel::init::driver_framework_init;
+use kernel::os::hosted::{self, fdt::FdtBuilder, take_log_records};
+
+extern "C" {
+    // The initcall of the module, see `module!`.
+    fn __platform_sample_init() -> c_int;
+}
+
+#[test]
+fn probe_matching_device() {
+    let mut fdt = FdtBuilder::new();
+    fdt.begin_node("")
+        .property_u32("#address-cells", 2)
+        .property_u32("#size-cells", 2)
+        .begin_node("serial@1000")
+        .property_strings("compatible", &["snps,dw-apb-uart"])
+        .end_node()
+        .begin_node("serial@2000")
+        .property_strings("compatible", &["ns16550a"])
+        .end_node()
+        .end_node();
+    hosted::load_device_tree(fdt.finish());
+    driver_framework_init();
+    take_log_records();
+
+    // SAFETY: The module is initialized once, by this test only.
+    assert_eq!(unsafe { __platform_sample_init() }, 0);
+    let probes = take_log_records()
+        .into_iter()
+        .filter(|r| r.message.contains("platform driver probe success"))
+        .count();
+    // Only the device matching the id table is probed.
+    assert_eq!(probes, 1);
+}
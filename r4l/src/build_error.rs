@@ -2,9 +2,10 @@
 
 //! Build-time error.
 //!
-//! This crate provides a [const function][const-functions] `build_error`, which will panic in
-//! compile-time if executed in [const context][const-context], and will cause a build error
-//! if not executed at compile time and the optimizer does not optimise away the call.
+//! This module provides a [const function][const-functions] `build_error`, which will panic in
+//! compile-time if executed in [const context][const-context], and panics at runtime
+//! otherwise. Unlike Linux, where the C side leaves the symbol undefined to fail the link,
+//! r4l has no such symbol to leave out, a call kept by the optimizer stays a runtime check.
 //!
 //! It is used by `build_assert!` in the kernel crate, allowing checking of
 //! conditions that could be checked statically, but could not be enforced in
@@ -17,7 +18,7 @@
 //! [const-functions]: https://doc.rust-lang.org/reference/const_eval.html#const-functions
 //! [const-context]: https://doc.rust-lang.org/reference/const_eval.html#const-context
 
-/// Panics if executed in [const context][const-context], or triggers a build error if not.
+/// Fails the build if executed in [const context][const-context], panics if not.
 ///
 /// [const-context]: https://doc.rust-lang.org/reference/const_eval.html#const-context
 #[inline(never)]
@@ -26,3 +27,25 @@
 pub const fn build_error(msg: &'static str) -> ! {
     panic!("{}", msg);
 }
+
+/// Asserts that a boolean expression is `true` at compile time.
+///
+/// If the condition is evaluated to `false` in const context, `build_assert!` will cause a
+/// compilation error. Otherwise the condition is checked at runtime and [`build_error`]
+/// panics if it is `false`; the optimizer drops the check when it can prove the condition.
+///
+/// Use it where the condition depends on values known at the call site, like constant
+/// offsets, but not on generic parameters alone.
+#[macro_export]
+macro_rules! build_assert {
+    ($cond:expr $(,)?) => {{
+        if !$cond {
+            $crate::build_error(concat!("assertion failed: ", stringify!($cond)));
+        }
+    }};
+    ($cond:expr, $msg:expr) => {{
+        if !$cond {
+            $crate::build_error($msg);
+        }
+    }};
+}
@@ -14,6 +14,10 @@ pub trait BusType {
     type Driver = ();
 
     fn bus_driver_match(&self, pdrv: Self::Driver) -> Vec<Self::Device>;
+    /// Returns the first registered driver able to drive `device`.
+    fn bus_device_match(&self, device: Self::Device) -> Option<Self::Driver>;
     fn add_device(&mut self, device: Self::Device) -> Result;
     fn add_driver(&mut self, driver: Self::Driver) -> Result;
+    fn remove_device(&mut self, device: Self::Device) -> Result;
+    fn remove_driver(&mut self, driver: Self::Driver) -> Result;
 }
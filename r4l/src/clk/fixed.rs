@@ -0,0 +1,95 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Fixed rate and fixed factor clocks.
+//!
+//! C source: [`drivers/clk/clk-fixed-rate.c`](../../../../drivers/clk/clk-fixed-rate.c) and
+//! [`drivers/clk/clk-fixed-factor.c`](../../../../drivers/clk/clk-fixed-factor.c)
+
+use super::*;
+use crate::of::of_property_strings;
+
+/// A clock running at a fixed rate, like an oscillator.
+pub struct FixedRateClk {
+    /// The rate, in Hz.
+    pub rate: u64,
+}
+
+impl ClkOps for FixedRateClk {
+    fn recalc_rate(&self, _parent_rate: u64) -> u64 {
+        self.rate
+    }
+}
+
+/// A clock whose rate is the rate of its parent multiplied by `mult / div`.
+pub struct FixedFactorClk {
+    /// The multiplier.
+    pub mult: u32,
+    /// The divider, not 0.
+    pub div: u32,
+}
+
+impl ClkOps for FixedFactorClk {
+    fn recalc_rate(&self, parent_rate: u64) -> u64 {
+        parent_rate * self.mult as u64 / self.div as u64
+    }
+}
+
+fn of_clk_name(node: OfNode<'static>) -> &'static str {
+    of_property_strings(node, "clock-output-names")
+        .next()
+        .unwrap_or_else(|| of_node_name(node))
+}
+
+fn of_fixed_clk_setup(node: OfNode<'static>) -> Result {
+    let rate = crate::of::of_property_read_cell(node, "clock-frequency", 0).ok_or(EINVAL)?;
+    let core = ClkCore::new(of_clk_name(node), Box::new(FixedRateClk { rate: rate as u64 }), None);
+    of_clk_add_provider(node, Arc::new(SimpleClkProvider(Arc::new(core))))
+}
+
+fn of_fixed_factor_clk_setup(node: OfNode<'static>) -> Result {
+    let mult = crate::of::of_property_read_cell(node, "clock-mult", 0).ok_or(EINVAL)?;
+    let div = crate::of::of_property_read_cell(node, "clock-div", 0).ok_or(EINVAL)?;
+    if div == 0 {
+        return Err(EINVAL);
+    }
+    let parent = of_clk_get(node, 0)?;
+    let core = ClkCore::new(of_clk_name(node), Box::new(FixedFactorClk { mult, div }), Some(parent));
+    of_clk_add_provider(node, Arc::new(SimpleClkProvider(Arc::new(core))))
+}
+
+/// Registers the clocks of the `fixed-clock` and `fixed-factor-clock` nodes.
+///
+/// Fixed factor clocks may hang on each other in any order, so they are set up in passes
+/// until every one found its parent.
+pub fn of_clk_init() -> Result {
+    for node in of::find_compatible_node(&["fixed-clock"]) {
+        if !of::of_device_is_available(node) {
+            continue;
+        }
+        if let Err(e) = of_fixed_clk_setup(node) {
+            pr_err!("fixed clock {} setup failed: {:?}", of_node_name(node), e);
+        }
+    }
+
+    let mut pending: Vec<OfNode<'static>> = of::find_compatible_node(&["fixed-factor-clock"])
+        .filter(|node| of::of_device_is_available(*node))
+        .collect();
+    while !pending.is_empty() {
+        let before = pending.len();
+        pending.retain(|node| match of_fixed_factor_clk_setup(*node) {
+            Ok(()) => false,
+            Err(EPROBE_DEFER) => true,
+            Err(e) => {
+                pr_err!("fixed factor clock {} setup failed: {:?}", of_node_name(*node), e);
+                false
+            }
+        });
+        if pending.len() == before {
+            for node in pending.iter() {
+                pr_warn!("fixed factor clock {} has no parent", of_node_name(*node));
+            }
+            break;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,308 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Common clock framework.
+//!
+//! C header: [`include/linux/clk.h`](../../../../include/linux/clk.h) and
+//! [`include/linux/clk-provider.h`](../../../../include/linux/clk-provider.h)
+//!
+//! Providers implement [`ClkOps`] for each clock, wrap it in a [`ClkCore`] and register a
+//! [`ClkProvider`] for their device tree node with [`of_clk_add_provider`]. Consumers get a
+//! [`Clk`] from the `clocks`/`clock-names` properties of their device.
+//!
+//! `fixed-clock` and `fixed-factor-clock` nodes are registered by [`of_clk_init`], during
+//! [`crate::init::driver_framework_init`].
+
+mod fixed;
+
+pub use fixed::*;
+
+use crate::device::Device;
+use crate::of::{of_get_phandle, of_node_name, parse_phandle_with_args};
+use crate::prelude::*;
+use crate::sync::{Arc, Mutex, SpinNoIrq};
+use of::OfNode;
+
+/// The operations of a clock, implemented by clock providers.
+///
+/// Only [`ClkOps::recalc_rate`] is mandatory, a clock that cannot be gated or changed keeps
+/// the default implementations.
+pub trait ClkOps: Send + Sync {
+    /// Prepares the clock, may sleep.
+    fn prepare(&self) -> Result {
+        Ok(())
+    }
+
+    /// Undoes [`ClkOps::prepare`].
+    fn unprepare(&self) {}
+
+    /// Ungates the clock, must not sleep.
+    fn enable(&self) -> Result {
+        Ok(())
+    }
+
+    /// Gates the clock, must not sleep.
+    fn disable(&self) {}
+
+    /// Returns the rate of the clock, given the rate of its parent, 0 without parent.
+    fn recalc_rate(&self, parent_rate: u64) -> u64;
+
+    /// Changes the rate of the clock.
+    fn set_rate(&self, _rate: u64, _parent_rate: u64) -> Result {
+        Err(ENOTSUPP)
+    }
+}
+
+/// A clock of the clock tree.
+pub struct ClkCore {
+    name: &'static str,
+    ops: Box<dyn ClkOps>,
+    parent: Option<Arc<ClkCore>>,
+    prepare_count: Mutex<usize>,
+    enable_count: SpinNoIrq<usize>,
+}
+
+impl ClkCore {
+    /// Creates a clock named `name`, whose input is `parent`.
+    pub fn new(name: &'static str, ops: Box<dyn ClkOps>, parent: Option<Arc<ClkCore>>) -> Self {
+        Self {
+            name,
+            ops,
+            parent,
+            prepare_count: Mutex::new(0),
+            enable_count: SpinNoIrq::new(0),
+        }
+    }
+
+    /// Returns the name of the clock.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the rate of the clock, in Hz.
+    pub fn rate(&self) -> u64 {
+        let parent_rate = self.parent.as_ref().map_or(0, |parent| parent.rate());
+        self.ops.recalc_rate(parent_rate)
+    }
+
+    fn prepare(&self) -> Result {
+        let mut count = self.prepare_count.lock();
+        if *count == 0 {
+            if let Some(parent) = &self.parent {
+                parent.prepare()?;
+            }
+            if let Err(e) = self.ops.prepare() {
+                if let Some(parent) = &self.parent {
+                    parent.unprepare();
+                }
+                return Err(e);
+            }
+        }
+        *count += 1;
+        Ok(())
+    }
+
+    fn unprepare(&self) {
+        let mut count = self.prepare_count.lock();
+        if *count == 0 {
+            pr_warn!("clk {} already unprepared", self.name);
+            return;
+        }
+        *count -= 1;
+        if *count == 0 {
+            self.ops.unprepare();
+            if let Some(parent) = &self.parent {
+                parent.unprepare();
+            }
+        }
+    }
+
+    fn enable(&self) -> Result {
+        let mut count = self.enable_count.lock();
+        if *count == 0 {
+            if let Some(parent) = &self.parent {
+                parent.enable()?;
+            }
+            if let Err(e) = self.ops.enable() {
+                if let Some(parent) = &self.parent {
+                    parent.disable();
+                }
+                return Err(e);
+            }
+        }
+        *count += 1;
+        Ok(())
+    }
+
+    fn disable(&self) {
+        let mut count = self.enable_count.lock();
+        if *count == 0 {
+            pr_warn!("clk {} already disabled", self.name);
+            return;
+        }
+        *count -= 1;
+        if *count == 0 {
+            self.ops.disable();
+            if let Some(parent) = &self.parent {
+                parent.disable();
+            }
+        }
+    }
+}
+
+/// A clock provider: the clocks of a device tree node with `#clock-cells`.
+pub trait ClkProvider: Send + Sync {
+    /// Returns the clock selected by the argument cells of a `clocks` entry.
+    fn get(&self, args: &[u32]) -> Result<Arc<ClkCore>>;
+}
+
+/// A provider of a single clock, for nodes with `#clock-cells = <0>`.
+pub struct SimpleClkProvider(pub Arc<ClkCore>);
+
+impl ClkProvider for SimpleClkProvider {
+    fn get(&self, _args: &[u32]) -> Result<Arc<ClkCore>> {
+        Ok(self.0.clone())
+    }
+}
+
+/// A provider of several clocks, selected by a one cell index.
+pub struct OneCellClkProvider(pub Vec<Arc<ClkCore>>);
+
+impl ClkProvider for OneCellClkProvider {
+    fn get(&self, args: &[u32]) -> Result<Arc<ClkCore>> {
+        let index = *args.first().ok_or(EINVAL)? as usize;
+        self.0.get(index).cloned().ok_or(EINVAL)
+    }
+}
+
+// Registered providers, keyed by the phandle of their node.
+static CLK_PROVIDERS: Mutex<Vec<(u32, Arc<dyn ClkProvider>)>> = Mutex::new(Vec::new());
+
+/// Registers the clock provider of `node`.
+pub fn of_clk_add_provider(node: OfNode<'static>, provider: Arc<dyn ClkProvider>) -> Result {
+    let phandle = of_get_phandle(node).ok_or(EINVAL)?;
+    let mut providers = CLK_PROVIDERS.lock();
+    if providers.iter().any(|(ph, _)| *ph == phandle) {
+        return Err(EEXIST);
+    }
+    providers.try_reserve(1)?;
+    providers.push((phandle, provider));
+    pr_debug!("clk provider {} registered", of_node_name(node));
+    Ok(())
+}
+
+/// Removes the clock provider of `node`.
+pub fn of_clk_del_provider(node: OfNode<'static>) {
+    if let Some(phandle) = of_get_phandle(node) {
+        CLK_PROVIDERS.lock().retain(|(ph, _)| *ph != phandle);
+    }
+}
+
+/// Returns the clock of the `clocks` entry `index` of `node`.
+///
+/// Fails with [`EPROBE_DEFER`] if the provider of the clock is not registered yet.
+pub fn of_clk_get(node: OfNode<'static>, index: usize) -> Result<Arc<ClkCore>> {
+    let spec = parse_phandle_with_args(node, "clocks", "#clock-cells", index)?;
+    let phandle = of_get_phandle(spec.np).ok_or(EINVAL)?;
+    let provider = CLK_PROVIDERS
+        .lock()
+        .iter()
+        .find(|(ph, _)| *ph == phandle)
+        .map(|(_, provider)| provider.clone())
+        .ok_or(EPROBE_DEFER)?;
+    provider.get(spec.args())
+}
+
+/// Returns the clock named `name` in the `clock-names` of `node`, or the first clock.
+pub fn of_clk_get_by_name(node: OfNode<'static>, name: Option<&str>) -> Result<Arc<ClkCore>> {
+    let index = match name {
+        Some(name) => crate::of::of_property_match_string(node, "clock-names", name).ok_or(ENOENT)?,
+        None => 0,
+    };
+    of_clk_get(node, index)
+}
+
+/// A clock used by a device.
+///
+/// The consumer must balance its calls: every [`Clk::prepare_enable`] is undone by a
+/// [`Clk::disable_unprepare`] before the `Clk` is dropped.
+///
+/// # Examples
+///
+/// ```ignore
+/// # use kernel::{clk::Clk, platform, prelude::*};
+/// fn probe(pdev: &mut platform::Device) -> Result {
+///     let clk = Clk::get(pdev.device(), None)?;
+///     clk.prepare_enable()?;
+///     pr_info!("input clock {} Hz", clk.get_rate());
+///     Ok(())
+/// }
+/// ```
+pub struct Clk(Arc<ClkCore>);
+
+impl Clk {
+    /// Gets the clock named `name` of `dev`, or its first clock if `name` is `None`.
+    pub fn get(dev: &Device, name: Option<&str>) -> Result<Self> {
+        Ok(Self(of_clk_get_by_name(dev.of_node(), name)?))
+    }
+
+    /// Like [`Clk::get`], but returns `None` if the device has no such clock.
+    pub fn get_optional(dev: &Device, name: Option<&str>) -> Result<Option<Self>> {
+        match Self::get(dev, name) {
+            Ok(clk) => Ok(Some(clk)),
+            Err(ENOENT) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Prepares the clock, may sleep.
+    pub fn prepare(&self) -> Result {
+        self.0.prepare()
+    }
+
+    /// Undoes [`Clk::prepare`].
+    pub fn unprepare(&self) {
+        self.0.unprepare()
+    }
+
+    /// Ungates the prepared clock.
+    pub fn enable(&self) -> Result {
+        self.0.enable()
+    }
+
+    /// Gates the clock.
+    pub fn disable(&self) {
+        self.0.disable()
+    }
+
+    /// Prepares and ungates the clock.
+    pub fn prepare_enable(&self) -> Result {
+        self.prepare()?;
+        if let Err(e) = self.enable() {
+            self.unprepare();
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Gates and unprepares the clock.
+    pub fn disable_unprepare(&self) {
+        self.disable();
+        self.unprepare();
+    }
+
+    /// Returns the rate of the clock, in Hz.
+    pub fn get_rate(&self) -> u64 {
+        self.0.rate()
+    }
+
+    /// Changes the rate of the clock, in Hz.
+    pub fn set_rate(&self, rate: u64) -> Result {
+        let parent_rate = self.0.parent.as_ref().map_or(0, |parent| parent.rate());
+        self.0.ops.set_rate(rate, parent_rate)
+    }
+
+    /// Returns the name of the clock.
+    pub fn name(&self) -> &'static str {
+        self.0.name()
+    }
+}
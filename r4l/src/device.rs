@@ -5,16 +5,28 @@
 //! C header: [`include/linux/device.h`](../../../../include/linux/device.h)
 //!
 
+use crate::dma::DmaAddr;
+use crate::io::Resource;
+use crate::irq::Flags;
+use crate::pinctrl::{Pinctrl, PINCTRL_STATE_DEFAULT, PINCTRL_STATE_INIT};
 use crate::pr_info;
 use crate::prelude::*;
+use crate::property::FwNode;
+use crate::sync::Mutex;
 use core::any::Any;
 use of::OfNode;
 
 pub struct Device {
     of_node: OfNode<'static>,
-    // Driver matched the first device compatiable
-    drv_matched: Option<&'static str>,
+    // Index of the entry of the driver id table that matched the device
+    drv_matched: Option<usize>,
     drv_data: Option<Box<dyn Any>>,
+    // The pin states, parsed when the device is bound.
+    pins: Option<Pinctrl>,
+    // The DMA addresses the device can reach.
+    dma_mask: u64,
+    // The release actions of the managed resources, in registration order.
+    devres: Mutex<Vec<Box<dyn FnOnce() + Send>>>,
 }
 
 impl Device {
@@ -23,10 +35,43 @@ impl Device {
             of_node,
             drv_data: None,
             drv_matched: None,
+            pins: None,
+            dma_mask: crate::dma::dma_bit_mask(32),
+            devres: Mutex::new(Vec::new()),
         }
     }
 
+    /// Returns the device tree node of the device.
+    pub fn of_node(&self) -> OfNode<'static> {
+        self.of_node
+    }
+
+    /// Returns the firmware node of the device, to read its properties.
+    pub fn fwnode(&self) -> FwNode {
+        FwNode::from_of_node(self.of_node)
+    }
+
+    /// Returns the name of the device, the name of its device tree node.
+    pub fn name(&self) -> &'static str {
+        crate::of::of_node_name(self.of_node)
+    }
+
+    /// Returns the register window `index` of the device, from its `reg` property.
+    pub fn io_resource(&self, index: usize) -> Result<Resource> {
+        crate::of::of_address_to_resource(self.of_node, index)
+    }
+
     pub fn irq_resource(&self, index: usize) -> Result<u32> {
+        Ok(self.irq_resource_flags(index)?.0)
+    }
+
+    /// Returns the irq named `name` in the `interrupt-names` of the device.
+    pub fn irq_by_name(&self, name: &str) -> Result<u32> {
+        Ok(crate::of::of_irq_get_byname(self.of_node, name)?.0)
+    }
+
+    /// Returns the irq `index` of the device along with its trigger flags.
+    pub fn irq_resource_flags(&self, index: usize) -> Result<(u32, Flags)> {
         crate::of::of_irq_get(self.of_node, index)
     }
 
@@ -38,16 +83,232 @@ impl Device {
         self.drv_data.as_ref()?.downcast_ref::<T>()
     }
 
+    /// Records the index of the driver id table entry that matched the device.
+    pub fn set_drv_matched(&mut self, index: Option<usize>) {
+        self.drv_matched = index;
+    }
+
+    /// Returns the index of the driver id table entry that matched the device.
+    pub fn drv_matched(&self) -> Option<usize> {
+        self.drv_matched
+    }
+
+    /// Selects the `init` pin state of the device before its probe, `default` if it has
+    /// none.
+    pub(crate) fn pinctrl_bind_pins(&mut self) -> Result {
+        let pins = match Pinctrl::get(self.of_node) {
+            Ok(pins) => pins,
+            Err(ENOENT) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        if pins.has_state(PINCTRL_STATE_INIT) {
+            pins.select_state(PINCTRL_STATE_INIT)?;
+        } else if pins.has_state(PINCTRL_STATE_DEFAULT) {
+            pins.select_state(PINCTRL_STATE_DEFAULT)?;
+        }
+        self.pins = Some(pins);
+        Ok(())
+    }
+
+    /// Moves the device from the `init` pin state to `default` after its probe succeeded,
+    /// unless the driver selected another state.
+    pub(crate) fn pinctrl_init_done(&self) -> Result {
+        match &self.pins {
+            Some(pins)
+                if pins.current_state() == Some(PINCTRL_STATE_INIT)
+                    && pins.has_state(PINCTRL_STATE_DEFAULT) =>
+            {
+                pins.select_state(PINCTRL_STATE_DEFAULT)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Drops the pin states after a failed probe.
+    pub(crate) fn pinctrl_unbind_pins(&mut self) {
+        self.pins = None;
+    }
+
+    /// Selects the pin state `name` of the device, like `sleep` or `default`.
+    ///
+    /// Fails with [`ENODEV`] if the device has no such state.
+    pub fn pinctrl_select_state(&self, name: &str) -> Result {
+        self.pins.as_ref().ok_or(ENODEV)?.select_state(name)
+    }
+
+    /// Drops the driver data and the pin states, once the driver is unbound.
+    pub fn clear_drv_data(&mut self) {
+        self.drv_data = None;
+        self.pins = None;
+    }
+
     pub fn compatible_match(&self, compatible: &'static str) -> bool {
-        match self.of_node.compatible() {
-            Some(n) => n.all().find(|one| *one == compatible).is_some(),
-            None => false,
+        self.compatible_index(compatible).is_some()
+    }
+
+    /// Returns the position of `compatible` in the compatible list of the device.
+    ///
+    /// The list goes from the most to the least specific string, so a lower position is a
+    /// better match.
+    pub fn compatible_index(&self, compatible: &str) -> Option<usize> {
+        self.of_node.compatible()?.all().position(|one| one == compatible)
+    }
+}
+
+/// DMA attributes, see [`crate::dma`].
+impl Device {
+    /// Sets the DMA addresses the device can reach, see [`crate::dma::dma_bit_mask`].
+    ///
+    /// Fails with [`EIO`] for masks below 24 bits, too narrow for any buffer.
+    pub fn dma_set_mask(&mut self, mask: u64) -> Result {
+        if mask < crate::dma::dma_bit_mask(24) {
+            return Err(EIO);
+        }
+        self.dma_mask = mask;
+        Ok(())
+    }
+
+    /// Returns the DMA addresses the device can reach.
+    pub fn dma_mask(&self) -> u64 {
+        self.dma_mask
+    }
+
+    /// Returns true if the DMA of the device is coherent with the CPU caches.
+    pub fn dma_coherent(&self) -> bool {
+        crate::of::of_dma_is_coherent(self.of_node)
+    }
+
+    /// Converts a CPU physical address to the address the device uses for it.
+    pub fn phys_to_dma(&self, paddr: usize) -> Result<DmaAddr> {
+        let offset = crate::of::of_dma_get_offset(self.of_node)?;
+        Ok((paddr as u64).wrapping_sub(offset))
+    }
+
+    /// Converts an address used by the device to the CPU physical address.
+    pub fn dma_to_phys(&self, dma_addr: DmaAddr) -> Result<usize> {
+        let offset = crate::of::of_dma_get_offset(self.of_node)?;
+        Ok(usize::try_from(dma_addr.wrapping_add(offset))?)
+    }
+}
+
+/// Managed resources, see [`crate::devres`].
+impl Device {
+    /// Registers `action` to run when the driver is unbound from the device, or when its
+    /// probe fails.
+    ///
+    /// The actions run in the reverse order of their registration, after the driver data
+    /// is dropped.
+    pub fn devm_add_action(&self, action: impl FnOnce() + Send + 'static) -> Result {
+        let mut devres = self.devres.lock();
+        devres.try_reserve(1)?;
+        devres.push(Box::new(action));
+        Ok(())
+    }
+
+    /// Like [`Device::devm_add_action`], but runs `action` right away if it cannot be
+    /// registered.
+    pub fn devm_add_action_or_reset(
+        &self,
+        action: impl FnOnce() + Send + 'static,
+    ) -> Result {
+        let mut devres = self.devres.lock();
+        if let Err(e) = devres.try_reserve(1) {
+            drop(devres);
+            action();
+            return Err(e.into());
         }
+        devres.push(Box::new(action));
+        Ok(())
+    }
+
+    /// Takes the release actions of the managed resources out of the device.
+    ///
+    /// The bus runs them with [`DevresActions::release`] once it unlocked the device, the
+    /// actions may lock it again.
+    pub(crate) fn devres_take_all(&self) -> DevresActions {
+        DevresActions(core::mem::take(&mut *self.devres.lock()))
+    }
+}
+
+/// The release actions of the managed resources of a device, see
+/// [`Device::devres_take_all`].
+pub(crate) struct DevresActions(Vec<Box<dyn FnOnce() + Send>>);
+
+impl DevresActions {
+    /// Runs the actions, the last registered first.
+    pub(crate) fn release(self) {
+        for action in self.0.into_iter().rev() {
+            action();
+        }
+    }
+}
+
+/// Property accessors, see [`FwNode`] for their semantics.
+impl Device {
+    /// Returns true if the device has the property `name`.
+    pub fn property_present(&self, name: &str) -> bool {
+        self.fwnode().property_present(name)
+    }
+
+    /// Reads a boolean property, true if it is present.
+    pub fn property_read_bool(&self, name: &str) -> bool {
+        self.fwnode().property_read_bool(name)
+    }
+
+    /// Returns the number of elements of `elem_size` bytes of the property `name`.
+    pub fn property_count_elems_of_size(&self, name: &str, elem_size: usize) -> Result<usize> {
+        self.fwnode().property_count_elems_of_size(name, elem_size)
+    }
+
+    /// Reads the first cell of the property `name`.
+    pub fn property_read_u32(&self, name: &str) -> Result<u32> {
+        self.fwnode().property_read_u32(name)
+    }
+
+    /// Reads the first 64-bit value of the property `name`.
+    pub fn property_read_u64(&self, name: &str) -> Result<u64> {
+        self.fwnode().property_read_u64(name)
+    }
+
+    /// Reads the first `out.len()` cells of the property `name`.
+    pub fn property_read_u32_array(&self, name: &str, out: &mut [u32]) -> Result {
+        self.fwnode().property_read_u32_array(name, out)
+    }
+
+    /// Reads the first `out.len()` 64-bit values of the property `name`.
+    pub fn property_read_u64_array(&self, name: &str, out: &mut [u64]) -> Result {
+        self.fwnode().property_read_u64_array(name, out)
+    }
+
+    /// Reads the first string of the property `name`.
+    pub fn property_read_string(&self, name: &str) -> Result<&'static str> {
+        self.fwnode().property_read_string(name)
+    }
+
+    /// Reads all the strings of the string list property `name`.
+    pub fn property_read_string_array(&self, name: &str) -> Result<Vec<&'static str>> {
+        self.fwnode().property_read_string_array(name)
+    }
+
+    /// Returns the index of `string` in the string list property `name`.
+    pub fn property_match_string(&self, name: &str, string: &str) -> Result<usize> {
+        self.fwnode().property_match_string(name, string)
+    }
+
+    /// Returns the available child nodes of the device.
+    pub fn child_nodes(&self) -> impl Iterator<Item = FwNode> {
+        self.fwnode().child_nodes()
+    }
+
+    /// Returns the available child node named `name`.
+    pub fn get_named_child(&self, name: &str) -> Option<FwNode> {
+        self.fwnode().get_named_child(name)
     }
 }
 
 pub trait DeviceOps {
     fn set_drv_data<T: Any + 'static>(&mut self, drv_data: T);
     fn get_drv_data<T: Any>(&self) -> Option<&T>;
+    fn clear_drv_data(&mut self);
     fn compatible_match(&self, compatible: &'static str) -> bool;
 }
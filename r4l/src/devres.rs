@@ -0,0 +1,178 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Managed device resources.
+//!
+//! C header: [`include/linux/device/devres.h`](../../../../include/linux/device/devres.h)
+//!
+//! The `devm_` helpers tie a resource to the device a driver is probing: it is released
+//! when the driver is unbound, or right away when the probe fails, so probe functions can
+//! return errors with `?` without cleaning up. Resources are released in the reverse order
+//! of their acquisition, after `DeviceRemoval::device_remove` and after the driver data is
+//! dropped.
+//!
+//! The helpers that hand out a resource return an [`Arc`], the device keeps another
+//! reference until the release. Other cleanups are registered with
+//! [`Device::devm_add_action`].
+//!
+//! # Examples
+//!
+//! ```ignore
+//! # use kernel::{devres, platform, prelude::*};
+//! fn probe(pdev: &mut platform::Device) -> Result {
+//!     let dev = pdev.device();
+//!     let _clk = devres::devm_clk_get_optional_enabled(dev, None)?;
+//!     // SAFETY: The registers of the controller belong to this driver only.
+//!     let base = unsafe { devres::devm_ioremap::<0x100>(dev, 0) }?;
+//!     // The clock is disabled again if the device is not supported.
+//!     if base.readl(0xfc) != 0x44570140 {
+//!         return Err(ENODEV);
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use crate::clk::Clk;
+use crate::device::Device;
+use crate::gpio::{Flags as GpioFlags, GpioDesc};
+use crate::io::IoMem;
+use crate::irq::{Flags as IrqFlags, Handler, Registration, ThreadedHandler, ThreadedRegistration};
+use crate::prelude::*;
+use crate::reset::ResetControl;
+use crate::sync::Arc;
+use core::fmt;
+
+/// Allocates `value`, freed once the driver is unbound from `dev` and every other
+/// reference is dropped.
+pub fn devm_alloc<T: Send + Sync + 'static>(dev: &Device, value: T) -> Result<Arc<T>> {
+    let value = Arc::new(value);
+    let held = value.clone();
+    dev.devm_add_action(move || drop(held))?;
+    Ok(value)
+}
+
+/// Maps the register window `index` of `dev`, unmapped once the driver is unbound.
+///
+/// # Safety
+///
+/// Same as [`IoMem::try_new`].
+pub unsafe fn devm_ioremap<const SIZE: usize>(
+    dev: &Device,
+    index: usize,
+) -> Result<Arc<IoMem<SIZE>>> {
+    // SAFETY: Guaranteed by the caller.
+    let base = unsafe { IoMem::<SIZE>::try_new(dev.io_resource(index)?) }?;
+    devm_alloc(dev, base)
+}
+
+/// Registers the handler `H` for `irq`, freed once the driver is unbound from `dev`.
+///
+/// See [`Registration::try_new`] for the arguments.
+pub fn devm_request_irq<H: Handler>(
+    dev: &Device,
+    irq: u32,
+    data: H::Data,
+    flags: IrqFlags,
+    name: fmt::Arguments<'_>,
+) -> Result
+where
+    H::Data: 'static,
+{
+    let registration = Registration::try_new::<H>(irq, data, flags.bits() as usize, name)?;
+    dev.devm_add_action_or_reset(move || drop(registration))
+}
+
+/// Registers the threaded handler `H` for `irq`, freed once the driver is unbound from
+/// `dev`.
+///
+/// See [`ThreadedRegistration::try_new`] for the arguments.
+pub fn devm_request_threaded_irq<H: ThreadedHandler>(
+    dev: &Device,
+    irq: u32,
+    data: H::Data,
+    flags: IrqFlags,
+    name: fmt::Arguments<'_>,
+) -> Result
+where
+    H::Data: 'static,
+{
+    let registration = ThreadedRegistration::try_new::<H>(irq, data, flags, name)?;
+    dev.devm_add_action_or_reset(move || drop(registration))
+}
+
+/// Gets the clock named `name` of `dev` and enables it, disabled once the driver is
+/// unbound.
+pub fn devm_clk_get_enabled(dev: &Device, name: Option<&str>) -> Result<Arc<Clk>> {
+    devm_enable_clk(dev, Clk::get(dev, name)?)
+}
+
+/// Like [`devm_clk_get_enabled`], but returns `None` if the device has no such clock.
+pub fn devm_clk_get_optional_enabled(dev: &Device, name: Option<&str>) -> Result<Option<Arc<Clk>>> {
+    match Clk::get_optional(dev, name)? {
+        Some(clk) => Ok(Some(devm_enable_clk(dev, clk)?)),
+        None => Ok(None),
+    }
+}
+
+fn devm_enable_clk(dev: &Device, clk: Clk) -> Result<Arc<Clk>> {
+    let clk = Arc::new(clk);
+    clk.prepare_enable()?;
+    let held = clk.clone();
+    dev.devm_add_action_or_reset(move || held.disable_unprepare())?;
+    Ok(clk)
+}
+
+/// Gets the GPIO line `con_id` of `dev`, see [`GpioDesc::get`]. The line is released once
+/// the driver is unbound and every other reference is dropped.
+pub fn devm_gpiod_get(dev: &Device, con_id: &str, flags: GpioFlags) -> Result<Arc<GpioDesc>> {
+    devm_alloc(dev, GpioDesc::get(dev, con_id, flags)?)
+}
+
+/// Like [`devm_gpiod_get`], but returns `None` if the device has no such line.
+pub fn devm_gpiod_get_optional(
+    dev: &Device,
+    con_id: &str,
+    flags: GpioFlags,
+) -> Result<Option<Arc<GpioDesc>>> {
+    match GpioDesc::get_optional(dev, con_id, flags)? {
+        Some(desc) => Ok(Some(devm_alloc(dev, desc)?)),
+        None => Ok(None),
+    }
+}
+
+/// Gets an exclusive control of the reset line `name` of `dev`, see
+/// [`ResetControl::get_exclusive`]. The control is released once the driver is unbound and
+/// every other reference is dropped.
+pub fn devm_reset_control_get_exclusive(dev: &Device, name: Option<&str>) -> Result<Arc<ResetControl>> {
+    devm_alloc(dev, ResetControl::get_exclusive(dev, name)?)
+}
+
+/// Gets a shared control of the reset line `name` of `dev`, see
+/// [`ResetControl::get_shared`]. The control is released once the driver is unbound and
+/// every other reference is dropped.
+pub fn devm_reset_control_get_shared(dev: &Device, name: Option<&str>) -> Result<Arc<ResetControl>> {
+    devm_alloc(dev, ResetControl::get_shared(dev, name)?)
+}
+
+/// Like [`devm_reset_control_get_exclusive`], but returns `None` if the device has no such
+/// reset line.
+pub fn devm_reset_control_get_optional_exclusive(
+    dev: &Device,
+    name: Option<&str>,
+) -> Result<Option<Arc<ResetControl>>> {
+    match ResetControl::get_optional_exclusive(dev, name)? {
+        Some(rstc) => Ok(Some(devm_alloc(dev, rstc)?)),
+        None => Ok(None),
+    }
+}
+
+/// Like [`devm_reset_control_get_shared`], but returns `None` if the device has no such
+/// reset line.
+pub fn devm_reset_control_get_optional_shared(
+    dev: &Device,
+    name: Option<&str>,
+) -> Result<Option<Arc<ResetControl>>> {
+    match ResetControl::get_optional_shared(dev, name)? {
+        Some(rstc) => Ok(Some(devm_alloc(dev, rstc)?)),
+        None => Ok(None),
+    }
+}
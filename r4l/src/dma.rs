@@ -0,0 +1,225 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Direct memory access (DMA).
+//!
+//! C header: [`include/linux/dma-mapping.h`](../../../../include/linux/dma-mapping.h)
+//!
+//! A device reaches memory through DMA addresses, which may differ from the CPU physical
+//! addresses (see `dma-ranges`) and are limited by the DMA mask of the device. Drivers use
+//! [`CoherentAllocation`] for buffers shared with the device for a long time, like
+//! descriptor rings, and [`map_single`] to hand an existing buffer to the device for one
+//! transfer.
+
+use crate::device::Device;
+use crate::error::{code::*, Result};
+use crate::os::{Os, OsInterface};
+use core::marker::PhantomData;
+
+/// An address as seen by a device doing DMA.
+pub type DmaAddr = u64;
+
+/// Returns the DMA mask of a device reaching the first `n` bits of the address space.
+pub const fn dma_bit_mask(n: u32) -> u64 {
+    if n >= 64 {
+        u64::MAX
+    } else {
+        (1 << n) - 1
+    }
+}
+
+/// The direction of the data of a DMA transfer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DataDirection {
+    /// The device reads and writes the buffer.
+    Bidirectional,
+    /// The device reads the buffer.
+    ToDevice,
+    /// The device writes the buffer.
+    FromDevice,
+    /// No data is transferred.
+    None,
+}
+
+// Returns true if `[dma_addr, dma_addr + size)` is reachable by `dev`.
+fn dma_capable(dev: &Device, dma_addr: DmaAddr, size: usize) -> bool {
+    match dma_addr.checked_add(size as u64) {
+        Some(end) => size == 0 || end - 1 <= dev.dma_mask(),
+        None => false,
+    }
+}
+
+/// An array of `T` allocated for DMA, reachable by both the CPU and the device.
+///
+/// The memory is zeroed and freed on drop. The device may access it at any time, so the
+/// CPU only accesses it through volatile [`CoherentAllocation::read`] and
+/// [`CoherentAllocation::write`].
+///
+/// # Examples
+///
+/// ```ignore
+/// # use kernel::{dma::CoherentAllocation, platform, prelude::*};
+/// fn probe(pdev: &mut platform::Device) -> Result {
+///     let ring = CoherentAllocation::<u64>::alloc_coherent(pdev.device(), 256)?;
+///     ring.write(0, 0x1);
+///     // Program `ring.dma_handle()` in the device.
+///     Ok(())
+/// }
+/// ```
+pub struct CoherentAllocation<T> {
+    vaddr: usize,
+    dma_handle: DmaAddr,
+    count: usize,
+    _p: PhantomData<T>,
+}
+
+impl<T: Copy> CoherentAllocation<T> {
+    /// Allocates an array of `count` elements for `dev`.
+    ///
+    /// Fails with [`ENOMEM`] if the memory is not reachable by the device, and with
+    /// [`ENOTSUPP`] if the OS cannot give uncached memory to a device that is not coherent:
+    /// such devices then use [`map_single`].
+    pub fn alloc_coherent(dev: &Device, count: usize) -> Result<Self> {
+        let size = core::mem::size_of::<T>()
+            .checked_mul(count)
+            .ok_or(EOVERFLOW)?;
+        if size == 0 {
+            return Err(EINVAL);
+        }
+        let (vaddr, paddr) = Os::dma_alloc_coherent(size, dev.dma_coherent())?;
+        let dma_handle = match dev.phys_to_dma(paddr) {
+            Ok(dma_handle) if dma_capable(dev, dma_handle, size) => dma_handle,
+            Ok(_) => {
+                Os::dma_free_coherent(vaddr, size);
+                return Err(ENOMEM);
+            }
+            Err(e) => {
+                Os::dma_free_coherent(vaddr, size);
+                return Err(e);
+            }
+        };
+        Ok(Self {
+            vaddr,
+            dma_handle,
+            count,
+            _p: PhantomData,
+        })
+    }
+
+    /// Returns the address of the array for the device.
+    pub fn dma_handle(&self) -> DmaAddr {
+        self.dma_handle
+    }
+
+    /// Returns the number of elements of the array.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Returns the element at `index`, or [`EINVAL`] if it is out of bounds.
+    pub fn read(&self, index: usize) -> Result<T> {
+        if index >= self.count {
+            return Err(EINVAL);
+        }
+        // SAFETY: The index is checked above, the array is allocated and aligned for `T`.
+        Ok(unsafe { core::ptr::read_volatile((self.vaddr as *const T).add(index)) })
+    }
+
+    /// Sets the element at `index`, or returns [`EINVAL`] if it is out of bounds.
+    pub fn write(&self, index: usize, value: T) -> Result {
+        if index >= self.count {
+            return Err(EINVAL);
+        }
+        // SAFETY: The index is checked above, the array is allocated and aligned for `T`.
+        unsafe { core::ptr::write_volatile((self.vaddr as *mut T).add(index), value) };
+        Ok(())
+    }
+}
+
+impl<T> Drop for CoherentAllocation<T> {
+    fn drop(&mut self) {
+        Os::dma_free_coherent(self.vaddr, core::mem::size_of::<T>() * self.count);
+    }
+}
+
+// SAFETY: The allocation is only accessed through volatile accesses of `T`.
+unsafe impl<T: Send> Send for CoherentAllocation<T> {}
+// SAFETY: The allocation is only accessed through volatile accesses of `T`.
+unsafe impl<T: Send> Sync for CoherentAllocation<T> {}
+
+/// Maps the `size` bytes at `cpu_addr` for a transfer of `dev` in direction `dir`, returns
+/// the address of the buffer for the device.
+///
+/// Fails with [`EIO`] if the buffer is not reachable by the device.
+///
+/// # Safety
+///
+/// The buffer must be in the linear mapping and stay valid until [`unmap_single`]. The CPU
+/// must not access it before [`unmap_single`] or [`sync_single_for_cpu`].
+pub unsafe fn map_single(
+    dev: &Device,
+    cpu_addr: *mut u8,
+    size: usize,
+    dir: DataDirection,
+) -> Result<DmaAddr> {
+    let paddr = Os::virt_to_phys(cpu_addr as usize);
+    let dma_addr = dev.phys_to_dma(paddr)?;
+    if !dma_capable(dev, dma_addr, size) {
+        return Err(EIO);
+    }
+    if !dev.dma_coherent() {
+        Os::dma_sync_for_device(cpu_addr as usize, size, dir);
+    }
+    Ok(dma_addr)
+}
+
+/// Unmaps a buffer mapped by [`map_single`], the CPU sees what the device wrote.
+///
+/// # Safety
+///
+/// `dma_addr`, `size` and `dir` must be the ones of a call to [`map_single`], and the
+/// device must be done with the buffer.
+pub unsafe fn unmap_single(dev: &Device, dma_addr: DmaAddr, size: usize, dir: DataDirection) {
+    // SAFETY: Guaranteed by the caller.
+    unsafe { sync_single_for_cpu(dev, dma_addr, size, dir) }
+}
+
+/// Gives a buffer mapped by [`map_single`] back to the CPU, until
+/// [`sync_single_for_device`].
+///
+/// # Safety
+///
+/// `dma_addr`, `size` and `dir` must be the ones of a call to [`map_single`], and the
+/// device must be done with the buffer.
+pub unsafe fn sync_single_for_cpu(
+    dev: &Device,
+    dma_addr: DmaAddr,
+    size: usize,
+    dir: DataDirection,
+) {
+    if dev.dma_coherent() {
+        return;
+    }
+    if let Ok(paddr) = dev.dma_to_phys(dma_addr) {
+        Os::dma_sync_for_cpu(Os::phys_to_virt(paddr), size, dir);
+    }
+}
+
+/// Gives a buffer back to the device after [`sync_single_for_cpu`].
+///
+/// # Safety
+///
+/// `dma_addr`, `size` and `dir` must be the ones of a call to [`map_single`], and the CPU
+/// must not access the buffer until [`unmap_single`] or [`sync_single_for_cpu`].
+pub unsafe fn sync_single_for_device(
+    dev: &Device,
+    dma_addr: DmaAddr,
+    size: usize,
+    dir: DataDirection,
+) {
+    if dev.dma_coherent() {
+        return;
+    }
+    if let Ok(paddr) = dev.dma_to_phys(dma_addr) {
+        Os::dma_sync_for_device(Os::phys_to_virt(paddr), size, dir);
+    }
+}
@@ -0,0 +1,509 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! DMA engine, slave channels of DMA controllers.
+//!
+//! C header: [`include/linux/dmaengine.h`](../../../../include/linux/dmaengine.h)
+//!
+//! DMA controller drivers implement [`DmaControllerOps`] and register it for their device
+//! tree node with [`dma_controller_register`]. Clients request a [`DmaChan`] from the
+//! `dmas`/`dma-names` properties of their device, then:
+//!
+//! 1. describe the device side of their transfers with [`DmaChan::slave_config`],
+//! 2. prepare a transfer with [`DmaChan::prep_slave_sg`] and [`TxDescriptor::submit`] it,
+//! 3. start the submitted transfers with [`DmaChan::issue_pending`].
+//!
+//! A channel runs its transfers one at a time, in submission order. The controller driver
+//! reports the end of each transfer with [`dma_chan_complete`], usually from its interrupt
+//! handler; the framework then calls the completion callback of the transfer and starts the
+//! next one.
+
+use crate::device::Device;
+use crate::dma::DmaAddr;
+use crate::of::{of_get_phandle, of_node_name, of_property_match_string, parse_phandle_with_args};
+use crate::prelude::*;
+use crate::sync::{Arc, SpinNoIrq};
+use alloc::collections::VecDeque;
+use of::OfNode;
+
+/// Identifies a submitted transfer on its channel, positive and increasing.
+pub type Cookie = i32;
+
+/// Called with the outcome of a transfer once it is done, usually in interrupt context.
+pub type DmaCallback = Box<dyn FnOnce(Result) + Send>;
+
+/// The direction of a slave transfer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransferDirection {
+    /// From memory to the device.
+    MemToDev,
+    /// From the device to memory.
+    DevToMem,
+}
+
+/// The state of a submitted transfer, see [`DmaChan::tx_status`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DmaStatus {
+    /// The transfer is done, or was terminated.
+    Complete,
+    /// The transfer is queued or running.
+    InProgress,
+}
+
+/// The device side of the transfers of a channel.
+///
+/// Only the fields of the direction of the transfer are used: `dst_*` for
+/// [`TransferDirection::MemToDev`], `src_*` for [`TransferDirection::DevToMem`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DmaSlaveConfig {
+    /// The address the device reads data from, usually a FIFO register.
+    pub src_addr: DmaAddr,
+    /// The address the device writes data to, usually a FIFO register.
+    pub dst_addr: DmaAddr,
+    /// The width in bytes of the accesses to `src_addr`.
+    pub src_addr_width: u32,
+    /// The width in bytes of the accesses to `dst_addr`.
+    pub dst_addr_width: u32,
+    /// The number of `src_addr_width` words read in one burst.
+    pub src_maxburst: u32,
+    /// The number of `dst_addr_width` words written in one burst.
+    pub dst_maxburst: u32,
+}
+
+/// A contiguous memory segment of a transfer, as returned by [`crate::dma::map_single`].
+#[derive(Clone, Copy, Debug)]
+pub struct SgEntry {
+    /// The address of the segment for the device.
+    pub dma_addr: DmaAddr,
+    /// The length of the segment in bytes.
+    pub len: usize,
+}
+
+/// A slave transfer, handed to the controller driver to be run.
+pub struct SlaveTransfer {
+    /// The cookie of the transfer.
+    pub cookie: Cookie,
+    /// The memory segments, transferred in order.
+    pub sgl: Vec<SgEntry>,
+    /// The direction of the transfer.
+    pub direction: TransferDirection,
+}
+
+/// The operations of a DMA controller, on the channel `chan`.
+///
+/// They are called without any lock of the framework held: they may call back into it, for
+/// instance [`dma_chan_complete`] from [`DmaControllerOps::start_transfer`].
+pub trait DmaControllerOps: Send + Sync {
+    /// Returns the number of channels of the controller.
+    fn nr_channels(&self) -> u32;
+
+    /// Prepares the channel for a new client, must not sleep.
+    fn alloc_chan_resources(&self, _chan: u32) -> Result {
+        Ok(())
+    }
+
+    /// Releases the channel once its client is gone, must not sleep.
+    fn free_chan_resources(&self, _chan: u32) {}
+
+    /// Sets the device side of the next transfers of the channel, must not sleep.
+    fn config(&self, chan: u32, config: &DmaSlaveConfig) -> Result;
+
+    /// Returns an error if the controller cannot run the transfer, called when it is
+    /// prepared. Must not sleep.
+    fn check_transfer(&self, _chan: u32, _tx: &SlaveTransfer) -> Result {
+        Ok(())
+    }
+
+    /// Starts the transfer on the idle channel, must not sleep.
+    ///
+    /// The driver reports its end with [`dma_chan_complete`].
+    fn start_transfer(&self, chan: u32, tx: &SlaveTransfer) -> Result;
+
+    /// Stops the running transfer of the channel, must not sleep.
+    fn terminate_all(&self, chan: u32) -> Result;
+
+    /// Translates the argument cells of a `dmas` entry to the channel.
+    ///
+    /// The default handles the usual one cell specifier.
+    fn xlate(&self, args: &[u32]) -> Result<u32> {
+        match args {
+            [chan, ..] if *chan < self.nr_channels() => Ok(*chan),
+            _ => Err(EINVAL),
+        }
+    }
+}
+
+// A transfer and its completion callback.
+struct Desc {
+    // Shared with the controller driver while the transfer starts.
+    tx: Arc<SlaveTransfer>,
+    callback: Option<DmaCallback>,
+}
+
+// A channel with a client.
+struct Channel {
+    id: u32,
+    last_cookie: Cookie,
+    completed_cookie: Cookie,
+    // Submitted, not yet issued.
+    submitted: VecDeque<Desc>,
+    // Issued, waiting for the channel.
+    issued: VecDeque<Desc>,
+    active: Option<Desc>,
+}
+
+// The operations of a controller are called without any lock of the framework held, so that
+// they may complete a transfer right away. The state of the channels is under the lock of
+// their controller.
+struct DmaController {
+    phandle: u32,
+    ops: Arc<dyn DmaControllerOps>,
+    // Completions come from interrupt handlers.
+    chans: SpinNoIrq<Vec<Channel>>,
+}
+
+impl DmaController {
+    // Runs `f` on the channel `id`, under the lock of the controller.
+    fn with_chan<R>(&self, id: u32, f: impl FnOnce(&mut Channel) -> R) -> Result<R> {
+        let mut chans = self.chans.lock();
+        let chan = chans.iter_mut().find(|chan| chan.id == id).ok_or(ENODEV)?;
+        Ok(f(chan))
+    }
+
+    // Starts the next issued transfer of the channel `id` if it is idle. A transfer that
+    // fails to start is completed with the error.
+    fn start_next(&self, id: u32) {
+        loop {
+            // The transfer is active before it starts: the driver may complete it from
+            // `start_transfer`.
+            let next = self.with_chan(id, |chan| {
+                if chan.active.is_some() {
+                    return None;
+                }
+                let desc = chan.issued.pop_front()?;
+                let tx = desc.tx.clone();
+                chan.active = Some(desc);
+                Some(tx)
+            });
+            let Ok(Some(tx)) = next else {
+                return;
+            };
+            let Err(e) = self.ops.start_transfer(id, &tx) else {
+                return;
+            };
+            pr_err!("dma channel {}: cannot start transfer: {:?}", id, e);
+            // The transfer may have been terminated meanwhile.
+            let failed = self.with_chan(id, |chan| {
+                match &chan.active {
+                    Some(desc) if Arc::ptr_eq(&desc.tx, &tx) => (),
+                    _ => return None,
+                }
+                chan.completed_cookie = tx.cookie;
+                chan.active.take()
+            });
+            if let Ok(Some(Desc {
+                callback: Some(callback),
+                ..
+            })) = failed
+            {
+                callback(Err(e));
+            }
+        }
+    }
+}
+
+static DMA_CONTROLLERS: SpinNoIrq<Vec<Arc<DmaController>>> = SpinNoIrq::new(Vec::new());
+
+// Returns the registered controller `phandle`.
+fn dma_controller_get(phandle: u32) -> Option<Arc<DmaController>> {
+    DMA_CONTROLLERS
+        .lock()
+        .iter()
+        .find(|dma| dma.phandle == phandle)
+        .cloned()
+}
+
+/// Registers `ops` as the DMA controller of `node`.
+pub fn dma_controller_register(node: OfNode<'static>, ops: Arc<dyn DmaControllerOps>) -> Result {
+    let phandle = of_get_phandle(node).ok_or(EINVAL)?;
+    let mut controllers = DMA_CONTROLLERS.lock();
+    if controllers.iter().any(|dma| dma.phandle == phandle) {
+        return Err(EEXIST);
+    }
+    controllers.try_reserve(1)?;
+    controllers.push(Arc::new(DmaController {
+        phandle,
+        ops,
+        chans: SpinNoIrq::new(Vec::new()),
+    }));
+    pr_debug!("dma controller {} registered", of_node_name(node));
+    Ok(())
+}
+
+/// Removes the DMA controller of `node`.
+///
+/// Fails with [`EBUSY`] while clients still hold some of its channels.
+pub fn dma_controller_unregister(node: OfNode<'static>) -> Result {
+    let phandle = of_get_phandle(node).ok_or(EINVAL)?;
+    let mut controllers = DMA_CONTROLLERS.lock();
+    let index = controllers
+        .iter()
+        .position(|dma| dma.phandle == phandle)
+        .ok_or(ENODEV)?;
+    if !controllers[index].chans.lock().is_empty() {
+        return Err(EBUSY);
+    }
+    controllers.remove(index);
+    Ok(())
+}
+
+/// Reports the end of the running transfer of the channel `chan` of the controller of
+/// `node`, with its outcome, then starts the next issued transfer.
+///
+/// Called by controller drivers, usually from their interrupt handler, possibly from
+/// [`DmaControllerOps::start_transfer`] for a transfer done right away.
+pub fn dma_chan_complete(node: OfNode<'static>, chan: u32, result: Result) {
+    let dma = of_get_phandle(node).and_then(dma_controller_get);
+    let done = dma.as_ref().ok_or(ENODEV).and_then(|dma| {
+        dma.with_chan(chan, |chan| {
+            // The transfer may have been terminated meanwhile.
+            let desc = chan.active.take()?;
+            chan.completed_cookie = desc.tx.cookie;
+            Some(desc)
+        })
+    });
+    let Ok(desc) = done else {
+        pr_warn!(
+            "{}: completion on unknown dma channel {}",
+            of_node_name(node),
+            chan
+        );
+        return;
+    };
+    if let Some(callback) = desc.and_then(|desc| desc.callback) {
+        callback(result);
+    }
+    if let Some(dma) = dma {
+        dma.start_next(chan);
+    }
+}
+
+/// A DMA channel used by a client device.
+///
+/// Dropping the channel terminates its transfers and releases it.
+///
+/// # Examples
+///
+/// ```ignore
+/// # use kernel::{dma, dmaengine::*, platform, prelude::*};
+/// fn start_tx(pdev: &mut platform::Device, buf: &mut [u8], fifo: dma::DmaAddr) -> Result {
+///     let chan = DmaChan::request(pdev.device(), "tx")?;
+///     chan.slave_config(&DmaSlaveConfig {
+///         dst_addr: fifo,
+///         dst_addr_width: 4,
+///         dst_maxburst: 8,
+///         ..Default::default()
+///     })?;
+///     // SAFETY: `buf` outlives the transfer and is not accessed until its end.
+///     let dma_addr = unsafe {
+///         dma::map_single(pdev.device(), buf.as_mut_ptr(), buf.len(), dma::DataDirection::ToDevice)
+///     }?;
+///     let sgl = [SgEntry { dma_addr, len: buf.len() }];
+///     let tx = chan.prep_slave_sg(&sgl, TransferDirection::MemToDev, Some(Box::new(|ret| {
+///         pr_info!("tx done: {:?}", ret);
+///     })))?;
+///     tx.submit()?;
+///     chan.issue_pending();
+///     Ok(())
+/// }
+/// ```
+pub struct DmaChan {
+    phandle: u32,
+    id: u32,
+}
+
+impl DmaChan {
+    /// Requests the DMA channel named `name` of `dev`.
+    ///
+    /// Fails with [`ENOENT`] if the device has no such channel, [`EPROBE_DEFER`] if its
+    /// controller is not registered yet and [`EBUSY`] if another client holds it.
+    pub fn request(dev: &Device, name: &str) -> Result<Self> {
+        let node = dev.of_node();
+        let index = of_property_match_string(node, "dma-names", name).ok_or(ENOENT)?;
+        let spec = parse_phandle_with_args(node, "dmas", "#dma-cells", index)?;
+        let phandle = of_get_phandle(spec.np).ok_or(EINVAL)?;
+
+        let dma = dma_controller_get(phandle).ok_or(EPROBE_DEFER)?;
+        let id = dma.ops.xlate(spec.args())?;
+        {
+            // Claim the channel before its resources are allocated, unlocked.
+            let mut chans = dma.chans.lock();
+            if chans.iter().any(|chan| chan.id == id) {
+                return Err(EBUSY);
+            }
+            chans.try_reserve(1)?;
+            chans.push(Channel {
+                id,
+                last_cookie: 0,
+                completed_cookie: 0,
+                submitted: VecDeque::new(),
+                issued: VecDeque::new(),
+                active: None,
+            });
+        }
+        if let Err(e) = dma.ops.alloc_chan_resources(id) {
+            dma.chans.lock().retain(|chan| chan.id != id);
+            return Err(e);
+        }
+        Ok(Self { phandle, id })
+    }
+
+    /// Like [`DmaChan::request`], but returns `None` if the device has no such channel.
+    pub fn request_optional(dev: &Device, name: &str) -> Result<Option<Self>> {
+        match Self::request(dev, name) {
+            Ok(chan) => Ok(Some(chan)),
+            Err(ENOENT) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    // The controller cannot go away while the channel is held.
+    fn controller(&self) -> Result<Arc<DmaController>> {
+        dma_controller_get(self.phandle).ok_or(ENODEV)
+    }
+
+    /// Sets the device side of the next transfers.
+    pub fn slave_config(&self, config: &DmaSlaveConfig) -> Result {
+        self.controller()?.ops.config(self.id, config)
+    }
+
+    /// Prepares a transfer of the segments `sgl` in direction `direction`, `callback` is
+    /// called once it is done.
+    ///
+    /// The transfer only runs once submitted and issued.
+    pub fn prep_slave_sg(
+        &self,
+        sgl: &[SgEntry],
+        direction: TransferDirection,
+        callback: Option<DmaCallback>,
+    ) -> Result<TxDescriptor<'_>> {
+        if sgl.is_empty() || sgl.iter().any(|sg| sg.len == 0) {
+            return Err(EINVAL);
+        }
+        let mut entries = Vec::new();
+        entries.try_reserve(sgl.len())?;
+        entries.extend_from_slice(sgl);
+        let tx = SlaveTransfer {
+            cookie: 0,
+            sgl: entries,
+            direction,
+        };
+        self.controller()?.ops.check_transfer(self.id, &tx)?;
+        Ok(TxDescriptor {
+            chan: self,
+            tx,
+            callback,
+        })
+    }
+
+    /// Starts the submitted transfers.
+    pub fn issue_pending(&self) {
+        let ret = self.controller().and_then(|dma| {
+            dma.with_chan(self.id, |chan| {
+                let mut submitted = core::mem::take(&mut chan.submitted);
+                chan.issued.append(&mut submitted);
+            })?;
+            dma.start_next(self.id);
+            Ok(())
+        });
+        if let Err(e) = ret {
+            pr_warn!("dma channel {}: issue_pending failed: {:?}", self.id, e);
+        }
+    }
+
+    /// Returns the state of the transfer submitted as `cookie`.
+    pub fn tx_status(&self, cookie: Cookie) -> Result<DmaStatus> {
+        self.controller()?.with_chan(self.id, |chan| {
+            cookie_status(cookie, chan.completed_cookie, chan.last_cookie)
+        })
+    }
+
+    /// Stops the running transfer and drops the queued ones, their callbacks are not
+    /// called and their cookies read as complete.
+    pub fn terminate_sync(&self) -> Result {
+        let dma = self.controller()?;
+        let ret = dma.ops.terminate_all(self.id);
+        // Dropped unlocked, like the callbacks.
+        let dropped = dma.with_chan(self.id, |chan| {
+            chan.completed_cookie = chan.last_cookie;
+            (
+                chan.active.take(),
+                core::mem::take(&mut chan.issued),
+                core::mem::take(&mut chan.submitted),
+            )
+        })?;
+        drop(dropped);
+        ret
+    }
+}
+
+impl Drop for DmaChan {
+    fn drop(&mut self) {
+        if let Err(e) = self.terminate_sync() {
+            pr_warn!("dma channel {}: terminate failed: {:?}", self.id, e);
+        }
+        if let Some(dma) = dma_controller_get(self.phandle) {
+            dma.ops.free_chan_resources(self.id);
+            dma.chans.lock().retain(|chan| chan.id != self.id);
+        }
+    }
+}
+
+// Cookies wrap from `Cookie::MAX` to 1: those in `(completed, last]` are in progress.
+fn cookie_status(cookie: Cookie, completed: Cookie, last: Cookie) -> DmaStatus {
+    let in_progress = if completed <= last {
+        cookie > completed && cookie <= last
+    } else {
+        cookie > completed || cookie <= last
+    };
+    if in_progress {
+        DmaStatus::InProgress
+    } else {
+        DmaStatus::Complete
+    }
+}
+
+/// A prepared transfer, see [`DmaChan::prep_slave_sg`].
+///
+/// Dropping it without submitting it drops the transfer.
+pub struct TxDescriptor<'a> {
+    chan: &'a DmaChan,
+    tx: SlaveTransfer,
+    callback: Option<DmaCallback>,
+}
+
+impl TxDescriptor<'_> {
+    /// Queues the transfer on its channel, returns its cookie.
+    ///
+    /// The transfer starts after the next [`DmaChan::issue_pending`].
+    pub fn submit(self) -> Result<Cookie> {
+        let Self {
+            chan,
+            mut tx,
+            callback,
+        } = self;
+        let dma = chan.controller()?;
+        dma.with_chan(chan.id, |chan| {
+            chan.submitted.try_reserve(1)?;
+            chan.last_cookie = match chan.last_cookie {
+                Cookie::MAX => 1,
+                cookie => cookie + 1,
+            };
+            tx.cookie = chan.last_cookie;
+            chan.submitted.push_back(Desc {
+                tx: Arc::new(tx),
+                callback,
+            });
+            Ok(chan.last_cookie)
+        })?
+    }
+}
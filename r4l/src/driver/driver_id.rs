@@ -16,6 +16,11 @@ impl<T, U, const N: usize> IdArray<T, U, N> {
         &self.ids
     }
 
+    /// Returns the context data of every id, in the order of [`IdArray::as_table`].
+    pub const fn id_infos(&'static self) -> &'static [Option<U>] {
+        &self.id_infos
+    }
+
     /// Returns the number of items in the ID table.
     pub const fn count(&self) -> usize {
         self.ids.len()
@@ -213,6 +218,8 @@ macro_rules! driver_id_table {
     ($table_name:ident, $id_type:ty, $data_type:ty, $target:expr) => {
         const OF_DEVICE_ID_TABLE_SIZE: usize = $target.count();
         const $table_name: Option<&'static [$id_type]> = Some($target.as_table());
+        const OF_DEVICE_ID_INFO_TABLE: Option<&'static [Option<$data_type>]> =
+            Some($target.id_infos());
     };
 }
 
@@ -1,81 +1,304 @@
 //! Defines the R4L error type.
 //!
-//! Every OS should provides:
-//! - An Error type
-//! - Errno: const variable in mod code
+//! Errors are Linux errnos, so error paths of drivers ported from Linux keep working. Each OS
+//! converts them from and to its own error type in its [`OsInterface`] implementation.
 //!
+//! # Fallback mapping
+//!
+//! An OS error type usually has far fewer variants than Linux has errnos. A backend converting
+//! an [`Error`] with no exact native equivalent maps it to the closest category, and to its
+//! generic I/O error when there is none:
+//!
+//! | errnos                                            | category          |
+//! |---------------------------------------------------|-------------------|
+//! | `EPERM`, `EACCES`, `EROFS`                        | permission denied |
+//! | `ENOENT`, `ESRCH`, `ENXIO`, `ENODEV`, `ENODATA`   | not found         |
+//! | `EINTR`, `ERESTART*`                              | interrupted       |
+//! | `EAGAIN`, `EPROBE_DEFER`, `EINPROGRESS`           | try again         |
+//! | `ENOMEM`, `ENOBUFS`                               | out of memory     |
+//! | `EBUSY`, `ETXTBSY`, `EALREADY`                    | busy              |
+//! | `EINVAL`, `EDOM`, `ERANGE`, `EOVERFLOW`, `EILSEQ` | invalid input     |
+//! | `ENOSPC`, `EFBIG`, `EDQUOT`                       | storage full      |
+//! | `ENOSYS`, `ENOTTY`, `EOPNOTSUPP`, `ENOTSUPP`      | unsupported       |
+//! | everything else                                   | I/O error         |
+//!
+//! The conversion back is exact for the native variants, so an errno only survives a round
+//! trip through the native type when it is the canonical errno of its category.
+//! [`Error::from_errno`] and [`Error::to_errno`] on the other hand always round trip.
 
-#[cfg(feature = "starry")]
-mod error {
-    use axerrno::AxError;
-    pub type Error = AxError;
+use crate::os::{Os, OsInterface};
+use alloc::collections::TryReserveError;
+use core::alloc::LayoutError;
+use core::fmt;
+use core::num::TryFromIntError;
+use core::str::Utf8Error;
 
-    pub mod code {
-        macro_rules! declare_err {
-            ($err:ident, $doc:expr, $var_b:ident) => {
-                #[doc = $doc]
-                pub const $err: super::Error = super::Error::$var_b;
-            };
+/// The maximum errno value, the same as Linux `MAX_ERRNO`.
+const MAX_ERRNO: i32 = 4095;
+
+/// Generic kernel error.
+///
+/// The value is always a negative Linux errno, in `-MAX_ERRNO..0`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Error(i32);
+
+impl Error {
+    /// Creates an [`Error`] from a negative errno.
+    ///
+    /// Values that are not an errno are reported and turned into [`code::EINVAL`].
+    pub fn from_errno(errno: i32) -> Error {
+        if !(-MAX_ERRNO..0).contains(&errno) {
+            crate::pr_warn!("attempted to create Error with out of range errno: {}", errno);
+            return code::EINVAL;
+        }
+        Error(errno)
+    }
+
+    /// Returns the error as a negative errno.
+    ///
+    /// `Error::from_errno(e.to_errno()) == e` for every error.
+    pub fn to_errno(self) -> i32 {
+        self.0
+    }
+
+    /// Returns the symbolic name of the error, e.g. `"EINVAL"`.
+    ///
+    /// Returns `None` for errnos Linux does not define.
+    pub fn name(&self) -> Option<&'static str> {
+        code::name(self.0)
+    }
+
+    /// Converts the error to the native error type of the OS.
+    pub fn to_native(self) -> <Os as OsInterface>::NativeError {
+        Os::error_to_native(self)
+    }
+
+    /// Converts a native error of the OS to an [`Error`].
+    pub fn from_native(err: <Os as OsInterface>::NativeError) -> Error {
+        Os::error_from_native(err)
+    }
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => f.write_str(name),
+            None => f.debug_tuple("Error").field(&-self.0).finish(),
         }
+    }
+}
 
-        declare_err!(EPERM, "Operation not permitted.", PermissionDenied);
-        declare_err!(ENOENT, "No such file or directory.", NotFound);
-        declare_err!(ESRCH, "No such process.", NotFound);
-        declare_err!(EINTR, "Interrupted system call.", Interrupted);
-        declare_err!(EIO, "I/O error.", Io);
-        //declare_err!(ENXIO, "No such device or address.");
-        //declare_err!(E2BIG, "Argument list too long.");
-        //declare_err!(ENOEXEC, "Exec format error.");
-        //declare_err!(EBADF, "Bad file number.");
-        //declare_err!(ECHILD, "No child processes.");
-        declare_err!(EAGAIN, "Try again.", Again);
-        declare_err!(ENOMEM, "Out of memory.", NoMemory);
-        declare_err!(EACCES, "Permission denied.", PermissionDenied);
-        declare_err!(EFAULT, "Bad address.", BadState);
-        //declare_err!(ENOTBLK, "Block device required.");
-        declare_err!(EBUSY, "Device or resource busy.", Busy);
-        declare_err!(EEXIST, "File exists.", AlreadyExists);
-        //declare_err!(EXDEV, "Cross-device link.");
-        //declare_err!(ENODEV, "No such device.");
-        declare_err!(ENOTDIR, "Not a directory.", NotADirectory);
-        //declare_err!(EISDIR, "Is a directory.");
-        declare_err!(EINVAL, "Invalid argument.", InvalidInput);
-        //declare_err!(ENFILE, "File table overflow.");
-        //declare_err!(EMFILE, "Too many open files.");
-        //declare_err!(ENOTTY, "Not a typewriter.");
-        //declare_err!(ETXTBSY, "Text file busy.");
-        //declare_err!(EFBIG, "File too large.");
-        declare_err!(ENOSPC, "No space left on device.", StorageFull);
-        //declare_err!(ESPIPE, "Illegal seek.");
-        //declare_err!(EROFS, "Read-only file system.");
-        //declare_err!(EMLINK, "Too many links.");
-        //declare_err!(EPIPE, "Broken pipe.");
-        //declare_err!(EDOM, "Math argument out of domain of func.");
-        //declare_err!(ERANGE, "Math result not representable.");
-        //declare_err!(ERESTARTSYS, "Restart the system call.");
-        //declare_err!(ERESTARTNOINTR, "System call was interrupted by a signal and will be restarted.");
-        //declare_err!(ERESTARTNOHAND, "Restart if no handler.");
-        //declare_err!(ENOIOCTLCMD, "No ioctl command.");
-        //declare_err!(ERESTART_RESTARTBLOCK, "Restart by calling sys_restart_syscall.");
-        //declare_err!(EPROBE_DEFER, "Driver requests probe retry.");
-        //declare_err!(EOPENSTALE, "Open found a stale dentry.");
-        //declare_err!(ENOPARAM, "Parameter not supported.");
-        //declare_err!(EBADHANDLE, "Illegal NFS file handle.");
-        //declare_err!(ENOTSYNC, "Update synchronization mismatch.");
-        //declare_err!(EBADCOOKIE, "Cookie is stale.");
-        declare_err!(ENOTSUPP, "Operation is not supported.", Unsupported);
-        // declare_err!(ETOOSMALL, "Buffer or request is too small.");
-        //declare_err!(ESERVERFAULT, "An untranslatable error occurred.");
-        // declare_err!(EBADTYPE, "Type not supported by server.");
-        //declare_err!(EJUKEBOX, "Request initiated, but will not complete before timeout.");
-        //declare_err!(EIOCBQUEUED, "iocb queued, will get completion event.");
-        //declare_err!(ERECALLCONFLICT, "Conflict with recalled state.");
-        //declare_err!(ENOGRACE, "NFS file lock reclaim refused.", );
+impl From<TryFromIntError> for Error {
+    fn from(_: TryFromIntError) -> Error {
+        code::EINVAL
     }
 }
 
-pub use self::error::code;
-pub use self::error::Error;
+impl From<Utf8Error> for Error {
+    fn from(_: Utf8Error) -> Error {
+        code::EINVAL
+    }
+}
+
+impl From<LayoutError> for Error {
+    fn from(_: LayoutError) -> Error {
+        code::ENOMEM
+    }
+}
+
+impl From<TryReserveError> for Error {
+    fn from(_: TryReserveError) -> Error {
+        code::ENOMEM
+    }
+}
+
+impl From<fmt::Error> for Error {
+    fn from(_: fmt::Error) -> Error {
+        code::EINVAL
+    }
+}
+
+/// Linux errno constants.
+///
+/// The numbers are the ones of `include/uapi/asm-generic/errno*.h` and `include/linux/errno.h`.
+pub mod code {
+    macro_rules! declare_err {
+        ($($err:ident, $errno:literal, $doc:literal;)*) => {
+            $(
+                #[doc = $doc]
+                pub const $err: super::Error = super::Error(-$errno);
+            )*
+
+            /// Returns the symbolic name of a negative errno.
+            pub(crate) fn name(errno: i32) -> Option<&'static str> {
+                match -errno {
+                    $($errno => Some(stringify!($err)),)*
+                    _ => None,
+                }
+            }
+        };
+    }
+
+    declare_err! {
+        EPERM, 1, "Operation not permitted.";
+        ENOENT, 2, "No such file or directory.";
+        ESRCH, 3, "No such process.";
+        EINTR, 4, "Interrupted system call.";
+        EIO, 5, "I/O error.";
+        ENXIO, 6, "No such device or address.";
+        E2BIG, 7, "Argument list too long.";
+        ENOEXEC, 8, "Exec format error.";
+        EBADF, 9, "Bad file number.";
+        ECHILD, 10, "No child processes.";
+        EAGAIN, 11, "Try again.";
+        ENOMEM, 12, "Out of memory.";
+        EACCES, 13, "Permission denied.";
+        EFAULT, 14, "Bad address.";
+        ENOTBLK, 15, "Block device required.";
+        EBUSY, 16, "Device or resource busy.";
+        EEXIST, 17, "File exists.";
+        EXDEV, 18, "Cross-device link.";
+        ENODEV, 19, "No such device.";
+        ENOTDIR, 20, "Not a directory.";
+        EISDIR, 21, "Is a directory.";
+        EINVAL, 22, "Invalid argument.";
+        ENFILE, 23, "File table overflow.";
+        EMFILE, 24, "Too many open files.";
+        ENOTTY, 25, "Not a typewriter.";
+        ETXTBSY, 26, "Text file busy.";
+        EFBIG, 27, "File too large.";
+        ENOSPC, 28, "No space left on device.";
+        ESPIPE, 29, "Illegal seek.";
+        EROFS, 30, "Read-only file system.";
+        EMLINK, 31, "Too many links.";
+        EPIPE, 32, "Broken pipe.";
+        EDOM, 33, "Math argument out of domain of func.";
+        ERANGE, 34, "Math result not representable.";
+        EDEADLK, 35, "Resource deadlock would occur.";
+        ENAMETOOLONG, 36, "File name too long.";
+        ENOLCK, 37, "No record locks available.";
+        ENOSYS, 38, "Invalid system call number.";
+        ENOTEMPTY, 39, "Directory not empty.";
+        ELOOP, 40, "Too many symbolic links encountered.";
+        ENOMSG, 42, "No message of desired type.";
+        EIDRM, 43, "Identifier removed.";
+        ECHRNG, 44, "Channel number out of range.";
+        EL2NSYNC, 45, "Level 2 not synchronized.";
+        EL3HLT, 46, "Level 3 halted.";
+        EL3RST, 47, "Level 3 reset.";
+        ELNRNG, 48, "Link number out of range.";
+        EUNATCH, 49, "Protocol driver not attached.";
+        ENOCSI, 50, "No CSI structure available.";
+        EL2HLT, 51, "Level 2 halted.";
+        EBADE, 52, "Invalid exchange.";
+        EBADR, 53, "Invalid request descriptor.";
+        EXFULL, 54, "Exchange full.";
+        ENOANO, 55, "No anode.";
+        EBADRQC, 56, "Invalid request code.";
+        EBADSLT, 57, "Invalid slot.";
+        EBFONT, 59, "Bad font file format.";
+        ENOSTR, 60, "Device not a stream.";
+        ENODATA, 61, "No data available.";
+        ETIME, 62, "Timer expired.";
+        ENOSR, 63, "Out of streams resources.";
+        ENONET, 64, "Machine is not on the network.";
+        ENOPKG, 65, "Package not installed.";
+        EREMOTE, 66, "Object is remote.";
+        ENOLINK, 67, "Link has been severed.";
+        EADV, 68, "Advertise error.";
+        ESRMNT, 69, "Srmount error.";
+        ECOMM, 70, "Communication error on send.";
+        EPROTO, 71, "Protocol error.";
+        EMULTIHOP, 72, "Multihop attempted.";
+        EDOTDOT, 73, "RFS specific error.";
+        EBADMSG, 74, "Not a data message.";
+        EOVERFLOW, 75, "Value too large for defined data type.";
+        ENOTUNIQ, 76, "Name not unique on network.";
+        EBADFD, 77, "File descriptor in bad state.";
+        EREMCHG, 78, "Remote address changed.";
+        ELIBACC, 79, "Can not access a needed shared library.";
+        ELIBBAD, 80, "Accessing a corrupted shared library.";
+        ELIBSCN, 81, ".lib section in a.out corrupted.";
+        ELIBMAX, 82, "Attempting to link in too many shared libraries.";
+        ELIBEXEC, 83, "Cannot exec a shared library directly.";
+        EILSEQ, 84, "Illegal byte sequence.";
+        ERESTART, 85, "Interrupted system call should be restarted.";
+        ESTRPIPE, 86, "Streams pipe error.";
+        EUSERS, 87, "Too many users.";
+        ENOTSOCK, 88, "Socket operation on non-socket.";
+        EDESTADDRREQ, 89, "Destination address required.";
+        EMSGSIZE, 90, "Message too long.";
+        EPROTOTYPE, 91, "Protocol wrong type for socket.";
+        ENOPROTOOPT, 92, "Protocol not available.";
+        EPROTONOSUPPORT, 93, "Protocol not supported.";
+        ESOCKTNOSUPPORT, 94, "Socket type not supported.";
+        EOPNOTSUPP, 95, "Operation not supported on transport endpoint.";
+        EPFNOSUPPORT, 96, "Protocol family not supported.";
+        EAFNOSUPPORT, 97, "Address family not supported by protocol.";
+        EADDRINUSE, 98, "Address already in use.";
+        EADDRNOTAVAIL, 99, "Cannot assign requested address.";
+        ENETDOWN, 100, "Network is down.";
+        ENETUNREACH, 101, "Network is unreachable.";
+        ENETRESET, 102, "Network dropped connection because of reset.";
+        ECONNABORTED, 103, "Software caused connection abort.";
+        ECONNRESET, 104, "Connection reset by peer.";
+        ENOBUFS, 105, "No buffer space available.";
+        EISCONN, 106, "Transport endpoint is already connected.";
+        ENOTCONN, 107, "Transport endpoint is not connected.";
+        ESHUTDOWN, 108, "Cannot send after transport endpoint shutdown.";
+        ETOOMANYREFS, 109, "Too many references: cannot splice.";
+        ETIMEDOUT, 110, "Connection timed out.";
+        ECONNREFUSED, 111, "Connection refused.";
+        EHOSTDOWN, 112, "Host is down.";
+        EHOSTUNREACH, 113, "No route to host.";
+        EALREADY, 114, "Operation already in progress.";
+        EINPROGRESS, 115, "Operation now in progress.";
+        ESTALE, 116, "Stale file handle.";
+        EUCLEAN, 117, "Structure needs cleaning.";
+        ENOTNAM, 118, "Not a XENIX named type file.";
+        ENAVAIL, 119, "No XENIX semaphores available.";
+        EISNAM, 120, "Is a named type file.";
+        EREMOTEIO, 121, "Remote I/O error.";
+        EDQUOT, 122, "Quota exceeded.";
+        ENOMEDIUM, 123, "No medium found.";
+        EMEDIUMTYPE, 124, "Wrong medium type.";
+        ECANCELED, 125, "Operation Canceled.";
+        ENOKEY, 126, "Required key not available.";
+        EKEYEXPIRED, 127, "Key has expired.";
+        EKEYREVOKED, 128, "Key has been revoked.";
+        EKEYREJECTED, 129, "Key was rejected by service.";
+        EOWNERDEAD, 130, "Owner died.";
+        ENOTRECOVERABLE, 131, "State not recoverable.";
+        ERFKILL, 132, "Operation not possible due to RF-kill.";
+        EHWPOISON, 133, "Memory page has hardware error.";
+
+        // Kernel internal errnos, never seen by user space.
+        ERESTARTSYS, 512, "Restart the system call.";
+        ERESTARTNOINTR, 513, "System call was interrupted by a signal and will be restarted.";
+        ERESTARTNOHAND, 514, "Restart if no handler.";
+        ENOIOCTLCMD, 515, "No ioctl command.";
+        ERESTART_RESTARTBLOCK, 516, "Restart by calling sys_restart_syscall.";
+        EPROBE_DEFER, 517, "Driver requests probe retry.";
+        EOPENSTALE, 518, "Open found a stale dentry.";
+        ENOPARAM, 519, "Parameter not supported.";
+        EBADHANDLE, 521, "Illegal NFS file handle.";
+        ENOTSYNC, 522, "Update synchronization mismatch.";
+        EBADCOOKIE, 523, "Cookie is stale.";
+        ENOTSUPP, 524, "Operation is not supported.";
+        ETOOSMALL, 525, "Buffer or request is too small.";
+        ESERVERFAULT, 526, "An untranslatable error occurred.";
+        EBADTYPE, 527, "Type not supported by server.";
+        EJUKEBOX, 528, "Request initiated, but will not complete before timeout.";
+        EIOCBQUEUED, 529, "iocb queued, will get completion event.";
+        ERECALLCONFLICT, 530, "Conflict with recalled state.";
+        ENOGRACE, 531, "NFS file lock reclaim refused.";
+    }
+
+    /// Operation would block, an alias of [`EAGAIN`].
+    pub const EWOULDBLOCK: super::Error = EAGAIN;
+    /// Resource deadlock would occur, an alias of [`EDEADLK`].
+    pub const EDEADLOCK: super::Error = EDEADLK;
+}
 
 /// A [`Result`] with an [`Error`] error type.
 pub type Result<T = (), E = Error> = core::result::Result<T, E>;
@@ -0,0 +1,211 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! GPIO consumers.
+//!
+//! C header: [`include/linux/gpio/consumer.h`](../../../../include/linux/gpio/consumer.h)
+
+use super::driver::{Direction, GpioChip, GpioIrqHandler, GPIO_CHIPS};
+use crate::device::Device;
+use crate::irq::Flags as IrqFlags;
+use crate::of::{of_get_phandle, of_property_present, parse_phandle_with_args};
+use crate::prelude::*;
+use crate::sync::Arc;
+use alloc::{format, string::String};
+use bitflags::bitflags;
+
+bitflags! {
+    /// How a line is set up when it is requested, Linux `enum gpiod_flags`.
+    #[repr(transparent)]
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    pub struct Flags: u32 {
+        /// Set the direction of the line.
+        const DIR_SET = 1;
+        /// Make the line an output.
+        const DIR_OUT = 1 << 1;
+        /// The initial logical value of an output.
+        const VAL = 1 << 2;
+
+        /// Leave the line as it is.
+        const AS_IS = 0;
+        /// Make the line an input.
+        const IN = Self::DIR_SET.bits();
+        /// Make the line an output, logically low.
+        const OUT_LOW = Self::DIR_SET.bits() | Self::DIR_OUT.bits();
+        /// Make the line an output, logically high.
+        const OUT_HIGH = Self::OUT_LOW.bits() | Self::VAL.bits();
+    }
+}
+
+/// `GPIO_ACTIVE_LOW` of `dt-bindings/gpio/gpio.h`.
+const GPIO_ACTIVE_LOW: u32 = 1;
+
+/// A GPIO line owned by a consumer.
+///
+/// The line is released when the descriptor is dropped.
+///
+/// # Examples
+///
+/// ```ignore
+/// # use kernel::{gpio, platform, prelude::*, time::msleep};
+/// fn probe(pdev: &mut platform::Device) -> Result {
+///     // `reset-gpios = <&gpio 3 GPIO_ACTIVE_LOW>;` asserts the reset with a low signal.
+///     let reset = gpio::GpioDesc::get(pdev.device(), "reset", gpio::Flags::OUT_HIGH)?;
+///     msleep(10);
+///     reset.set_value(false);
+///     Ok(())
+/// }
+/// ```
+pub struct GpioDesc {
+    chip: Arc<dyn GpioChip>,
+    phandle: u32,
+    offset: u32,
+    active_low: bool,
+}
+
+impl GpioDesc {
+    /// Gets the first line of the `<con_id>-gpios` property of `dev`, `gpios` if `con_id`
+    /// is empty, and sets it up as `flags` says.
+    pub fn get(dev: &Device, con_id: &str, flags: Flags) -> Result<Self> {
+        Self::get_index(dev, con_id, 0, flags)
+    }
+
+    /// Like [`GpioDesc::get`], but returns `None` if the device has no such line.
+    pub fn get_optional(dev: &Device, con_id: &str, flags: Flags) -> Result<Option<Self>> {
+        match Self::get(dev, con_id, flags) {
+            Ok(desc) => Ok(Some(desc)),
+            Err(ENOENT) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Gets the line `index` of the `<con_id>-gpios` property of `dev`.
+    ///
+    /// Fails with [`ENOENT`] if there is no such line, with [`EPROBE_DEFER`] if its
+    /// controller is not registered yet and with [`EBUSY`] if another consumer owns it.
+    pub fn get_index(dev: &Device, con_id: &str, index: usize, flags: Flags) -> Result<Self> {
+        let node = dev.of_node();
+        let mut spec = None;
+        // The `-gpio` suffix is deprecated but still found in device trees.
+        for suffix in ["gpios", "gpio"] {
+            let prop = if con_id.is_empty() {
+                String::from(suffix)
+            } else {
+                format!("{}-{}", con_id, suffix)
+            };
+            if of_property_present(node, &prop) {
+                spec = Some(parse_phandle_with_args(node, &prop, "#gpio-cells", index)?);
+                break;
+            }
+        }
+        let spec = spec.ok_or(ENOENT)?;
+        let phandle = of_get_phandle(spec.np).ok_or(EINVAL)?;
+
+        let (chip, offset, dt_flags) = {
+            let mut chips = GPIO_CHIPS.lock();
+            let entry = chips
+                .iter_mut()
+                .find(|entry| entry.phandle == phandle)
+                .ok_or(EPROBE_DEFER)?;
+            let (offset, dt_flags) = entry.chip.xlate(spec.args())?;
+            if entry.requested.contains(&offset) {
+                return Err(EBUSY);
+            }
+            entry.chip.request(offset)?;
+            entry.requested.try_reserve(1)?;
+            entry.requested.push(offset);
+            (entry.chip.clone(), offset, dt_flags)
+        };
+
+        let desc = Self {
+            chip,
+            phandle,
+            offset,
+            active_low: dt_flags & GPIO_ACTIVE_LOW != 0,
+        };
+        if flags.contains(Flags::DIR_SET) {
+            if flags.contains(Flags::DIR_OUT) {
+                desc.direction_output(flags.contains(Flags::VAL))?;
+            } else {
+                desc.direction_input()?;
+            }
+        }
+        Ok(desc)
+    }
+
+    /// Returns the offset of the line in its controller.
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    /// Returns true if the line is active low.
+    pub fn is_active_low(&self) -> bool {
+        self.active_low
+    }
+
+    /// Returns the direction of the line.
+    pub fn get_direction(&self) -> Result<Direction> {
+        self.chip.get_direction(self.offset)
+    }
+
+    /// Makes the line an input.
+    pub fn direction_input(&self) -> Result {
+        self.chip.direction_input(self.offset)
+    }
+
+    /// Makes the line an output, driving the logical `value`.
+    pub fn direction_output(&self, value: bool) -> Result {
+        self.chip.direction_output(self.offset, value != self.active_low)
+    }
+
+    /// Reads the logical value of the line.
+    pub fn get_value(&self) -> Result<bool> {
+        Ok(self.get_raw_value()? != self.active_low)
+    }
+
+    /// Drives the logical `value` on the output line.
+    pub fn set_value(&self, value: bool) {
+        self.set_raw_value(value != self.active_low)
+    }
+
+    /// Reads the signal of the line, ignoring its polarity.
+    pub fn get_raw_value(&self) -> Result<bool> {
+        self.chip.get(self.offset)
+    }
+
+    /// Drives the signal of the output line, ignoring its polarity.
+    pub fn set_raw_value(&self, value: bool) {
+        self.chip.set(self.offset, value)
+    }
+
+    /// Installs `handler` for the interrupt of the line, `trigger` gives the signal edges or
+    /// levels that raise it. The handler is called with the offset of the line, the data it
+    /// needs is captured by the closure.
+    ///
+    /// The handler runs in interrupt context, until [`GpioDesc::free_irq`] or the drop of
+    /// the descriptor.
+    pub fn request_irq(
+        &self,
+        trigger: IrqFlags,
+        handler: impl Fn(u32) + Send + Sync + 'static,
+    ) -> Result {
+        let handler: GpioIrqHandler = Box::new(handler);
+        self.chip.irq_request(self.offset, trigger, handler)
+    }
+
+    /// Removes the interrupt handler of the line.
+    pub fn free_irq(&self) {
+        self.chip.irq_free(self.offset)
+    }
+}
+
+impl Drop for GpioDesc {
+    fn drop(&mut self) {
+        let mut chips = GPIO_CHIPS.lock();
+        if let Some(entry) = chips.iter_mut().find(|entry| entry.phandle == self.phandle) {
+            entry.requested.retain(|offset| *offset != self.offset);
+        }
+        drop(chips);
+        self.chip.irq_free(self.offset);
+        self.chip.free(self.offset);
+    }
+}
@@ -0,0 +1,125 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! GPIO controller drivers.
+//!
+//! C header: [`include/linux/gpio/driver.h`](../../../../include/linux/gpio/driver.h)
+
+use crate::irq::Flags as IrqFlags;
+use crate::of::{of_get_phandle, of_node_name};
+use crate::prelude::*;
+use crate::sync::{Arc, Mutex};
+use of::OfNode;
+
+/// The direction of a line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// The line is an input.
+    In,
+    /// The line is an output.
+    Out,
+}
+
+/// The handler of a line interrupt, called with the offset of the line.
+pub type GpioIrqHandler = Box<dyn Fn(u32) + Send + Sync>;
+
+/// A GPIO controller, implemented by its driver.
+///
+/// Values are raw: `true` is a high signal, whatever the polarity of the consumer.
+pub trait GpioChip: Send + Sync {
+    /// Returns the number of lines of the controller.
+    fn ngpio(&self) -> u32;
+
+    /// Prepares the line `offset` for a consumer.
+    fn request(&self, _offset: u32) -> Result {
+        Ok(())
+    }
+
+    /// Releases the line `offset`, once its consumer is gone.
+    fn free(&self, _offset: u32) {}
+
+    /// Returns the direction of the line `offset`.
+    fn get_direction(&self, offset: u32) -> Result<Direction>;
+
+    /// Makes the line `offset` an input.
+    fn direction_input(&self, offset: u32) -> Result;
+
+    /// Makes the line `offset` an output, driving `value`.
+    fn direction_output(&self, offset: u32, value: bool) -> Result;
+
+    /// Reads the signal of the line `offset`.
+    fn get(&self, offset: u32) -> Result<bool>;
+
+    /// Drives the output line `offset`.
+    fn set(&self, offset: u32, value: bool);
+
+    /// Installs `handler` for the interrupt of the line `offset`, triggered as `trigger`
+    /// says, and unmasks it.
+    ///
+    /// Controllers that cannot raise interrupts keep the default, [`ENOTSUPP`].
+    fn irq_request(&self, _offset: u32, _trigger: IrqFlags, _handler: GpioIrqHandler) -> Result {
+        Err(ENOTSUPP)
+    }
+
+    /// Masks the interrupt of the line `offset` and removes its handler.
+    fn irq_free(&self, _offset: u32) {}
+
+    /// Translates the argument cells of a `gpios` entry to the line offset and the flags
+    /// of `dt-bindings/gpio/gpio.h`.
+    ///
+    /// The default handles the usual `<offset flags>` specifier.
+    fn xlate(&self, args: &[u32]) -> Result<(u32, u32)> {
+        let [offset, flags, ..] = args else {
+            return Err(EINVAL);
+        };
+        if *offset >= self.ngpio() {
+            return Err(EINVAL);
+        }
+        Ok((*offset, *flags))
+    }
+}
+
+pub(crate) struct GpioChipEntry {
+    pub(crate) phandle: u32,
+    pub(crate) chip: Arc<dyn GpioChip>,
+    // Lines owned by a consumer.
+    pub(crate) requested: Vec<u32>,
+}
+
+pub(crate) static GPIO_CHIPS: Mutex<Vec<GpioChipEntry>> = Mutex::new(Vec::new());
+
+/// Registers `chip` as the GPIO controller of `node`.
+///
+/// Fails with [`EINVAL`] if `node` has no phandle and with [`EEXIST`] if it already has a
+/// controller.
+pub fn gpiochip_add(node: OfNode<'static>, chip: Arc<dyn GpioChip>) -> Result {
+    let phandle = of_get_phandle(node).ok_or(EINVAL)?;
+    let mut chips = GPIO_CHIPS.lock();
+    if chips.iter().any(|entry| entry.phandle == phandle) {
+        return Err(EEXIST);
+    }
+    chips.try_reserve(1)?;
+    chips.push(GpioChipEntry {
+        phandle,
+        chip,
+        requested: Vec::new(),
+    });
+    pr_debug!("gpio chip {} registered", of_node_name(node));
+    Ok(())
+}
+
+/// Removes the GPIO controller of `node`.
+///
+/// Fails with [`EBUSY`] while consumers still hold some of its lines.
+pub fn gpiochip_remove(node: OfNode<'static>) -> Result {
+    let phandle = of_get_phandle(node).ok_or(EINVAL)?;
+    let mut chips = GPIO_CHIPS.lock();
+    let index = chips
+        .iter()
+        .position(|entry| entry.phandle == phandle)
+        .ok_or(ENODEV)?;
+    if !chips[index].requested.is_empty() {
+        return Err(EBUSY);
+    }
+    chips.remove(index);
+    Ok(())
+}
@@ -0,0 +1,14 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! General purpose input/output lines.
+//!
+//! Controller drivers implement [`GpioChip`] and register it for their device tree node
+//! with [`gpiochip_add`]. Consumers get a [`GpioDesc`] from the `<function>-gpios`
+//! properties of their device and work with logical values: a line flagged active low in
+//! the device tree reads `true` when its signal is low.
+
+mod consumer;
+mod driver;
+
+pub use consumer::*;
+pub use driver::*;
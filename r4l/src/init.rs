@@ -1,21 +1,33 @@
 //! Driver init
 
+#[cfg(not(feature = "hosted"))]
 use core::ffi::{c_int, c_void};
 
 
+#[cfg(not(feature = "hosted"))]
 struct InitcallAddrPair(*const u8, *const u8);
 
 pub fn driver_framework_init() {
     subsys_fn_init();
     module_fn_init();
+    crate::platform::platform_deferred_probe_report();
 }
 
 fn subsys_fn_init() {
+    // Clock and regulator providers first, the probes of the populated devices use them. A
+    // failure leaves the devices that need them deferred or failing, the others still probe.
+    if let Err(e) = crate::clk::of_clk_init() {
+        crate::pr_err!("clock init failed: {:?}", e);
+    }
+    if let Err(e) = crate::regulator::of_regulator_init() {
+        crate::pr_err!("regulator init failed: {:?}", e);
+    }
     if let Err(e) = crate::of::of_platform_default_populate_init() {
-        panic!("subsys fn init failed");
+        crate::pr_err!("platform device populate failed: {:?}", e);
     }
 }
 
+#[cfg(not(feature = "hosted"))]
 fn initcall(pair: InitcallAddrPair) {
     let fn_ptr_size = core::mem::size_of::<*const extern "C" fn() -> c_int>();
     let start_addr = pair.0;
@@ -41,6 +53,7 @@ fn initcall(pair: InitcallAddrPair) {
     }
 }
 
+#[cfg(not(feature = "hosted"))]
 fn module_fn_init() {
     initcall(InitcallAddrPair(_initcall1 as *const u8, _initcall1_end as *const u8));
     initcall(InitcallAddrPair(_initcall2 as *const u8, _initcall2_end as *const u8));
@@ -51,6 +64,12 @@ fn module_fn_init() {
     initcall(InitcallAddrPair(_initcall7 as *const u8, _initcall7_end as *const u8));
 }
 
+// Hosted builds are linked by the host toolchain, which knows nothing about the initcall
+// sections; modules are registered directly by the test or program instead.
+#[cfg(feature = "hosted")]
+fn module_fn_init() {}
+
+#[cfg(not(feature = "hosted"))]
 extern "C" {
     fn _initcall1();
     fn _initcall1_end();
@@ -0,0 +1,252 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Memory-mapped IO.
+//!
+//! C header: [`include/asm-generic/io.h`](../../../../include/asm-generic/io.h)
+
+use crate::build_assert;
+use crate::error::{code::EINVAL, Result};
+use crate::os::{Os, OsInterface};
+use core::sync::atomic::{fence, Ordering};
+
+/// A window of physical addresses, as described by an entry of a `reg` property.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Resource {
+    /// The first physical address of the window.
+    pub start: usize,
+    /// The size of the window in bytes.
+    pub size: usize,
+}
+
+impl Resource {
+    /// Creates a resource of `size` bytes starting at `start`.
+    pub const fn new(start: usize, size: usize) -> Self {
+        Self { start, size }
+    }
+
+    /// Returns the last address of the resource, included.
+    ///
+    /// Returns `None` if the resource is empty or ends past the address space.
+    pub const fn end(&self) -> Option<usize> {
+        if self.size == 0 {
+            return None;
+        }
+        self.start.checked_add(self.size - 1)
+    }
+}
+
+/// Represents a memory block of at least `SIZE` bytes.
+///
+/// Accesses at a constant offset are checked at build time, the `try_` variants check the
+/// offset at runtime and return [`EINVAL`] when it is out of bounds or misaligned.
+///
+/// The window only needs the alignment of the accesses made to it, e.g. 4 bytes for a
+/// device with 32-bit registers. A wider access than the window start is aligned for
+/// panics, or fails with [`EINVAL`] for the `try_` variants.
+///
+/// The plain accessors order the access against normal memory accesses, like Linux
+/// `readl()`/`writel()`: a read completes before later memory reads, a write happens after
+/// earlier memory writes. The `_relaxed` variants only order accesses to the same device.
+///
+/// # Examples
+///
+/// ```ignore
+/// # use kernel::{io::IoMem, platform, prelude::*};
+/// fn probe(pdev: &mut platform::Device) -> Result {
+///     // SAFETY: The driver owns the registers of the device.
+///     let mem = unsafe { IoMem::<0x100>::try_new(pdev.io_resource(0)?) }?;
+///     mem.writel(1, 0x10);
+///     let status = mem.readl(0x14);
+///     Ok(())
+/// }
+/// ```
+pub struct IoMem<const SIZE: usize> {
+    ptr: usize,
+    // Size of the mapping, may be larger than `SIZE`.
+    len: usize,
+}
+
+macro_rules! define_read {
+    ($(#[$attr:meta])* $name:ident, $try_name:ident, $type_name:ty, $relaxed:expr) => {
+        $(#[$attr])*
+        #[inline]
+        pub fn $name(&self, offset: usize) -> $type_name {
+            self.check_access::<$type_name>(offset);
+            // SAFETY: The offset is checked above, `ptr` maps at least `SIZE` bytes.
+            unsafe { self.read::<$type_name>(offset, $relaxed) }
+        }
+
+        /// Reads IO data from the given offset, checking it at runtime.
+        ///
+        /// Returns [`EINVAL`] if the offset is out of bounds or misaligned.
+        pub fn $try_name(&self, offset: usize) -> Result<$type_name> {
+            if !Self::offset_ok::<$type_name>(offset) || !self.base_aligned::<$type_name>() {
+                return Err(EINVAL);
+            }
+            // SAFETY: The offset is checked above, `ptr` maps at least `SIZE` bytes.
+            Ok(unsafe { self.read::<$type_name>(offset, $relaxed) })
+        }
+    };
+}
+
+macro_rules! define_write {
+    ($(#[$attr:meta])* $name:ident, $try_name:ident, $type_name:ty, $relaxed:expr) => {
+        $(#[$attr])*
+        #[inline]
+        pub fn $name(&self, value: $type_name, offset: usize) {
+            self.check_access::<$type_name>(offset);
+            // SAFETY: The offset is checked above, `ptr` maps at least `SIZE` bytes.
+            unsafe { self.write::<$type_name>(value, offset, $relaxed) }
+        }
+
+        /// Writes IO data to the given offset, checking it at runtime.
+        ///
+        /// Returns [`EINVAL`] if the offset is out of bounds or misaligned.
+        pub fn $try_name(&self, value: $type_name, offset: usize) -> Result {
+            if !Self::offset_ok::<$type_name>(offset) || !self.base_aligned::<$type_name>() {
+                return Err(EINVAL);
+            }
+            // SAFETY: The offset is checked above, `ptr` maps at least `SIZE` bytes.
+            unsafe { self.write::<$type_name>(value, offset, $relaxed) };
+            Ok(())
+        }
+    };
+}
+
+impl<const SIZE: usize> IoMem<SIZE> {
+    /// Maps the physical window of `res`.
+    ///
+    /// Fails with [`EINVAL`] if `res` is smaller than `SIZE` bytes.
+    ///
+    /// # Safety
+    ///
+    /// Callers must ensure that `res` is a device memory window they own, nothing else
+    /// should access it while the returned object lives.
+    pub unsafe fn try_new(res: Resource) -> Result<Self> {
+        if res.size < SIZE {
+            return Err(EINVAL);
+        }
+        let ptr = Os::ioremap(res.start, res.size)?;
+        Ok(Self { ptr, len: res.size })
+    }
+
+    const fn offset_ok<T>(offset: usize) -> bool {
+        let type_size = core::mem::size_of::<T>();
+        match offset.checked_add(type_size) {
+            Some(end) => end <= SIZE && offset % type_size == 0,
+            None => false,
+        }
+    }
+
+    // The mapping keeps the offset of the window in its page, so `ptr` is aligned like the
+    // start of the window.
+    #[inline]
+    fn base_aligned<T>(&self) -> bool {
+        self.ptr % core::mem::size_of::<T>() == 0
+    }
+
+    #[inline]
+    fn check_access<T>(&self, offset: usize) {
+        build_assert!(Self::offset_ok::<T>(offset), "IoMem offset overflow");
+        assert!(self.base_aligned::<T>(), "IoMem access wider than the window alignment");
+    }
+
+    // SAFETY: `offset` must be in bounds and aligned for `T`.
+    #[inline]
+    unsafe fn read<T>(&self, offset: usize, relaxed: bool) -> T {
+        let value = unsafe { core::ptr::read_volatile((self.ptr + offset) as *const T) };
+        if !relaxed {
+            // Later memory reads must see what the device wrote before this read.
+            fence(Ordering::SeqCst);
+        }
+        value
+    }
+
+    // SAFETY: `offset` must be in bounds and aligned for `T`.
+    #[inline]
+    unsafe fn write<T>(&self, value: T, offset: usize, relaxed: bool) {
+        if !relaxed {
+            // Earlier memory writes must be visible to the device before this write.
+            fence(Ordering::SeqCst);
+        }
+        unsafe { core::ptr::write_volatile((self.ptr + offset) as *mut T, value) }
+    }
+
+    define_read!(
+        /// Reads a byte at `offset`.
+        readb, try_readb, u8, false
+    );
+    define_read!(
+        /// Reads a 16-bit word at `offset`.
+        readw, try_readw, u16, false
+    );
+    define_read!(
+        /// Reads a 32-bit word at `offset`.
+        readl, try_readl, u32, false
+    );
+    define_read!(
+        /// Reads a 64-bit word at `offset`.
+        readq, try_readq, u64, false
+    );
+
+    define_read!(
+        /// Reads a byte at `offset`, without ordering against memory.
+        readb_relaxed, try_readb_relaxed, u8, true
+    );
+    define_read!(
+        /// Reads a 16-bit word at `offset`, without ordering against memory.
+        readw_relaxed, try_readw_relaxed, u16, true
+    );
+    define_read!(
+        /// Reads a 32-bit word at `offset`, without ordering against memory.
+        readl_relaxed, try_readl_relaxed, u32, true
+    );
+    define_read!(
+        /// Reads a 64-bit word at `offset`, without ordering against memory.
+        readq_relaxed, try_readq_relaxed, u64, true
+    );
+
+    define_write!(
+        /// Writes a byte at `offset`.
+        writeb, try_writeb, u8, false
+    );
+    define_write!(
+        /// Writes a 16-bit word at `offset`.
+        writew, try_writew, u16, false
+    );
+    define_write!(
+        /// Writes a 32-bit word at `offset`.
+        writel, try_writel, u32, false
+    );
+    define_write!(
+        /// Writes a 64-bit word at `offset`.
+        writeq, try_writeq, u64, false
+    );
+
+    define_write!(
+        /// Writes a byte at `offset`, without ordering against memory.
+        writeb_relaxed, try_writeb_relaxed, u8, true
+    );
+    define_write!(
+        /// Writes a 16-bit word at `offset`, without ordering against memory.
+        writew_relaxed, try_writew_relaxed, u16, true
+    );
+    define_write!(
+        /// Writes a 32-bit word at `offset`, without ordering against memory.
+        writel_relaxed, try_writel_relaxed, u32, true
+    );
+    define_write!(
+        /// Writes a 64-bit word at `offset`, without ordering against memory.
+        writeq_relaxed, try_writeq_relaxed, u64, true
+    );
+}
+
+impl<const SIZE: usize> Drop for IoMem<SIZE> {
+    fn drop(&mut self) {
+        Os::iounmap(self.ptr, self.len);
+    }
+}
+
+// SAFETY: The mapping is owned by the `IoMem` and only accessed through volatile accesses.
+unsafe impl<const SIZE: usize> Send for IoMem<SIZE> {}
+unsafe impl<const SIZE: usize> Sync for IoMem<SIZE> {}
@@ -0,0 +1,284 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Interrupt domains: translation of device tree interrupt specifiers.
+//!
+//! C header: [`include/linux/irqdomain.h`](../../../../include/linux/irqdomain.h)
+//!
+//! Every interrupt controller node has a domain that turns the specifiers of its consumers
+//! into an irq number of the OS and trigger flags. Controller drivers register their domain
+//! with [`irq_domain_add`]; controllers without a driver get a builtin domain chosen from
+//! their compatible, or from `#interrupt-cells` for the root controller.
+//!
+//! The lines of a chained domain, like the one of a GPIO controller, are not known to the OS:
+//! they get irq numbers from [`IRQ_CHAINED_BASE`] on, and requesting them goes through the
+//! domain, whose driver calls the handlers from the handler of its own interrupt.
+
+use super::{Flags, IrqHandler};
+use crate::of::{of_get_phandle, of_node_name};
+use crate::os::{Os, OsInterface};
+use crate::prelude::*;
+use crate::sync::{Arc, Mutex, SpinNoIrq};
+use of::OfNode;
+
+/// The operations of an interrupt domain.
+pub trait IrqDomainOps: Send + Sync {
+    /// Translates an interrupt specifier to the hardware irq number and trigger flags.
+    fn xlate(&self, intspec: &[u32]) -> Result<(u32, Flags)>;
+
+    /// Maps a hardware irq number of the domain to an irq number of the OS.
+    ///
+    /// The default is the identity, right for the root interrupt controller.
+    fn to_virq(&self, hwirq: u32) -> Result<u32> {
+        Ok(hwirq)
+    }
+
+    /// Returns true if the driver of the domain demultiplexes its lines, see the module
+    /// documentation. Such domains implement the `*_line` operations.
+    fn is_chained(&self) -> bool {
+        false
+    }
+
+    /// Installs a handler for the line `hwirq`, triggered as `trigger` says, and unmasks it.
+    /// The driver calls `handler(virq)` when the line fires.
+    fn request_line(
+        &self,
+        _hwirq: u32,
+        _trigger: Flags,
+        _virq: u32,
+        _handler: IrqHandler,
+    ) -> Result {
+        Err(ENOTSUPP)
+    }
+
+    /// Masks the line `hwirq` and removes its handler.
+    fn free_line(&self, _hwirq: u32) {}
+
+    /// Unmasks or masks the line `hwirq`.
+    fn set_line_enabled(&self, _hwirq: u32, _enabled: bool) {}
+}
+
+/// Domain of a controller with a one cell specifier, the hardware irq number.
+pub struct OneCellDomain;
+
+impl IrqDomainOps for OneCellDomain {
+    fn xlate(&self, intspec: &[u32]) -> Result<(u32, Flags)> {
+        match intspec {
+            [hwirq, ..] => Ok((*hwirq, Flags::TRIGGER_NONE)),
+            [] => Err(EINVAL),
+        }
+    }
+}
+
+/// Domain of a controller with a two cells specifier, hardware irq number and trigger type.
+pub struct TwoCellDomain;
+
+impl IrqDomainOps for TwoCellDomain {
+    fn xlate(&self, intspec: &[u32]) -> Result<(u32, Flags)> {
+        match intspec {
+            [hwirq, trigger, ..] => Ok((*hwirq, Flags::from_dt_trigger(*trigger))),
+            _ => Err(EINVAL),
+        }
+    }
+}
+
+/// Domain of the ARM GIC, with `<type number trigger>` specifiers.
+///
+/// The hardware irq number is the interrupt ID of the GIC: SPIs start at 32, PPIs at 16.
+pub struct GicDomain;
+
+const GIC_SPI: u32 = 0;
+const GIC_PPI: u32 = 1;
+
+impl IrqDomainOps for GicDomain {
+    fn xlate(&self, intspec: &[u32]) -> Result<(u32, Flags)> {
+        let [kind, number, trigger, ..] = intspec else {
+            return Err(EINVAL);
+        };
+        let hwirq = match *kind {
+            GIC_SPI => number + 32,
+            GIC_PPI => number + 16,
+            _ => return Err(EINVAL),
+        };
+        let mut flags = Flags::from_dt_trigger(trigger & 0xf);
+        if *kind == GIC_PPI {
+            flags |= Flags::PERCPU;
+        }
+        Ok((hwirq, flags))
+    }
+}
+
+/// Domain of the RISC-V PLIC, the specifier is the interrupt source, level triggered.
+pub struct PlicDomain;
+
+impl IrqDomainOps for PlicDomain {
+    fn xlate(&self, intspec: &[u32]) -> Result<(u32, Flags)> {
+        match intspec {
+            [0, ..] | [] => Err(EINVAL),
+            [source, ..] => Ok((*source, Flags::TRIGGER_HIGH)),
+        }
+    }
+}
+
+const GIC_COMPATIBLES: [&str; 6] = [
+    "arm,gic-v3",
+    "arm,gic-400",
+    "arm,cortex-a15-gic",
+    "arm,cortex-a9-gic",
+    "arm,cortex-a7-gic",
+    "arm,arm11mp-gic",
+];
+
+const PLIC_COMPATIBLES: [&str; 3] = ["riscv,plic0", "sifive,plic-1.0.0", "thead,c900-plic"];
+
+// Registered domains, keyed by the phandle of their controller node.
+static IRQ_DOMAINS: Mutex<Vec<(u32, Arc<dyn IrqDomainOps>)>> = Mutex::new(Vec::new());
+
+/// The first irq number given to the lines of chained domains, above the interrupt IDs of
+/// the root controllers.
+pub const IRQ_CHAINED_BASE: u32 = 0x10000;
+
+// A line of a chained domain.
+struct IrqMapping {
+    virq: u32,
+    domain: Arc<dyn IrqDomainOps>,
+    hwirq: u32,
+    trigger: Flags,
+}
+
+// The lines of chained domains, looked up from interrupt context.
+static IRQ_MAPPINGS: SpinNoIrq<Vec<IrqMapping>> = SpinNoIrq::new(Vec::new());
+
+/// Registers the domain of the interrupt controller `node`.
+///
+/// Fails with [`EINVAL`] if `node` has no phandle, nobody can refer to it, and with
+/// [`EEXIST`] if the controller already has a domain.
+pub fn irq_domain_add(node: OfNode<'static>, ops: Arc<dyn IrqDomainOps>) -> Result {
+    let phandle = of_get_phandle(node).ok_or(EINVAL)?;
+    let mut domains = IRQ_DOMAINS.lock();
+    if domains.iter().any(|(ph, _)| *ph == phandle) {
+        return Err(EEXIST);
+    }
+    domains.try_reserve(1)?;
+    domains.push((phandle, ops));
+    Ok(())
+}
+
+/// Removes the domain of the interrupt controller `node`, and the irq numbers of its lines.
+pub fn irq_domain_remove(node: OfNode<'static>) {
+    let Some(phandle) = of_get_phandle(node) else {
+        return;
+    };
+    let mut domains = IRQ_DOMAINS.lock();
+    if let Some(index) = domains.iter().position(|(ph, _)| *ph == phandle) {
+        let (_, ops) = domains.remove(index);
+        IRQ_MAPPINGS
+            .lock()
+            .retain(|map| !Arc::ptr_eq(&map.domain, &ops));
+    }
+}
+
+/// Returns the irq number of the line `hwirq` of the chained domain `domain`, triggered as
+/// `trigger` says, allocating it on first use.
+pub fn irq_create_mapping(
+    domain: &Arc<dyn IrqDomainOps>,
+    hwirq: u32,
+    trigger: Flags,
+) -> Result<u32> {
+    let mut mappings = IRQ_MAPPINGS.lock();
+    if let Some(map) = mappings
+        .iter_mut()
+        .find(|map| Arc::ptr_eq(&map.domain, domain) && map.hwirq == hwirq)
+    {
+        map.trigger = trigger;
+        return Ok(map.virq);
+    }
+    // Numbers of removed domains are not reused.
+    let virq = mappings
+        .iter()
+        .map(|map| map.virq + 1)
+        .max()
+        .unwrap_or(IRQ_CHAINED_BASE);
+    mappings.try_reserve(1)?;
+    mappings.push(IrqMapping {
+        virq,
+        domain: domain.clone(),
+        hwirq,
+        trigger,
+    });
+    Ok(virq)
+}
+
+// Returns the chained line of `irq`, if it is one.
+fn irq_find_mapping(irq: u32) -> Option<(Arc<dyn IrqDomainOps>, u32, Flags)> {
+    let mappings = IRQ_MAPPINGS.lock();
+    let map = mappings.iter().find(|map| map.virq == irq)?;
+    Some((map.domain.clone(), map.hwirq, map.trigger))
+}
+
+/// Installs `handler` for `irq` and enables the line, through its domain for the lines of
+/// chained domains and through the OS otherwise.
+pub(crate) fn irq_request_line(irq: u32, handler: IrqHandler) -> Result {
+    match irq_find_mapping(irq) {
+        Some((domain, hwirq, trigger)) => domain.request_line(hwirq, trigger, irq, handler),
+        None => Os::request_irq(irq, handler),
+    }
+}
+
+/// Removes the handler of `irq` and disables the line.
+pub(crate) fn irq_free_line(irq: u32) {
+    match irq_find_mapping(irq) {
+        Some((domain, hwirq, _)) => domain.free_line(hwirq),
+        None => Os::free_irq(irq),
+    }
+}
+
+/// Unmasks or masks `irq`.
+pub(crate) fn irq_set_line_enabled(irq: u32, enabled: bool) {
+    match irq_find_mapping(irq) {
+        Some((domain, hwirq, _)) => domain.set_line_enabled(hwirq, enabled),
+        None => Os::set_irq_enabled(irq, enabled),
+    }
+}
+
+/// Returns the domain of the interrupt controller `node`.
+///
+/// Fails with [`EPROBE_DEFER`] if the controller needs a driver that did not register yet.
+/// Only the root controller, which has no interrupt parent, may go without a driver: its
+/// interrupt numbers are the ones of the OS.
+pub fn irq_find_domain(node: OfNode<'static>) -> Result<Arc<dyn IrqDomainOps>> {
+    if let Some(phandle) = of_get_phandle(node) {
+        let domains = IRQ_DOMAINS.lock();
+        if let Some((_, ops)) = domains.iter().find(|(ph, _)| *ph == phandle) {
+            return Ok(ops.clone());
+        }
+    }
+
+    let is_compatible = |table: &[&str]| {
+        node.compatible()
+            .is_some_and(|c| c.all().any(|one| table.contains(&one)))
+    };
+    if is_compatible(&GIC_COMPATIBLES) {
+        return Ok(Arc::new(GicDomain));
+    }
+    if is_compatible(&PLIC_COMPATIBLES) {
+        return Ok(Arc::new(PlicDomain));
+    }
+    match node.interrupt_cells() {
+        Some(1) if is_root_controller(node) => Ok(Arc::new(OneCellDomain)),
+        Some(2) if is_root_controller(node) => Ok(Arc::new(TwoCellDomain)),
+        _ => {
+            // The driver of the controller may not be probed yet.
+            pr_debug!("no irq domain for {} yet", of_node_name(node));
+            Err(EPROBE_DEFER)
+        }
+    }
+}
+
+// Returns true if `node` has no interrupt parent. An `interrupt-parent` inherited from the
+// root node may point back to the controller itself.
+fn is_root_controller(node: OfNode<'static>) -> bool {
+    match node.interrupt_parent() {
+        None => true,
+        Some(parent) => of_get_phandle(parent).is_some_and(|ph| Some(ph) == of_get_phandle(node)),
+    }
+}
@@ -22,5 +22,45 @@ bitflags! {
         const COND_SUSPEND = 1 << 4;
         /// Interrupt is per cpu.
         const PERCPU = 1 << 5;
+        /// The interrupt is triggered when the signal goes from high to low.
+        const TRIGGER_FALLING = 1 << 6;
+        /// The interrupt is triggered while the signal is held high.
+        const TRIGGER_HIGH = 1 << 7;
+        /// The interrupt is triggered while the signal is held low.
+        const TRIGGER_LOW = 1 << 8;
+        /// Keep the line masked after the hard handler woke the thread handler up, until the
+        /// thread handler returns. Needed for level triggered lines the hard handler cannot
+        /// silence.
+        const ONESHOT = 1 << 9;
+        /// All the trigger type flags.
+        const TRIGGER_MASK = Self::TRIGGER_NONE.bits()
+            | Self::TRIGGER_RISING.bits()
+            | Self::TRIGGER_FALLING.bits()
+            | Self::TRIGGER_HIGH.bits()
+            | Self::TRIGGER_LOW.bits();
+    }
+}
+
+impl Flags {
+    /// Converts the trigger type of a device tree interrupt specifier, the
+    /// `IRQ_TYPE_*` values of `dt-bindings/interrupt-controller/irq.h`.
+    pub fn from_dt_trigger(cell: u32) -> Self {
+        let mut flags = Self::empty();
+        if cell & 1 != 0 {
+            flags |= Self::TRIGGER_RISING;
+        }
+        if cell & 2 != 0 {
+            flags |= Self::TRIGGER_FALLING;
+        }
+        if cell & 4 != 0 {
+            flags |= Self::TRIGGER_HIGH;
+        }
+        if cell & 8 != 0 {
+            flags |= Self::TRIGGER_LOW;
+        }
+        if flags.is_empty() {
+            flags = Self::TRIGGER_NONE;
+        }
+        flags
     }
 }
@@ -7,11 +7,15 @@
 //! Compatible with r4l's irq module interface
 //!
 
+mod domain;
 mod flags;
+mod threaded;
+use domain::{irq_free_line, irq_request_line, irq_set_line_enabled};
+pub use domain::*;
 pub use flags::*;
+pub use threaded::*;
 
-mod os_api;
-pub use os_api::*;
+pub use crate::os::IrqHandler;
 
 use crate::{
     error::Result,
@@ -19,9 +23,10 @@ use crate::{
 };
 
 use crate::prelude::*;
-use crate::sync::Mutex;
+use crate::sync::{Arc, SpinNoIrq};
 use core::fmt;
 use core::any::Any;
+use core::sync::atomic::{AtomicUsize, Ordering};
 
 /// The return value from interrupt handlers.
 pub enum Return {
@@ -29,8 +34,9 @@ pub enum Return {
     None,
     /// The interrupt was handled by this device.
     Handled,
-    /// The handler wants the handler thread to wake up.
-    /// Maybe os not support this
+    /// The handler wants the handler thread to wake up, see [`ThreadedHandler`].
+    ///
+    /// Handled like [`Return::Handled`] for a [`Handler`].
     WakeThread,
 }
 
@@ -44,13 +50,12 @@ impl  InternalRegistration {
     fn try_new(
         irq: u32,
         handler: IrqHandler,
-        _thread_fn: Option<IrqHandler>,
         flags: usize,
         name: fmt::Arguments<'_>,
     ) -> Result<Self> {
         let name = CString::try_from_fmt(name)?;
         // setup os irq handler
-        request_threaded_irq(irq, handler);
+        irq_request_line(irq, handler)?;
         Ok(Self {
             irq,
             name,
@@ -61,6 +66,7 @@ impl  InternalRegistration {
 impl Drop for InternalRegistration {
     fn drop(&mut self) {
         // Unregister irq handler.
+        irq_free_line(self.irq);
     }
 }
 
@@ -97,20 +103,45 @@ pub trait Handler {
 ///     irq::Registration::try_new(irq, data, irq::flags::SHARED, fmt!("example_{irq}"))
 /// }
 /// ```
-pub struct Registration(InternalRegistration);
+pub struct Registration {
+    reg: Option<InternalRegistration>,
+    id: usize,
+}
 
 unsafe impl Send for Registration {}
 unsafe impl Sync for Registration {}
 
 struct IrqData {
     data: Box<dyn Any>,
-    irq: u32
+    irq: u32,
+    // Tells the registrations of a line apart.
+    id: usize,
 }
 
 unsafe impl Send for IrqData {}
 unsafe impl Sync for IrqData {}
 
-static IRQ_DATA_ARRAY: Mutex<Vec<IrqData>> = Mutex::new(Vec::new());
+// Looked up by the handlers, in interrupt context.
+static IRQ_DATA_ARRAY: SpinNoIrq<Vec<IrqData>> = SpinNoIrq::new(Vec::new());
+
+static IRQ_DATA_ID: AtomicUsize = AtomicUsize::new(0);
+
+// Publishes the data of a registration, returns the id to remove it with.
+fn irq_data_add(irq: u32, data: Box<dyn Any>) -> usize {
+    let id = IRQ_DATA_ID.fetch_add(1, Ordering::Relaxed);
+    IRQ_DATA_ARRAY.lock().push(IrqData { data, irq, id });
+    id
+}
+
+// Removes the data published by `irq_data_add`, and only that one.
+fn irq_data_remove(id: usize) {
+    let removed = {
+        let mut array = IRQ_DATA_ARRAY.lock();
+        array.iter().position(|x| x.id == id).map(|index| array.remove(index))
+    };
+    // Dropped with interrupts enabled.
+    drop(removed);
+}
 
 impl Registration {
     /// Registers a new irq handler.
@@ -122,20 +153,37 @@ impl Registration {
         flags: usize,
         name: fmt::Arguments<'_>,
     ) -> Result<Self>  where <H as Handler>::Data: 'static {
-        IRQ_DATA_ARRAY.lock().push(IrqData{data: Box::new(data), irq});
-        Ok(Self(InternalRegistration::try_new(irq, Self::handler::<H>, None, flags, name)?))
+        let id = irq_data_add(irq, Box::new(Arc::new(data)));
+        match InternalRegistration::try_new(irq, Self::handler::<H>, flags, name) {
+            Ok(reg) => Ok(Self { reg: Some(reg), id }),
+            Err(e) => {
+                irq_data_remove(id);
+                Err(e)
+            }
+        }
     }
 
-    #[cfg(feature = "starry")]
     fn handler<H: Handler> (irq:u32) where <H as Handler>::Data: 'static {
-        let lock = IRQ_DATA_ARRAY.lock();
-        let irq_data = lock.iter().find(|x|x.irq == irq).unwrap();
-        H::handle_irq(irq_data.data.downcast_ref::<H::Data>().unwrap());
+        // Do not hold the table while the handler runs: the handler of a chained domain runs
+        // the handlers of its lines, which look the table up too. The line may have other
+        // registrations, of other handler types, so skip the entries that are not ours.
+        let data = IRQ_DATA_ARRAY
+            .lock()
+            .iter()
+            .filter(|x|x.irq == irq)
+            .find_map(|x|x.data.downcast_ref::<Arc<H::Data>>())
+            .cloned();
+        let Some(data) = data else {
+            return;
+        };
+        H::handle_irq(&data);
     }
 }
 
 impl Drop for Registration {
     fn drop(&mut self) {
-        IRQ_DATA_ARRAY.lock().retain(|x:&IrqData| x.irq != self.0.irq);
+        // Free the line first, the handler needs the data until then.
+        drop(self.reg.take());
+        irq_data_remove(self.id);
     }
 }
@@ -0,0 +1,205 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Threaded interrupt handlers.
+//!
+//! A threaded handler splits the work of an interrupt: the hard handler runs in interrupt
+//! context and only checks and silences the device, the thread handler runs in a kernel task
+//! of the registration and may sleep.
+
+use super::{
+    irq_data_add, irq_data_remove, irq_set_line_enabled, Flags, InternalRegistration, Return,
+    IRQ_DATA_ARRAY,
+};
+use crate::os::{Os, OsInterface};
+use crate::prelude::*;
+use crate::sync::{Arc, WaitQueue};
+use core::fmt;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// A threaded irq handler.
+pub trait ThreadedHandler {
+    /// The context data associated with and made available to the handlers.
+    type Data: Send + Sync = ();
+
+    /// Called from interrupt context when the irq happens.
+    ///
+    /// Returns [`Return::WakeThread`] to run [`ThreadedHandler::thread_fn`], the default.
+    fn handle_irq(_data: &Self::Data) -> Return {
+        Return::WakeThread
+    }
+
+    /// Called from the thread of the registration after the hard handler asked for it.
+    fn thread_fn(data: &Self::Data) -> Return;
+}
+
+// The state shared by the hard handler, the thread and the registration.
+struct IrqThread<T> {
+    irq: u32,
+    oneshot: bool,
+    // Set by the hard handler, cleared by the thread before it runs the thread handler.
+    wake: AtomicBool,
+    // Set when the registration is dropped, the thread exits.
+    stop: AtomicBool,
+    exited: AtomicBool,
+    wq: WaitQueue,
+    data: T,
+}
+
+impl<T> IrqThread<T> {
+    fn run<H: ThreadedHandler<Data = T>>(&self) {
+        loop {
+            self.wq.wait_until(|| {
+                self.wake.load(Ordering::Acquire) || self.stop.load(Ordering::Acquire)
+            });
+            if self.stop.load(Ordering::Acquire) {
+                break;
+            }
+            self.wake.store(false, Ordering::Release);
+            H::thread_fn(&self.data);
+            if self.oneshot {
+                irq_set_line_enabled(self.irq, true);
+            }
+        }
+        self.exited.store(true, Ordering::Release);
+        self.wq.notify_all();
+    }
+
+    // Stops the thread and waits until it is done with the thread handler.
+    fn stop(&self) {
+        self.stop.store(true, Ordering::Release);
+        self.wq.notify_all();
+        self.wq.wait_until(|| self.exited.load(Ordering::Acquire));
+    }
+}
+
+/// The registration of a threaded interrupt handler.
+///
+/// Each registration has its own kernel thread, started on registration and stopped when
+/// the registration is dropped.
+///
+/// # Examples
+///
+/// ```ignore
+/// # use kernel::prelude::*;
+/// use kernel::irq;
+///
+/// struct Example;
+///
+/// impl irq::ThreadedHandler for Example {
+///     type Data = Box<u32>;
+///
+///     fn thread_fn(_data: &Box<u32>) -> irq::Return {
+///         irq::Return::Handled
+///     }
+/// }
+///
+/// fn request_irq(irq: u32, data: Box<u32>) -> Result<irq::ThreadedRegistration> {
+///     irq::ThreadedRegistration::try_new::<Example>(
+///         irq,
+///         data,
+///         irq::Flags::ONESHOT,
+///         format_args!("example_{irq}"),
+///     )
+/// }
+/// ```
+pub struct ThreadedRegistration {
+    irq: u32,
+    oneshot: bool,
+    // The id of the data of the registration in `IRQ_DATA_ARRAY`.
+    id: usize,
+    // Stops the thread of the registration.
+    stop: Box<dyn Fn() + Send + Sync>,
+    reg: Option<InternalRegistration>,
+}
+
+impl ThreadedRegistration {
+    /// Registers a new threaded irq handler.
+    ///
+    /// With [`Flags::ONESHOT`], the line stays masked from the wake up of the thread until
+    /// [`ThreadedHandler::thread_fn`] returns.
+    pub fn try_new<H: ThreadedHandler>(
+        irq: u32,
+        data: H::Data,
+        flags: Flags,
+        name: fmt::Arguments<'_>,
+    ) -> Result<Self>
+    where
+        H::Data: 'static,
+    {
+        let thread = Arc::new(IrqThread {
+            irq,
+            oneshot: flags.contains(Flags::ONESHOT),
+            wake: AtomicBool::new(false),
+            stop: AtomicBool::new(false),
+            exited: AtomicBool::new(false),
+            wq: WaitQueue::new(),
+            data,
+        });
+        let task = thread.clone();
+        Os::spawn(
+            &alloc::format!("irq/{}-{}", irq, name),
+            Box::new(move || task.run::<H>()),
+        )?;
+        let stopped = thread.clone();
+        let oneshot = thread.oneshot;
+        let mut registration = Self {
+            irq,
+            oneshot,
+            id: irq_data_add(irq, Box::new(thread)),
+            stop: Box::new(move || stopped.stop()),
+            reg: None,
+        };
+        // On failure, dropping the registration stops the thread.
+        registration.reg = Some(InternalRegistration::try_new(
+            irq,
+            Self::handler::<H>,
+            flags.bits() as usize,
+            name,
+        )?);
+        Ok(registration)
+    }
+
+    fn handler<H: ThreadedHandler>(irq: u32)
+    where
+        H::Data: 'static,
+    {
+        // Do not hold the table while the handler runs. The line may have other registrations,
+        // of other handler types, so skip the entries that are not ours.
+        let thread = IRQ_DATA_ARRAY
+            .lock()
+            .iter()
+            .filter(|x| x.irq == irq)
+            .find_map(|x| x.data.downcast_ref::<Arc<IrqThread<H::Data>>>())
+            .cloned();
+        let Some(thread) = thread else {
+            return;
+        };
+        if let Return::WakeThread = H::handle_irq(&thread.data) {
+            if thread.oneshot {
+                irq_set_line_enabled(irq, false);
+            }
+            thread.wake.store(true, Ordering::Release);
+            thread.wq.notify_all();
+        }
+    }
+}
+
+impl Drop for ThreadedRegistration {
+    fn drop(&mut self) {
+        // Stop the thread first, so that it does not unmask the line once it is freed. The
+        // hard handler may still run meanwhile, with nobody to wake up.
+        (self.stop)();
+        // The hard handler may have masked the line for a wake up the thread never handled.
+        if self.oneshot && self.reg.is_some() {
+            irq_set_line_enabled(self.irq, true);
+        }
+        drop(self.reg.take());
+        irq_data_remove(self.id);
+    }
+}
+
+// SAFETY: The thread state is `Send + Sync`, the internal registration only holds the irq
+// number and its name.
+unsafe impl Send for ThreadedRegistration {}
+// SAFETY: See `Send`.
+unsafe impl Sync for ThreadedRegistration {}
@@ -3,6 +3,11 @@
 //! Include:
 //! - error: error type used by drivers
 //! - log: log interface used by drivers
+//! - io: memory-mapped device registers
+//! - devres: resources released with the driver of their device
+//! - dma: DMA mapping and coherent allocations
+//! - dmaengine: slave channels of DMA controllers
+//! - os: the interface every OS implements for r4l
 
 #![no_std]
 #![feature(associated_type_defaults)]
@@ -12,22 +17,36 @@
 extern crate self as kernel;
 
 extern crate alloc;
+#[cfg(feature = "hosted")]
+extern crate std;
 
 //pub mod net;
 //pub mod i2c;
 mod build_error;
 mod bus;
+pub mod clk;
 pub mod device;
+pub mod devres;
+pub mod dma;
+pub mod dmaengine;
 pub mod driver;
 pub mod error;
+pub mod gpio;
 pub mod init;
+pub mod io;
 pub mod linked_list;
 pub mod of;
+pub mod os;
+pub mod pinctrl;
 pub mod platform;
 pub mod prelude;
 pub mod print;
+pub mod property;
+pub mod regulator;
+pub mod reset;
 pub mod str;
 pub mod sync;
+pub mod time;
 pub mod uapi;
 pub mod irq;
 
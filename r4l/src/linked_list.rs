@@ -1,11 +1,6 @@
 //! Defines the OS base link list.
 //!
-//! Every OS should provides:
-//! linked_list
+//! The intrusive lists of the `linked_list` crate do not depend on the OS, every backend
+//! shares them.
 
-#[cfg(feature = "starry")]
-mod list {
-    pub use linked_list::*;
-}
-
-pub use list::*;
+pub use linked_list::*;
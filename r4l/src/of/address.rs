@@ -0,0 +1,138 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Parsing of the `reg` property and translation of bus addresses.
+//!
+//! C header: [`include/linux/of_address.h`](../../../../include/linux/of_address.h)
+
+use super::base::*;
+use crate::io::Resource;
+use crate::prelude::*;
+use of::OfNode;
+
+/// Returns the entry `index` of the `reg` property of `node`, as a CPU physical window.
+///
+/// The entry is read with the `#address-cells` and `#size-cells` of the parent, then the
+/// address is translated to the CPU address space, see [`translate_address`]. An empty window
+/// fails with [`EINVAL`].
+pub fn of_address_to_resource(node: OfNode<'static>, index: usize) -> Result<Resource> {
+    let na = of_n_addr_cells(node);
+    let ns = of_n_size_cells(node);
+    if na == 0 || ns == 0 {
+        // Not a memory mapped bus, e.g. the `reg` of an i2c client is its bus address.
+        return Err(EINVAL);
+    }
+    let onesize = na + ns;
+    let addr = of_property_read_number(node, "reg", index * onesize, na).ok_or(EINVAL)?;
+    let size = of_property_read_number(node, "reg", index * onesize + na, ns).ok_or(EINVAL)?;
+    if size == 0 {
+        return Err(EINVAL);
+    }
+
+    let start = translate_address(node, addr)?;
+    pr_debug!("{} reg[{}]: {:#x} -> {:#x} size {:#x}", of_node_name(node), index, addr, start, size);
+    Ok(Resource::new(usize::try_from(start)?, usize::try_from(size)?))
+}
+
+/// Translates `addr`, an address in the `reg` space of `node`, to a CPU physical address.
+///
+/// Walks up the tree applying the `ranges` of every bus on the way to the root. An empty
+/// `ranges` maps the child space 1:1, a bus without `ranges` cannot be translated through.
+/// Fails with [`EINVAL`] if the address is outside every range of a bus.
+pub fn translate_address(node: OfNode<'static>, addr: u64) -> Result<u64> {
+    of_translate(node, addr, "ranges")
+}
+
+/// Translates `addr`, a DMA address as seen by `node`, to a CPU physical address.
+///
+/// Like [`translate_address`] through `dma-ranges`, except that a bus without `dma-ranges`
+/// is taken as a 1:1 mapping, as Linux does.
+pub fn translate_dma_address(node: OfNode<'static>, addr: u64) -> Result<u64> {
+    of_translate(node, addr, "dma-ranges")
+}
+
+fn of_translate(node: OfNode<'static>, mut addr: u64, rprop: &str) -> Result<u64> {
+    let Some(mut bus) = of_get_parent(node) else {
+        return Ok(addr);
+    };
+    let mut na = of_bus_n_addr_cells(bus);
+    let mut ns = of_bus_n_size_cells(bus);
+    // The children of the root are in the CPU address space.
+    while let Some(pbus) = of_get_parent(bus) {
+        let pna = of_bus_n_addr_cells(pbus);
+        addr = of_translate_one(bus, addr, rprop, na, ns, pna)?;
+        na = pna;
+        ns = of_bus_n_size_cells(pbus);
+        bus = pbus;
+    }
+    Ok(addr)
+}
+
+/// Translates `addr` from the child space of `bus` to the space of its parent.
+fn of_translate_one(
+    bus: OfNode<'static>,
+    addr: u64,
+    rprop: &str,
+    na: usize,
+    ns: usize,
+    pna: usize,
+) -> Result<u64> {
+    let rlen = of_property_count_cells(bus, rprop);
+    if rlen == 0 {
+        // An empty property is a 1:1 mapping, so is a missing `dma-ranges`.
+        if of_property_present(bus, rprop) || rprop == "dma-ranges" {
+            return Ok(addr);
+        }
+        pr_err!("{}: no {}, cannot translate {:#x}", of_node_name(bus), rprop, addr);
+        return Err(EINVAL);
+    }
+
+    let rone = na + pna + ns;
+    if rone == 0 || rlen % rone != 0 {
+        pr_err!("{}: malformed {}, cannot translate {:#x}", of_node_name(bus), rprop, addr);
+        return Err(EINVAL);
+    }
+    for i in 0..rlen / rone {
+        let base = i * rone;
+        let child = of_property_read_number(bus, rprop, base, na).ok_or(EINVAL)?;
+        let parent = of_property_read_number(bus, rprop, base + na, pna).ok_or(EINVAL)?;
+        let len = of_property_read_number(bus, rprop, base + na + pna, ns).ok_or(EINVAL)?;
+        if addr >= child && addr - child < len {
+            return Ok(parent + (addr - child));
+        }
+    }
+    pr_err!("{}: address {:#x} is not in its {}", of_node_name(bus), addr, rprop);
+    Err(EINVAL)
+}
+
+/// Returns true if the DMA of `node` is coherent with the CPU caches.
+///
+/// The `dma-coherent` property applies to the node and to every node below it.
+pub fn of_dma_is_coherent(node: OfNode<'static>) -> bool {
+    let mut np = Some(node);
+    while let Some(node) = np {
+        if of_property_present(node, "dma-coherent") {
+            return true;
+        }
+        np = of_get_parent(node);
+    }
+    false
+}
+
+/// Returns the offset between the CPU physical addresses and the DMA addresses of `node`:
+/// `cpu = dma + offset`, modulo 2^64.
+///
+/// The offset comes from the first entry of the closest `dma-ranges` above the node, the
+/// buses with several DMA windows are not supported. Without `dma-ranges` it is 0.
+pub fn of_dma_get_offset(node: OfNode<'static>) -> Result<u64> {
+    let mut np = of_get_parent(node);
+    while let Some(bus) = np {
+        if of_property_count_cells(bus, "dma-ranges") > 0 {
+            let na = of_bus_n_addr_cells(bus);
+            let dma = of_property_read_number(bus, "dma-ranges", 0, na).ok_or(EINVAL)?;
+            let cpu = translate_dma_address(node, dma)?;
+            return Ok(cpu.wrapping_sub(dma));
+        }
+        np = of_get_parent(bus);
+    }
+    Ok(0)
+}
@@ -0,0 +1,140 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Basic accessors of device tree nodes.
+//!
+//! C header: [`include/linux/of.h`](../../../../include/linux/of.h)
+//!
+//! Everything r4l reads from a node goes through this file, the rest of the `of` module
+//! builds on it.
+
+use of::OfNode;
+
+/// Sets the device tree blob every other accessor reads, before the first of them runs.
+///
+/// Starry hands the blob of the firmware over itself, tests of the hosted backend build one.
+#[cfg(feature = "hosted")]
+pub fn of_init_fdt(fdt: &'static [u8]) {
+    of::init_fdt_ptr(fdt.as_ptr());
+}
+
+/// Returns the full name of `node`, e.g. `pl061@9030000`.
+pub fn of_node_name(node: OfNode<'static>) -> &'static str {
+    node.name()
+}
+
+/// Returns the parent of `node`, `None` for the root node.
+pub fn of_get_parent(node: OfNode<'static>) -> Option<OfNode<'static>> {
+    node.parent()
+}
+
+/// Reads the cell `index` of the property `name` of `node`.
+pub fn of_property_read_cell(node: OfNode<'static>, name: &str, index: usize) -> Option<u32> {
+    of::of_property_read_u32(node, name, index)
+}
+
+/// Returns the number of cells of the property `name` of `node`, 0 if it is absent.
+pub fn of_property_count_cells(node: OfNode<'static>, name: &str) -> usize {
+    let mut count = 0;
+    while of_property_read_cell(node, name, count).is_some() {
+        count += 1;
+    }
+    count
+}
+
+/// Reads a number of `size` cells, starting at cell `index` of the property `name`.
+///
+/// Numbers are stored big endian, most significant cell first, and only the low 64 bits
+/// are kept, like Linux `of_read_number()`.
+pub fn of_property_read_number(
+    node: OfNode<'static>,
+    name: &str,
+    index: usize,
+    size: usize,
+) -> Option<u64> {
+    let mut number = 0u64;
+    for i in 0..size {
+        let cell = of_property_read_cell(node, name, index + i)?;
+        number = (number << 32) | cell as u64;
+    }
+    Some(number)
+}
+
+/// Returns the `#address-cells` of the children of `bus`.
+///
+/// The value comes from `bus` or its closest ancestor defining it, 2 if none does.
+pub fn of_bus_n_addr_cells(bus: OfNode<'static>) -> usize {
+    of_bus_cells(bus, "#address-cells", 2)
+}
+
+/// Returns the `#size-cells` of the children of `bus`.
+///
+/// The value comes from `bus` or its closest ancestor defining it, 1 if none does.
+pub fn of_bus_n_size_cells(bus: OfNode<'static>) -> usize {
+    of_bus_cells(bus, "#size-cells", 1)
+}
+
+/// Returns the `#address-cells` that applies to the `reg` of `node`.
+pub fn of_n_addr_cells(node: OfNode<'static>) -> usize {
+    of_get_parent(node).map_or(2, of_bus_n_addr_cells)
+}
+
+/// Returns the `#size-cells` that applies to the `reg` of `node`.
+pub fn of_n_size_cells(node: OfNode<'static>) -> usize {
+    of_get_parent(node).map_or(1, of_bus_n_size_cells)
+}
+
+fn of_bus_cells(bus: OfNode<'static>, name: &str, default: usize) -> usize {
+    let mut np = Some(bus);
+    while let Some(node) = np {
+        if let Some(cells) = of_property_read_cell(node, name, 0) {
+            return cells as usize;
+        }
+        np = of_get_parent(node);
+    }
+    default
+}
+
+/// Returns true if `node` has the property `name`, even if it has no value.
+pub fn of_property_present(node: OfNode<'static>, name: &str) -> bool {
+    of_get_property(node, name).is_some()
+}
+
+/// Returns the root node of the device tree, `None` if there is no device tree.
+pub fn of_root() -> Option<OfNode<'static>> {
+    of::root_node()
+}
+
+/// Returns the phandle of `node`, `None` if nothing refers to it.
+pub fn of_get_phandle(node: OfNode<'static>) -> Option<u32> {
+    of_property_read_cell(node, "phandle", 0).or_else(|| of_property_read_cell(node, "linux,phandle", 0))
+}
+
+/// Returns the node whose phandle is `phandle`.
+pub fn of_find_node_by_phandle(phandle: u32) -> Option<OfNode<'static>> {
+    of::find_node_by_phandle(phandle)
+}
+
+/// Returns the raw value of the property `name` of `node`.
+pub fn of_get_property(node: OfNode<'static>, name: &str) -> Option<&'static [u8]> {
+    node.find_property(name).map(|prop| prop.value)
+}
+
+/// Returns the strings of the string list property `name` of `node`.
+///
+/// A string that is not valid UTF-8 reads as an empty string, to keep the indexes right.
+pub fn of_property_strings(
+    node: OfNode<'static>,
+    name: &str,
+) -> impl Iterator<Item = &'static str> {
+    let value = of_get_property(node, name).unwrap_or_default();
+    let value = value.strip_suffix(&[0]).unwrap_or(value);
+    value
+        .split(|b| *b == 0)
+        .take(if value.is_empty() { 0 } else { usize::MAX })
+        .map(|s| core::str::from_utf8(s).unwrap_or_default())
+}
+
+/// Returns the index of `string` in the string list property `name` of `node`.
+pub fn of_property_match_string(node: OfNode<'static>, name: &str, string: &str) -> Option<usize> {
+    of_property_strings(node, name).position(|s| s == string)
+}
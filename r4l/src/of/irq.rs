@@ -1,38 +1,145 @@
 // SPDX-License-Identifier: GPL-2.0
 
+//! Parsing of device tree interrupt specifiers.
+//!
+//! C header: [`include/linux/of_irq.h`](../../../../include/linux/of_irq.h)
+
+use super::base::*;
+use super::phandle::*;
+use crate::irq::{irq_create_mapping, irq_find_domain, Flags};
 use crate::prelude::*;
 use of::OfNode;
 
-const MAX_PHANDLE_ARGS: usize = 32;
-struct OfPhandleArgs {
-    np: OfNode<'static>,
-    args_count: usize,
-    args: [u32; MAX_PHANDLE_ARGS],
+/// Resolves the interrupt `index` of `node` to its interrupt controller and specifier.
+fn of_irq_parse_one(node: OfNode<'static>, index: usize) -> Result<OfPhandleArgs> {
+    // The unit address of the device, matched by `interrupt-map` along with the specifier.
+    let mut addr = [0u32; MAX_PHANDLE_ARGS];
+    let na = of_n_addr_cells(node).min(MAX_PHANDLE_ARGS);
+    for (i, cell) in addr.iter_mut().enumerate().take(na) {
+        *cell = of_property_read_cell(node, "reg", i).unwrap_or(0);
+    }
+
+    // `interrupts-extended` names the parent of every interrupt, it wins over `interrupts`.
+    if of_property_present(node, "interrupts-extended") {
+        let mut res = parse_phandle_with_args(node, "interrupts-extended", "#interrupt-cells", index)?;
+        of_irq_parse_raw(&mut res, addr)?;
+        return Ok(res);
+    }
+
+    let parent = node.interrupt_parent().ok_or(EINVAL)?;
+    let intsize = parent.interrupt_cells().ok_or(EINVAL)?;
+    if intsize > MAX_PHANDLE_ARGS {
+        return Err(EINVAL);
+    }
+
+    pr_debug!("irq {} parent intsize={}\n", of_node_name(parent), intsize);
+
+    let mut res = OfPhandleArgs{np: parent, args_count: intsize, args: [0;MAX_PHANDLE_ARGS]};
+
+    for i in 0..intsize {
+        res.args[i] = of::of_property_read_u32(node, "interrupts", (index * intsize) + i).ok_or(EINVAL)?;
+    }
+    pr_debug!(" intspec={:?}\n", res.args);
+    of_irq_parse_raw(&mut res, addr)?;
+    Ok(res)
 }
 
-impl OfPhandleArgs {
-    fn of_irq_parse_one(node: OfNode<'static>, index: usize)
-        -> Result<Self>  {
-        let parent = node.interrupt_parent().ok_or(EINVAL)?;
-        let intsize = parent.interrupt_cells().ok_or(EINVAL)?;
-        
-        pr_debug!("irq {} parent intsize={}\n",parent.compatible().unwrap().first(), intsize);
+/// Walks up the interrupt tree, through `interrupt-map`s, to the interrupt controller.
+///
+/// Like Linux `of_irq_parse_raw()`, `addr` is the unit address of the device, as seen
+/// by `np`.
+fn of_irq_parse_raw(out: &mut OfPhandleArgs, mut addr: [u32; MAX_PHANDLE_ARGS]) -> Result {
+    loop {
+        let ipar = out.np;
+        if of_property_present(ipar, "interrupt-controller") {
+            pr_debug!(" -> got it, {}", of_node_name(ipar));
+            return Ok(());
+        }
+
+        let intsize = out.args_count;
+        let imaplen = of_property_count_cells(ipar, "interrupt-map");
+        if imaplen == 0 {
+            // No map, the specifier goes through unchanged to the parent.
+            out.np = ipar.interrupt_parent().ok_or(EINVAL)?;
+            continue;
+        }
 
-        let mut res = Self{np: parent, args_count: intsize, args: [0;MAX_PHANDLE_ARGS]};
+        let addrsize = of_property_read_cell(ipar, "#address-cells", 0).unwrap_or(2) as usize;
+        let matchsize = addrsize + intsize;
+        if matchsize > MAX_PHANDLE_ARGS {
+            return Err(EINVAL);
+        }
+        let mut spec = [0u32; MAX_PHANDLE_ARGS];
+        spec[..addrsize].copy_from_slice(&addr[..addrsize]);
+        spec[addrsize..matchsize].copy_from_slice(&out.args[..intsize]);
+        let mut mask = [u32::MAX; MAX_PHANDLE_ARGS];
+        for (i, cell) in mask.iter_mut().enumerate().take(matchsize) {
+            if let Some(m) = of_property_read_cell(ipar, "interrupt-map-mask", i) {
+                *cell = m;
+            }
+        }
 
-        for i in 0..intsize {
-            res.args[i] = of::of_property_read_u32(node, "interrupts", (index * intsize) + i).ok_or(EINVAL)?;
+        // Every entry is: child unit address, child specifier, parent phandle, parent
+        // unit address, parent specifier.
+        let mut pos = 0;
+        let mut found = None;
+        while pos + matchsize < imaplen {
+            let cell = |i: usize| of_property_read_cell(ipar, "interrupt-map", pos + i).ok_or(EINVAL);
+            let mut matched = true;
+            for i in 0..matchsize {
+                matched &= (cell(i)? ^ (spec[i] & mask[i])) == 0;
+            }
+            let newpar = of_find_node_by_phandle(cell(matchsize)?).ok_or(EINVAL)?;
+            let newaddrsize = of_property_read_cell(newpar, "#address-cells", 0).unwrap_or(0) as usize;
+            let newintsize = newpar.interrupt_cells().ok_or(EINVAL)?;
+            if newaddrsize + newintsize > MAX_PHANDLE_ARGS {
+                return Err(EINVAL);
+            }
+            if matched {
+                let base = matchsize + 1;
+                let mut newaddr = [0u32; MAX_PHANDLE_ARGS];
+                for (i, cell_out) in newaddr.iter_mut().enumerate().take(newaddrsize) {
+                    *cell_out = cell(base + i)?;
+                }
+                for i in 0..newintsize {
+                    out.args[i] = cell(base + newaddrsize + i)?;
+                }
+                found = Some((newpar, newintsize, newaddr));
+                break;
+            }
+            pos += matchsize + 1 + newaddrsize + newintsize;
         }
-        pr_debug!(" intspec={:?}\n", res.args);
-        // TODO: Check if there are any interrupt-map translations to process 
-        Ok(res)
+
+        let Some((newpar, newintsize, newaddr)) = found else {
+            pr_err!("{}: no interrupt-map entry for {:?}", of_node_name(ipar), &spec[..matchsize]);
+            return Err(EINVAL);
+        };
+        pr_debug!(" -> mapped to {} {:?}", of_node_name(newpar), &out.args[..newintsize]);
+        out.np = newpar;
+        out.args_count = newintsize;
+        addr = newaddr;
     }
 }
 
-pub fn of_irq_get(node: OfNode<'static>, index: usize) -> Result<u32> {
-    let oirq = OfPhandleArgs::of_irq_parse_one(node, index)?;
-    if oirq.args_count != 3 {
-        panic!("now only support arm interrupt")
-    }
-    Ok(oirq.args[1])
+/// Returns the irq number of the OS and the trigger flags of the interrupt `index` of `node`.
+///
+/// The specifier is translated by the [`crate::irq::IrqDomainOps`] of the interrupt
+/// controller it resolves to.
+pub fn of_irq_get(node: OfNode<'static>, index: usize) -> Result<(u32, Flags)> {
+    let oirq = of_irq_parse_one(node, index)?;
+    let domain = irq_find_domain(oirq.np)?;
+    let (hwirq, flags) = domain.xlate(&oirq.args[..oirq.args_count])?;
+    let virq = if domain.is_chained() {
+        irq_create_mapping(&domain, hwirq, flags)?
+    } else {
+        domain.to_virq(hwirq)?
+    };
+    pr_debug!("{} irq {}: hwirq {} -> virq {} {:?}", of_node_name(node), index, hwirq, virq, flags);
+    Ok((virq, flags))
+}
+
+/// Returns the interrupt named `name` in `interrupt-names` of `node`, like [`of_irq_get`].
+pub fn of_irq_get_byname(node: OfNode<'static>, name: &str) -> Result<(u32, Flags)> {
+    let index = of_property_match_string(node, "interrupt-names", name).ok_or(EINVAL)?;
+    of_irq_get(node, index)
 }
@@ -1,9 +1,15 @@
 // SPDX-License-Identifier: GPL-2.0
 
+mod address;
+mod base;
 mod device_id;
 mod platform;
 mod irq;
+mod phandle;
 
+pub use address::*;
+pub use base::*;
 pub use device_id::*;
 pub use platform::*;
 pub use irq::*;
+pub use phandle::*;
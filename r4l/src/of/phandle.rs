@@ -0,0 +1,121 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Parsing of phandle lists, the references between device tree nodes.
+//!
+//! C header: [`include/linux/of.h`](../../../../include/linux/of.h)
+
+use super::base::*;
+use crate::prelude::*;
+use of::OfNode;
+
+/// The maximum number of argument cells of a phandle reference.
+pub const MAX_PHANDLE_ARGS: usize = 32;
+
+/// A phandle reference: the target node and the argument cells that follow the phandle.
+#[derive(Clone, Copy)]
+pub struct OfPhandleArgs {
+    /// The target node.
+    pub np: OfNode<'static>,
+    /// The number of valid cells in `args`.
+    pub args_count: usize,
+    /// The argument cells.
+    pub args: [u32; MAX_PHANDLE_ARGS],
+}
+
+impl OfPhandleArgs {
+    /// Returns the argument cells.
+    pub fn args(&self) -> &[u32] {
+        &self.args[..self.args_count]
+    }
+}
+
+// An entry of a phandle list: the phandle, 0 for an empty entry, then the position of its
+// first argument cell and their count.
+struct PhandleEntry {
+    phandle: u32,
+    pos: usize,
+    count: usize,
+}
+
+/// Splits the phandle list `list_name` of `node` in entries.
+fn of_phandle_entries(
+    node: OfNode<'static>,
+    list_name: &str,
+    cells_name: Option<&str>,
+) -> Result<Vec<PhandleEntry>> {
+    if !of_property_present(node, list_name) {
+        return Err(ENOENT);
+    }
+    let len = of_property_count_cells(node, list_name);
+    let mut entries = Vec::new();
+    let mut pos = 0;
+    while pos < len {
+        let phandle = of_property_read_cell(node, list_name, pos).ok_or(EINVAL)?;
+        pos += 1;
+        let mut count = 0;
+        if phandle != 0 {
+            if let Some(cells_name) = cells_name {
+                let target = of_find_node_by_phandle(phandle).ok_or_else(|| {
+                    pr_err!("{}: {}: no node with phandle {:#x}", of_node_name(node), list_name, phandle);
+                    EINVAL
+                })?;
+                count = of_property_read_cell(target, cells_name, 0).ok_or_else(|| {
+                    pr_err!("{}: {} missing in {}", of_node_name(node), cells_name, of_node_name(target));
+                    EINVAL
+                })? as usize;
+            }
+            if count > MAX_PHANDLE_ARGS || pos + count > len {
+                pr_err!("{}: {}: bad argument count {}", of_node_name(node), list_name, count);
+                return Err(EINVAL);
+            }
+        }
+        entries.try_reserve(1)?;
+        entries.push(PhandleEntry { phandle, pos, count });
+        pos += count;
+    }
+    Ok(entries)
+}
+
+fn of_parse_phandle_args(
+    node: OfNode<'static>,
+    list_name: &str,
+    cells_name: Option<&str>,
+    index: usize,
+) -> Result<OfPhandleArgs> {
+    let entries = of_phandle_entries(node, list_name, cells_name)?;
+    let entry = entries.get(index).ok_or(ENOENT)?;
+    if entry.phandle == 0 {
+        return Err(ENOENT);
+    }
+    let np = of_find_node_by_phandle(entry.phandle).ok_or(EINVAL)?;
+    let mut res = OfPhandleArgs { np, args_count: entry.count, args: [0; MAX_PHANDLE_ARGS] };
+    for i in 0..entry.count {
+        res.args[i] = of_property_read_cell(node, list_name, entry.pos + i).ok_or(EINVAL)?;
+    }
+    Ok(res)
+}
+
+/// Returns the entry `index` of the phandle list `list_name` of `node`.
+///
+/// The number of argument cells of an entry is the `cells_name` property of its target,
+/// like `#clock-cells` for `clocks`. Fails with [`ENOENT`] if the list has no such entry or
+/// the entry is empty, with [`EINVAL`] if the list is malformed.
+pub fn parse_phandle_with_args(
+    node: OfNode<'static>,
+    list_name: &str,
+    cells_name: &str,
+    index: usize,
+) -> Result<OfPhandleArgs> {
+    of_parse_phandle_args(node, list_name, Some(cells_name), index)
+}
+
+/// Returns the target of the entry `index` of the phandle list `list_name` of `node`, a list
+/// of phandles without arguments.
+pub fn parse_phandle(node: OfNode<'static>, list_name: &str, index: usize) -> Result<OfNode<'static>> {
+    Ok(of_parse_phandle_args(node, list_name, None, index)?.np)
+}
+
+/// Returns the number of entries of the phandle list `list_name` of `node`.
+pub fn count_phandle_with_args(node: OfNode<'static>, list_name: &str, cells_name: &str) -> Result<usize> {
+    Ok(of_phandle_entries(node, list_name, Some(cells_name))?.len())
+}
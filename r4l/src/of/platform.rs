@@ -1,41 +1,109 @@
 // SPDX-License-Identifier: GPL-2.0
 
-use super::DeviceId;
+//! Creation of platform devices from the device tree.
+//!
+//! C header: [`include/linux/of_platform.h`](../../../../include/linux/of_platform.h)
+
+use super::base::{of_node_name, of_root};
+use super::DeviceId::{self, Compatible};
 use crate::error::*;
 use crate::platform::{platform_device_register, PlatformDevice};
-use crate::pr_debug;
 use crate::sync::Arc;
 use crate::sync::Mutex;
+use crate::{pr_debug, pr_err, pr_info};
 use of::OfNode;
 
-const OF_DEFAULT_BUS_MATCH_TABLE: [&'static str; 4] =
-    ["simple-bus", "simple-mfd", "isa", "arm,amba-bus"];
+/// The buses whose children are populated by default.
+pub const OF_DEFAULT_BUS_MATCH_TABLE: [DeviceId; 4] = [
+    Compatible("simple-bus"),
+    Compatible("simple-mfd"),
+    Compatible("isa"),
+    Compatible("arm,amba-bus"),
+];
+
+fn of_match_node(matches: &[DeviceId], node: OfNode<'static>) -> bool {
+    let Some(compatible) = node.compatible() else {
+        return false;
+    };
+    matches.iter().any(|id| match id {
+        Compatible(id) => compatible.all().any(|one| one == *id),
+    })
+}
+
+fn of_platform_device_create(
+    node: OfNode<'static>,
+    parent: Option<&Arc<Mutex<PlatformDevice>>>,
+) -> Result<Arc<Mutex<PlatformDevice>>> {
+    let mut pdev = PlatformDevice::new(node);
+    if let Some(parent) = parent {
+        pdev.set_parent(parent);
+    }
+    let pdev = Arc::new(Mutex::new(pdev));
+    platform_device_register(pdev.clone())?;
+    Ok(pdev)
+}
+
+/// Creates the device of `bus`, then the devices of its children if it matches `matches`.
+fn of_platform_bus_create(
+    bus: OfNode<'static>,
+    matches: &[DeviceId],
+    parent: Option<&Arc<Mutex<PlatformDevice>>>,
+) -> Result {
+    // Nodes without compatible, like `chosen` or `aliases`, are not devices.
+    let Some(compatible) = bus.compatible() else {
+        pr_debug!("skip {}, no compatible", of_node_name(bus));
+        return Ok(());
+    };
+    if !of::of_device_is_available(bus) {
+        return Ok(());
+    }
 
-fn of_platform_bus_device_create(node: OfNode<'static>) -> Result {
-    if !of::of_device_is_available(node) {
+    let pdev = of_platform_device_create(bus, parent)?;
+    pr_info!("create platform device {} ({})", of_node_name(bus), compatible.first());
+    if !of_match_node(matches, bus) {
         return Ok(());
     }
-    let pdev = Arc::new(Mutex::new(PlatformDevice::new(node)));
-    platform_device_register(pdev)?;
+    for child in bus.children() {
+        of_platform_child_create(child, matches, Some(&pdev));
+    }
     Ok(())
 }
 
-pub fn of_platform_default_populate_init() -> Result {
-    let bus_node = of::find_compatible_node(&OF_DEFAULT_BUS_MATCH_TABLE);
-    for i in bus_node {
-        crate::pr_info!("bus node: {:?} ", i.compatible().unwrap().first());
-        for c in i.children() {
-            of_platform_bus_device_create(c)?;
-            match (i.compatible(), c.compatible()) {
-                (Some(i), Some(c)) => {
-                    crate::pr_info!("create platform {:?} child device {}", i.first(), c.first())
-                }
-                (Some(i), None) => {
-                    crate::pr_info!("create platform {:?} child device none", i.first())
-                }
-                _ => panic!("imposible"),
-            }
-        }
+// Logs the failure of `node`, which must not keep its siblings from being populated.
+fn of_platform_child_create(
+    node: OfNode<'static>,
+    matches: &[DeviceId],
+    parent: Option<&Arc<Mutex<PlatformDevice>>>,
+) {
+    if let Err(e) = of_platform_bus_create(node, matches, parent) {
+        pr_err!("failed to create platform device {}: {:?}", of_node_name(node), e);
+    }
+}
+
+/// Populates platform devices from the device tree.
+///
+/// Creates a platform device for every child of `root`, with `parent` as parent device.
+/// The children of the nodes matching `matches` are populated the same way, recursively,
+/// with the device of their bus as parent.
+///
+/// Nodes without compatible or disabled are skipped. A node whose device cannot be created
+/// is logged and skipped with its subtree, the other nodes are still populated.
+///
+/// A subtree must be populated only once, or its devices are created twice.
+pub fn of_platform_populate(
+    root: OfNode<'static>,
+    matches: &[DeviceId],
+    parent: Option<&Arc<Mutex<PlatformDevice>>>,
+) -> Result {
+    pr_debug!("populate {}", of_node_name(root));
+    for child in root.children() {
+        of_platform_child_create(child, matches, parent);
     }
     Ok(())
 }
+
+/// Populates the platform devices of the whole device tree, through the default buses.
+pub fn of_platform_default_populate_init() -> Result {
+    let root = of_root().ok_or(ENODEV)?;
+    of_platform_populate(root, &OF_DEFAULT_BUS_MATCH_TABLE, None)
+}
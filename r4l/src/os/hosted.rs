@@ -0,0 +1,231 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! The hosted backend: r4l on top of `std`, for running drivers in Linux userspace.
+//!
+//! Besides the [`OsInterface`] implementation, it offers the hooks a test needs to drive the
+//! simulated hardware: [`load_device_tree`] describes the devices with a blob built by
+//! [`fdt::FdtBuilder`], [`trigger_irq`] raises an interrupt and [`take_log_records`] returns
+//! what the driver logged.
+
+pub mod fdt;
+
+use super::{IrqHandler, OsInterface, RawLock, RawWaitQueue};
+use crate::dma::DataDirection;
+use crate::error::{code::*, Error, Result};
+use crate::print::LogLevel;
+use crate::sync::Mutex;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+/// Linux userspace, through `std`.
+pub struct HostedOs;
+
+/// A lock that yields the thread while waiting.
+///
+/// There are no interrupts to mask in userspace, so it also serves as the `SpinNoIrq` lock.
+pub struct RawMutex(AtomicBool);
+
+unsafe impl RawLock for RawMutex {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = RawMutex(AtomicBool::new(false));
+
+    fn lock(&self) {
+        while !self.try_lock() {
+            std::thread::yield_now();
+        }
+    }
+
+    fn try_lock(&self) -> bool {
+        self.0
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }
+
+    unsafe fn unlock(&self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+/// A wait queue of threads.
+pub struct WaitQueue {
+    lock: std::sync::Mutex<()>,
+    cond: std::sync::Condvar,
+}
+
+impl RawWaitQueue for WaitQueue {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = WaitQueue {
+        lock: std::sync::Mutex::new(()),
+        cond: std::sync::Condvar::new(),
+    };
+
+    fn wait_until(&self, condition: &dyn Fn() -> bool) {
+        let mut guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        while !condition() {
+            guard = self.cond.wait(guard).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    fn notify_all(&self) {
+        // Taking the lock orders the notification after a concurrent condition check.
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        self.cond.notify_all();
+    }
+}
+
+/// A message captured by the in-process log sink.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogRecord {
+    pub level: LogLevel,
+    pub message: String,
+}
+
+static LOG_SINK: Mutex<Vec<LogRecord>> = Mutex::new(Vec::new());
+
+/// Drains every message logged so far, oldest first.
+pub fn take_log_records() -> Vec<LogRecord> {
+    core::mem::take(&mut *LOG_SINK.lock())
+}
+
+/// Installs the device tree `blob`, once per process and before
+/// [`crate::init::driver_framework_init`].
+pub fn load_device_tree(blob: Vec<u8>) {
+    crate::of::of_init_fdt(Box::leak(blob.into_boxed_slice()));
+}
+
+struct IrqLine {
+    handler: IrqHandler,
+    enabled: bool,
+}
+
+// The simulated interrupt controller.
+static IRQ_LINES: Mutex<BTreeMap<u32, IrqLine>> = Mutex::new(BTreeMap::new());
+
+/// Raises `irq` on the simulated interrupt controller, running its handler synchronously.
+///
+/// Returns `false` if no handler is registered for the line or the line is masked.
+pub fn trigger_irq(irq: u32) -> bool {
+    // Do not hold the table lock while the handler runs, it may mask or free irqs.
+    let handler = match IRQ_LINES.lock().get(&irq) {
+        Some(line) if line.enabled => line.handler,
+        _ => return false,
+    };
+    handler(irq);
+    true
+}
+
+impl OsInterface for HostedOs {
+    /// A host errno, as found in `errno`.
+    type NativeError = i32;
+    type RawMutex = RawMutex;
+    type RawSpinNoIrq = RawMutex;
+    type RawWaitQueue = WaitQueue;
+
+    fn error_to_native(err: Error) -> i32 {
+        -err.to_errno()
+    }
+
+    fn error_from_native(err: i32) -> Error {
+        Error::from_errno(-err)
+    }
+
+    fn log(level: LogLevel, args: fmt::Arguments<'_>) {
+        let message = alloc::format!("{}", args);
+        std::eprintln!("[{:?}] {}", level, message);
+        LOG_SINK.lock().push(LogRecord { level, message });
+    }
+
+    fn request_irq(irq: u32, handler: IrqHandler) -> Result {
+        let mut lines = IRQ_LINES.lock();
+        if lines.contains_key(&irq) {
+            return Err(EBUSY);
+        }
+        lines.insert(irq, IrqLine { handler, enabled: true });
+        Ok(())
+    }
+
+    fn free_irq(irq: u32) {
+        IRQ_LINES.lock().remove(&irq);
+    }
+
+    fn set_irq_enabled(irq: u32, enabled: bool) {
+        if let Some(line) = IRQ_LINES.lock().get_mut(&irq) {
+            line.enabled = enabled;
+        }
+    }
+
+    fn current_time() -> Duration {
+        static BOOT: OnceLock<Instant> = OnceLock::new();
+        BOOT.get_or_init(Instant::now).elapsed()
+    }
+
+    fn busy_wait(dur: Duration) {
+        let end = Instant::now() + dur;
+        while Instant::now() < end {
+            core::hint::spin_loop();
+        }
+    }
+
+    fn sleep(dur: Duration) {
+        std::thread::sleep(dur);
+    }
+
+    fn spawn(name: &str, entry: Box<dyn FnOnce() + Send>) -> Result {
+        std::thread::Builder::new()
+            .name(String::from(name))
+            .spawn(entry)
+            .map_err(|_| ENOMEM)?;
+        Ok(())
+    }
+
+    fn phys_to_virt(paddr: usize) -> usize {
+        paddr
+    }
+
+    fn virt_to_phys(vaddr: usize) -> usize {
+        vaddr
+    }
+
+    fn ioremap(paddr: usize, _size: usize) -> Result<usize> {
+        // Tests hand in the address of a buffer standing in for the registers.
+        Ok(paddr)
+    }
+
+    fn iounmap(_vaddr: usize, _size: usize) {}
+
+    fn dma_alloc_coherent(size: usize, _coherent: bool) -> Result<(usize, usize)> {
+        let layout = dma_layout(size)?;
+        // SAFETY: `layout` has a non-zero size.
+        let vaddr = unsafe { std::alloc::alloc_zeroed(layout) } as usize;
+        if vaddr == 0 {
+            return Err(ENOMEM);
+        }
+        Ok((vaddr, Self::virt_to_phys(vaddr)))
+    }
+
+    fn dma_free_coherent(vaddr: usize, size: usize) {
+        if let Ok(layout) = dma_layout(size) {
+            // SAFETY: `vaddr` was returned by `dma_alloc_coherent` with the same layout.
+            unsafe { std::alloc::dealloc(vaddr as *mut u8, layout) }
+        }
+    }
+
+    // Userspace memory is coherent, there is nothing to sync.
+    fn dma_sync_for_device(_vaddr: usize, _size: usize, _dir: DataDirection) {}
+
+    fn dma_sync_for_cpu(_vaddr: usize, _size: usize, _dir: DataDirection) {}
+}
+
+fn dma_layout(size: usize) -> Result<std::alloc::Layout> {
+    if size == 0 {
+        return Err(EINVAL);
+    }
+    Ok(std::alloc::Layout::from_size_align(size, 4096)?)
+}
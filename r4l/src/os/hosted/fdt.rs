@@ -0,0 +1,171 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! A builder of flattened device tree blobs, for tests to describe the simulated hardware.
+//!
+//! See the devicetree specification, chapter 5, for the format.
+//!
+//! # Examples
+//!
+//! ```ignore
+//! use kernel::os::hosted::{self, fdt::FdtBuilder};
+//!
+//! let mut fdt = FdtBuilder::new();
+//! fdt.begin_node("")
+//!     .property_u32("#address-cells", 2)
+//!     .property_u32("#size-cells", 2)
+//!     .begin_node("serial@1000")
+//!     .property_strings("compatible", &["snps,dw-apb-uart"])
+//!     .end_node()
+//!     .end_node();
+//! hosted::load_device_tree(fdt.finish());
+//! ```
+
+use alloc::vec::Vec;
+
+const FDT_MAGIC: u32 = 0xd00d_feed;
+const FDT_VERSION: u32 = 17;
+const FDT_LAST_COMP_VERSION: u32 = 16;
+const FDT_HEADER_SIZE: usize = 40;
+// The memory reservation block only holds its terminating empty entry.
+const FDT_RSVMAP_SIZE: usize = 16;
+
+const FDT_BEGIN_NODE: u32 = 1;
+const FDT_END_NODE: u32 = 2;
+const FDT_PROP: u32 = 3;
+const FDT_END: u32 = 9;
+
+/// Builds a device tree blob node by node.
+///
+/// Nodes are opened with [`FdtBuilder::begin_node`], get their properties, their children,
+/// then are closed with [`FdtBuilder::end_node`]. The first node is the root, named `""`.
+#[derive(Default)]
+pub struct FdtBuilder {
+    structs: Vec<u8>,
+    strings: Vec<u8>,
+    depth: usize,
+}
+
+impl FdtBuilder {
+    /// Creates an empty builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Opens the node `name`, a child of the open node.
+    pub fn begin_node(&mut self, name: &str) -> &mut Self {
+        self.push_u32(FDT_BEGIN_NODE);
+        self.structs.extend_from_slice(name.as_bytes());
+        self.structs.push(0);
+        self.pad();
+        self.depth += 1;
+        self
+    }
+
+    /// Closes the open node.
+    pub fn end_node(&mut self) -> &mut Self {
+        assert!(self.depth > 0, "no open node");
+        self.push_u32(FDT_END_NODE);
+        self.depth -= 1;
+        self
+    }
+
+    /// Adds the property `name` with the raw `value` to the open node.
+    pub fn property(&mut self, name: &str, value: &[u8]) -> &mut Self {
+        assert!(self.depth > 0, "no open node");
+        let name_offset = self.string_offset(name);
+        self.push_u32(FDT_PROP);
+        self.push_u32(value.len() as u32);
+        self.push_u32(name_offset);
+        self.structs.extend_from_slice(value);
+        self.pad();
+        self
+    }
+
+    /// Adds the boolean property `name`, like `interrupt-controller`.
+    pub fn property_empty(&mut self, name: &str) -> &mut Self {
+        self.property(name, &[])
+    }
+
+    /// Adds the property `name` with one cell.
+    pub fn property_u32(&mut self, name: &str, value: u32) -> &mut Self {
+        self.property_cells(name, &[value])
+    }
+
+    /// Adds the property `name` with the cells `values`, like `reg` or `interrupts`.
+    pub fn property_cells(&mut self, name: &str, values: &[u32]) -> &mut Self {
+        let value: Vec<u8> = values.iter().flat_map(|cell| cell.to_be_bytes()).collect();
+        self.property(name, &value)
+    }
+
+    /// Adds the property `name` with a list of strings, like `compatible`.
+    pub fn property_strings(&mut self, name: &str, values: &[&str]) -> &mut Self {
+        let mut value = Vec::new();
+        for string in values {
+            value.extend_from_slice(string.as_bytes());
+            value.push(0);
+        }
+        self.property(name, &value)
+    }
+
+    /// Returns the blob, every node must be closed.
+    pub fn finish(&mut self) -> Vec<u8> {
+        assert!(self.depth == 0, "node left open");
+        let mut structs = core::mem::take(&mut self.structs);
+        structs.extend_from_slice(&FDT_END.to_be_bytes());
+        let strings = core::mem::take(&mut self.strings);
+
+        let off_mem_rsvmap = FDT_HEADER_SIZE;
+        let off_dt_struct = off_mem_rsvmap + FDT_RSVMAP_SIZE;
+        let off_dt_strings = off_dt_struct + structs.len();
+        let total_size = off_dt_strings + strings.len();
+
+        let mut blob = Vec::with_capacity(total_size);
+        for field in [
+            FDT_MAGIC,
+            total_size as u32,
+            off_dt_struct as u32,
+            off_dt_strings as u32,
+            off_mem_rsvmap as u32,
+            FDT_VERSION,
+            FDT_LAST_COMP_VERSION,
+            // boot_cpuid_phys
+            0,
+            strings.len() as u32,
+            structs.len() as u32,
+        ] {
+            blob.extend_from_slice(&field.to_be_bytes());
+        }
+        blob.resize(off_dt_struct, 0);
+        blob.extend_from_slice(&structs);
+        blob.extend_from_slice(&strings);
+        blob
+    }
+
+    fn push_u32(&mut self, value: u32) {
+        self.structs.extend_from_slice(&value.to_be_bytes());
+    }
+
+    // Aligns the structure block on the next cell.
+    fn pad(&mut self) {
+        let len = self.structs.len().next_multiple_of(4);
+        self.structs.resize(len, 0);
+    }
+
+    // Returns the offset of `name` in the strings block, adding it if needed.
+    fn string_offset(&mut self, name: &str) -> u32 {
+        let mut offset = 0;
+        for string in self.strings.split(|&b| b == 0) {
+            if offset == self.strings.len() {
+                break;
+            }
+            if string == name.as_bytes() {
+                return offset as u32;
+            }
+            offset += string.len() + 1;
+        }
+        let offset = self.strings.len();
+        self.strings.extend_from_slice(name.as_bytes());
+        self.strings.push(0);
+        offset as u32
+    }
+}
@@ -0,0 +1,141 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! The interface between r4l and the OS it runs on.
+//!
+//! Everything r4l needs from an OS is collected in [`OsInterface`]. Porting r4l to a new OS
+//! means adding one implementation of it, next to the existing backends, and selecting it
+//! with a cargo feature:
+//! - `starry`: [`starry::StarryOs`]
+//! - `hosted`: [`hosted::HostedOs`], `std` based, for running drivers in Linux userspace
+//!
+//! The rest of r4l only uses the backend through the [`Os`] alias.
+
+use crate::dma::DataDirection;
+use crate::error::{Error, Result};
+use crate::print::LogLevel;
+use alloc::boxed::Box;
+use core::fmt;
+use core::time::Duration;
+
+#[cfg(feature = "starry")]
+pub mod starry;
+#[cfg(feature = "starry")]
+pub type Os = starry::StarryOs;
+
+#[cfg(feature = "hosted")]
+pub mod hosted;
+#[cfg(feature = "hosted")]
+pub type Os = hosted::HostedOs;
+
+#[cfg(all(feature = "starry", feature = "hosted"))]
+compile_error!("features `starry` and `hosted` select different OS backends, enable only one");
+
+#[cfg(not(any(feature = "starry", feature = "hosted")))]
+compile_error!("r4l needs an OS backend, enable the `starry` or `hosted` feature");
+
+/// An interrupt handler registered with the OS, called with the irq number.
+pub type IrqHandler = fn(u32);
+
+/// A raw lock, without the data it protects.
+///
+/// The lock types in [`crate::sync`] are built on the raw locks of the OS.
+///
+/// # Safety
+///
+/// Implementers must guarantee that at most one caller holds the lock at a time: `lock` does
+/// not return and `try_lock` returns `false` while the lock is held.
+pub unsafe trait RawLock {
+    /// The unlocked state, usable to initialize `static` locks.
+    const INIT: Self;
+
+    /// Acquires the lock, waiting until it is available.
+    fn lock(&self);
+
+    /// Tries to acquire the lock without waiting, returns `true` on success.
+    fn try_lock(&self) -> bool;
+
+    /// Releases the lock.
+    ///
+    /// # Safety
+    ///
+    /// The caller must hold the lock.
+    unsafe fn unlock(&self);
+}
+
+/// A raw wait queue, tasks sleep on it until a condition holds.
+///
+/// [`crate::sync::WaitQueue`] is built on the raw wait queue of the OS.
+pub trait RawWaitQueue {
+    /// The empty queue, usable to initialize `static` queues.
+    const INIT: Self;
+
+    /// Puts the current task to sleep until `condition` returns true.
+    ///
+    /// `condition` is checked again each time the queue is notified.
+    fn wait_until(&self, condition: &dyn Fn() -> bool);
+
+    /// Wakes every task sleeping on the queue up, usable in interrupt context.
+    fn notify_all(&self);
+}
+
+/// The services an OS provides to r4l.
+pub trait OsInterface {
+    /// The error type used natively by the OS.
+    type NativeError;
+    /// A lock that may sleep while waiting, see [`crate::sync::Mutex`].
+    type RawMutex: RawLock + Send + Sync;
+    /// A spin lock that masks local interrupts while held, see [`crate::sync::SpinNoIrq`].
+    type RawSpinNoIrq: RawLock + Send + Sync;
+    /// A wait queue, see [`crate::sync::WaitQueue`].
+    type RawWaitQueue: RawWaitQueue + Send + Sync;
+
+    /// Converts an r4l error to the native error of the OS.
+    fn error_to_native(err: Error) -> Self::NativeError;
+    /// Converts a native error of the OS to an r4l error.
+    fn error_from_native(err: Self::NativeError) -> Error;
+
+    /// Writes a log message.
+    fn log(level: LogLevel, args: fmt::Arguments<'_>);
+
+    /// Installs `handler` for `irq` and enables the line.
+    fn request_irq(irq: u32, handler: IrqHandler) -> Result;
+    /// Removes the handler of `irq` and disables the line.
+    fn free_irq(irq: u32);
+    /// Unmasks or masks `irq` at the interrupt controller.
+    fn set_irq_enabled(irq: u32, enabled: bool);
+
+    /// Returns the monotonic time since boot.
+    fn current_time() -> Duration;
+    /// Spins for `dur`, usable in atomic context.
+    fn busy_wait(dur: Duration);
+    /// Puts the current task to sleep for at least `dur`.
+    fn sleep(dur: Duration);
+    /// Starts a kernel task named `name` running `entry`, the task ends when `entry`
+    /// returns.
+    fn spawn(name: &str, entry: Box<dyn FnOnce() + Send>) -> Result;
+
+    /// Converts a physical address to a kernel virtual address.
+    fn phys_to_virt(paddr: usize) -> usize;
+    /// Converts a kernel virtual address to a physical address.
+    fn virt_to_phys(vaddr: usize) -> usize;
+    /// Maps `size` bytes of device memory at `paddr`, returns the virtual address.
+    fn ioremap(paddr: usize, size: usize) -> Result<usize>;
+    /// Unmaps a mapping returned by [`OsInterface::ioremap`].
+    fn iounmap(vaddr: usize, size: usize);
+
+    /// Allocates `size` bytes of physically contiguous, zeroed memory for DMA, returns its
+    /// virtual and physical addresses.
+    ///
+    /// For a device that is not `coherent`, the memory must not be cached, or be kept in
+    /// sync by the OS. Fails with [`crate::error::code::ENOTSUPP`] if the OS cannot do
+    /// either.
+    fn dma_alloc_coherent(size: usize, coherent: bool) -> Result<(usize, usize)>;
+    /// Frees memory returned by [`OsInterface::dma_alloc_coherent`].
+    fn dma_free_coherent(vaddr: usize, size: usize);
+    /// Makes the CPU writes to `[vaddr, vaddr + size)` visible to a device that is not
+    /// coherent, before it accesses the buffer in direction `dir`.
+    fn dma_sync_for_device(vaddr: usize, size: usize, dir: DataDirection);
+    /// Makes the device writes to `[vaddr, vaddr + size)` visible to the CPU, after a
+    /// device that is not coherent accessed the buffer in direction `dir`.
+    fn dma_sync_for_cpu(vaddr: usize, size: usize, dir: DataDirection);
+}
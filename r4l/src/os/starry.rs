@@ -0,0 +1,348 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! The Starry OS backend.
+
+use super::{IrqHandler, OsInterface, RawLock, RawWaitQueue};
+use crate::dma::DataDirection;
+use crate::error::{code::*, Error, Result};
+use crate::print::LogLevel;
+use crate::sync::SpinNoIrq;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use axerrno::AxError;
+use core::fmt;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+
+/// Starry OS.
+pub struct StarryOs;
+
+// The handlers of the lines requested through r4l, `None` once freed.
+//
+// Starry has no way to remove a handler, so each line gets `dispatch_irq` the first time it is
+// requested and keeps it: a freed line can be requested again through r4l, but not directly
+// from axhal.
+static IRQ_HANDLERS: SpinNoIrq<BTreeMap<u32, Option<IrqHandler>>> = SpinNoIrq::new(BTreeMap::new());
+
+fn dispatch_irq(irq: u32) {
+    // Do not hold the table while the handler runs, it may free or request irqs.
+    let handler = IRQ_HANDLERS.lock().get(&irq).copied().flatten();
+    if let Some(handler) = handler {
+        handler(irq);
+    }
+}
+
+/// The mutex of Starry: waiters sleep, and the owner inherits their priority.
+pub struct RawMutex(axsync::RawMutex);
+
+unsafe impl RawLock for RawMutex {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = RawMutex(<axsync::RawMutex as lock_api::RawMutex>::INIT);
+
+    fn lock(&self) {
+        lock_api::RawMutex::lock(&self.0);
+    }
+
+    fn try_lock(&self) -> bool {
+        lock_api::RawMutex::try_lock(&self.0)
+    }
+
+    unsafe fn unlock(&self) {
+        // SAFETY: The caller holds the lock.
+        unsafe { lock_api::RawMutex::unlock(&self.0) }
+    }
+}
+
+/// A spin lock that disables local interrupts while held.
+pub struct RawSpinNoIrq {
+    locked: AtomicBool,
+    // Interrupt state of the holder before it took the lock.
+    irq_enabled: AtomicBool,
+}
+
+impl RawSpinNoIrq {
+    fn try_acquire(&self) -> bool {
+        self.locked
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }
+}
+
+unsafe impl RawLock for RawSpinNoIrq {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = RawSpinNoIrq {
+        locked: AtomicBool::new(false),
+        irq_enabled: AtomicBool::new(false),
+    };
+
+    fn lock(&self) {
+        let irq_enabled = axhal::arch::irqs_enabled();
+        axhal::arch::disable_irqs();
+        while !self.try_acquire() {
+            core::hint::spin_loop();
+        }
+        self.irq_enabled.store(irq_enabled, Ordering::Relaxed);
+    }
+
+    fn try_lock(&self) -> bool {
+        let irq_enabled = axhal::arch::irqs_enabled();
+        axhal::arch::disable_irqs();
+        if self.try_acquire() {
+            self.irq_enabled.store(irq_enabled, Ordering::Relaxed);
+            return true;
+        }
+        if irq_enabled {
+            axhal::arch::enable_irqs();
+        }
+        false
+    }
+
+    unsafe fn unlock(&self) {
+        let irq_enabled = self.irq_enabled.load(Ordering::Relaxed);
+        self.locked.store(false, Ordering::Release);
+        if irq_enabled {
+            axhal::arch::enable_irqs();
+        }
+    }
+}
+
+/// A wait queue of Starry tasks.
+pub struct WaitQueue(axtask::WaitQueue);
+
+impl RawWaitQueue for WaitQueue {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = WaitQueue(axtask::WaitQueue::new());
+
+    fn wait_until(&self, condition: &dyn Fn() -> bool) {
+        self.0.wait_until(condition);
+    }
+
+    fn notify_all(&self) {
+        // Called from interrupt handlers, the woken tasks run at the next schedule.
+        self.0.notify_all(false);
+    }
+}
+
+/// The stack size of the tasks started by r4l.
+const TASK_STACK_SIZE: usize = 0x10000;
+
+impl OsInterface for StarryOs {
+    type NativeError = AxError;
+    type RawMutex = RawMutex;
+    type RawSpinNoIrq = RawSpinNoIrq;
+    type RawWaitQueue = WaitQueue;
+
+    // Follows the fallback mapping documented in `crate::error`.
+    fn error_to_native(err: Error) -> AxError {
+        match err {
+            EPERM | EACCES | EROFS => AxError::PermissionDenied,
+            ENOENT | ESRCH | ENXIO | ENODEV | ENODATA => AxError::NotFound,
+            EINTR | ERESTART | ERESTARTSYS | ERESTARTNOINTR | ERESTARTNOHAND
+            | ERESTART_RESTARTBLOCK => AxError::Interrupted,
+            EAGAIN | EPROBE_DEFER | EINPROGRESS => AxError::Again,
+            ENOMEM | ENOBUFS => AxError::NoMemory,
+            EFAULT => AxError::BadState,
+            EBUSY | ETXTBSY | EALREADY => AxError::Busy,
+            EEXIST => AxError::AlreadyExists,
+            ENOTDIR => AxError::NotADirectory,
+            EINVAL | EDOM | ERANGE | EOVERFLOW | EILSEQ => AxError::InvalidInput,
+            ENOSPC | EFBIG | EDQUOT => AxError::StorageFull,
+            ENOSYS | ENOTTY | EOPNOTSUPP | ENOTSUPP => AxError::Unsupported,
+            _ => AxError::Io,
+        }
+    }
+
+    fn error_from_native(err: AxError) -> Error {
+        match err {
+            AxError::PermissionDenied => EPERM,
+            AxError::NotFound => ENOENT,
+            AxError::Interrupted => EINTR,
+            AxError::Again => EAGAIN,
+            AxError::NoMemory => ENOMEM,
+            AxError::BadState => EFAULT,
+            AxError::Busy => EBUSY,
+            AxError::AlreadyExists => EEXIST,
+            AxError::NotADirectory => ENOTDIR,
+            AxError::InvalidInput => EINVAL,
+            AxError::StorageFull => ENOSPC,
+            AxError::Unsupported => ENOTSUPP,
+            _ => EIO,
+        }
+    }
+
+    fn log(level: LogLevel, args: fmt::Arguments<'_>) {
+        match level {
+            LogLevel::Emerge | LogLevel::Alert | LogLevel::Crit | LogLevel::Error => {
+                axlog::error!("{}", args)
+            }
+            LogLevel::Warn => axlog::warn!("{}", args),
+            LogLevel::Notice | LogLevel::Info | LogLevel::Cont => axlog::info!("{}", args),
+            LogLevel::Debug => axlog::debug!("{}", args),
+        }
+    }
+
+    fn request_irq(irq: u32, handler: IrqHandler) -> Result {
+        let mut handlers = IRQ_HANDLERS.lock();
+        match handlers.get(&irq) {
+            Some(Some(_)) => return Err(EBUSY),
+            // Freed before, `dispatch_irq` is still registered for the line.
+            Some(None) => {}
+            None => {
+                if !axhal::irq::register_handler(irq as usize, dispatch_irq) {
+                    return Err(EBUSY);
+                }
+            }
+        }
+        handlers.insert(irq, Some(handler));
+        drop(handlers);
+        axhal::irq::set_enable(irq as usize, true);
+        Ok(())
+    }
+
+    fn free_irq(irq: u32) {
+        axhal::irq::set_enable(irq as usize, false);
+        if let Some(handler) = IRQ_HANDLERS.lock().get_mut(&irq) {
+            *handler = None;
+        }
+    }
+
+    fn set_irq_enabled(irq: u32, enabled: bool) {
+        axhal::irq::set_enable(irq as usize, enabled);
+    }
+
+    fn current_time() -> Duration {
+        axhal::time::current_time()
+    }
+
+    fn busy_wait(dur: Duration) {
+        axhal::time::busy_wait(dur);
+    }
+
+    fn sleep(dur: Duration) {
+        axtask::sleep(dur);
+    }
+
+    fn spawn(name: &str, entry: Box<dyn FnOnce() + Send>) -> Result {
+        axtask::spawn_raw(entry, String::from(name), TASK_STACK_SIZE);
+        Ok(())
+    }
+
+    fn phys_to_virt(paddr: usize) -> usize {
+        axhal::mem::phys_to_virt(paddr.into()).as_usize()
+    }
+
+    fn virt_to_phys(vaddr: usize) -> usize {
+        axhal::mem::virt_to_phys(vaddr.into()).as_usize()
+    }
+
+    fn ioremap(paddr: usize, _size: usize) -> Result<usize> {
+        // Device memory is part of the kernel linear mapping.
+        Ok(Self::phys_to_virt(paddr))
+    }
+
+    fn iounmap(_vaddr: usize, _size: usize) {}
+
+    fn dma_alloc_coherent(size: usize, coherent: bool) -> Result<(usize, usize)> {
+        // The linear mapping is cached and Starry cannot remap memory uncached yet: devices
+        // that do not snoop the caches must use streaming mappings, kept in sync by
+        // `dma_sync_*`.
+        if !coherent && cache::NEEDS_MAINTENANCE {
+            return Err(ENOTSUPP);
+        }
+        let layout = dma_layout(size)?;
+        // SAFETY: `layout` has a non-zero size.
+        let vaddr = unsafe { alloc::alloc::alloc_zeroed(layout) } as usize;
+        if vaddr == 0 {
+            return Err(ENOMEM);
+        }
+        Ok((vaddr, Self::virt_to_phys(vaddr)))
+    }
+
+    fn dma_free_coherent(vaddr: usize, size: usize) {
+        if let Ok(layout) = dma_layout(size) {
+            // SAFETY: `vaddr` was returned by `dma_alloc_coherent` with the same layout.
+            unsafe { alloc::alloc::dealloc(vaddr as *mut u8, layout) }
+        }
+    }
+
+    fn dma_sync_for_device(vaddr: usize, size: usize, dir: DataDirection) {
+        match dir {
+            // The device only writes, stale dirty lines must not be evicted over its data.
+            DataDirection::FromDevice => cache::clean_invalidate(vaddr, size),
+            DataDirection::ToDevice => cache::clean(vaddr, size),
+            DataDirection::Bidirectional => cache::clean_invalidate(vaddr, size),
+            DataDirection::None => {}
+        }
+    }
+
+    fn dma_sync_for_cpu(vaddr: usize, size: usize, dir: DataDirection) {
+        match dir {
+            DataDirection::FromDevice | DataDirection::Bidirectional => {
+                cache::clean_invalidate(vaddr, size)
+            }
+            DataDirection::ToDevice | DataDirection::None => {}
+        }
+    }
+}
+
+fn dma_layout(size: usize) -> Result<core::alloc::Layout> {
+    if size == 0 {
+        return Err(EINVAL);
+    }
+    let align = axhal::mem::PAGE_SIZE_4K;
+    Ok(core::alloc::Layout::from_size_align(size, align)?)
+}
+
+/// Data cache maintenance by virtual address.
+#[cfg(target_arch = "aarch64")]
+mod cache {
+    use core::arch::asm;
+
+    /// DMA of devices that are not coherent is not snooped.
+    pub(super) const NEEDS_MAINTENANCE: bool = true;
+
+    fn line_size() -> usize {
+        let ctr: u64;
+        // SAFETY: Reading CTR_EL0 has no side effect.
+        unsafe { asm!("mrs {}, ctr_el0", out(reg) ctr) };
+        4 << ((ctr >> 16) & 0xf)
+    }
+
+    fn for_each_line(vaddr: usize, size: usize, mut op: impl FnMut(usize)) {
+        let line = line_size();
+        let mut addr = vaddr & !(line - 1);
+        while addr < vaddr + size {
+            op(addr);
+            addr += line;
+        }
+        // SAFETY: A barrier has no side effect on memory.
+        unsafe { asm!("dsb sy") };
+    }
+
+    pub(super) fn clean(vaddr: usize, size: usize) {
+        // SAFETY: Cleaning writes dirty lines back, the memory content is unchanged.
+        for_each_line(vaddr, size, |addr| unsafe {
+            asm!("dc cvac, {}", in(reg) addr)
+        });
+    }
+
+    pub(super) fn clean_invalidate(vaddr: usize, size: usize) {
+        // SAFETY: Dirty lines are written back before being dropped, nothing is lost.
+        for_each_line(vaddr, size, |addr| unsafe {
+            asm!("dc civac, {}", in(reg) addr)
+        });
+    }
+}
+
+/// Data cache maintenance by virtual address.
+///
+/// The other architectures Starry runs on snoop DMA, there is nothing to do.
+#[cfg(not(target_arch = "aarch64"))]
+mod cache {
+    pub(super) const NEEDS_MAINTENANCE: bool = false;
+
+    pub(super) fn clean(_vaddr: usize, _size: usize) {}
+
+    pub(super) fn clean_invalidate(_vaddr: usize, _size: usize) {}
+}
@@ -0,0 +1,163 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Pin control: multiplexing and configuration of pins.
+//!
+//! C header: [`include/linux/pinctrl/consumer.h`](../../../../include/linux/pinctrl/consumer.h)
+//!
+//! Pin controller drivers implement [`PinctrlOps`] and register it for their device tree
+//! node with [`pinctrl_register`]. A device lists its pin states in `pinctrl-names`, state
+//! `N` being the configuration nodes of the `pinctrl-N` phandle list. These nodes are
+//! children of the controller, which applies them.
+//!
+//! The `default` state is selected by the platform bus before the probe of the device. A
+//! device with an `init` state gets it before the probe instead, and `default` after a
+//! successful probe unless the driver switched states meanwhile. Drivers switch states with
+//! [`crate::device::Device::pinctrl_select_state`].
+
+use crate::of::{
+    of_get_parent, of_get_phandle, of_node_name, of_property_count_cells, of_property_present,
+    of_property_strings, parse_phandle,
+};
+use crate::prelude::*;
+use crate::sync::{Arc, Mutex};
+use alloc::{format, string::String};
+use of::OfNode;
+
+/// The state selected before probe, or after it if the device has an `init` state.
+pub const PINCTRL_STATE_DEFAULT: &str = "default";
+/// The state selected before probe instead of `default`, for devices whose pins may only
+/// be muxed once the driver set the device up.
+pub const PINCTRL_STATE_INIT: &str = "init";
+/// The state of a device that is not used.
+pub const PINCTRL_STATE_SLEEP: &str = "sleep";
+/// The state of an idle device.
+pub const PINCTRL_STATE_IDLE: &str = "idle";
+
+/// The operations of a pin controller.
+pub trait PinctrlOps: Send + Sync {
+    /// Applies the configuration node `config`, a child of the controller node, e.g. sets
+    /// the function of a group of pins and their bias.
+    fn apply(&self, config: OfNode<'static>) -> Result;
+}
+
+// Registered pin controllers, keyed by the phandle of their node.
+static PINCTRL_DEVS: Mutex<Vec<(u32, Arc<dyn PinctrlOps>)>> = Mutex::new(Vec::new());
+
+/// Registers `ops` as the pin controller of `node`.
+pub fn pinctrl_register(node: OfNode<'static>, ops: Arc<dyn PinctrlOps>) -> Result {
+    let phandle = of_get_phandle(node).ok_or(EINVAL)?;
+    let mut pctldevs = PINCTRL_DEVS.lock();
+    if pctldevs.iter().any(|(ph, _)| *ph == phandle) {
+        return Err(EEXIST);
+    }
+    pctldevs.try_reserve(1)?;
+    pctldevs.push((phandle, ops));
+    pr_debug!("pin controller {} registered", of_node_name(node));
+    Ok(())
+}
+
+/// Removes the pin controller of `node`.
+pub fn pinctrl_unregister(node: OfNode<'static>) {
+    if let Some(phandle) = of_get_phandle(node) {
+        PINCTRL_DEVS.lock().retain(|(ph, _)| *ph != phandle);
+    }
+}
+
+/// Returns the controller of the configuration node `config`, its closest registered
+/// ancestor.
+fn pinctrl_find(config: OfNode<'static>) -> Result<Arc<dyn PinctrlOps>> {
+    let pctldevs = PINCTRL_DEVS.lock();
+    let mut np = of_get_parent(config);
+    while let Some(node) = np {
+        if let Some(phandle) = of_get_phandle(node) {
+            if let Some((_, ops)) = pctldevs.iter().find(|(ph, _)| *ph == phandle) {
+                return Ok(ops.clone());
+            }
+        }
+        np = of_get_parent(node);
+    }
+    // The driver of the controller may not be probed yet.
+    Err(EPROBE_DEFER)
+}
+
+struct PinctrlState {
+    name: String,
+    configs: Vec<OfNode<'static>>,
+}
+
+/// The pin states of a device.
+pub struct Pinctrl {
+    states: Vec<PinctrlState>,
+    // The index of the selected state.
+    current: Mutex<Option<usize>>,
+}
+
+impl Pinctrl {
+    /// Parses the pin states of `node`.
+    ///
+    /// Fails with [`ENOENT`] if the node has no `pinctrl-0`.
+    pub fn get(node: OfNode<'static>) -> Result<Self> {
+        let names: Vec<&'static str> = of_property_strings(node, "pinctrl-names").collect();
+        let mut states = Vec::new();
+        for index in 0.. {
+            let prop = format!("pinctrl-{}", index);
+            if !of_property_present(node, &prop) {
+                break;
+            }
+            let mut configs = Vec::new();
+            for i in 0..of_property_count_cells(node, &prop) {
+                configs.try_reserve(1)?;
+                configs.push(parse_phandle(node, &prop, i)?);
+            }
+            // A state without name is named after its index.
+            let name = match names.get(index) {
+                Some(name) => String::from(*name),
+                None => format!("{}", index),
+            };
+            states.try_reserve(1)?;
+            states.push(PinctrlState { name, configs });
+        }
+        if states.is_empty() {
+            return Err(ENOENT);
+        }
+        Ok(Self {
+            states,
+            current: Mutex::new(None),
+        })
+    }
+
+    /// Returns true if the device has the state `name`.
+    pub fn has_state(&self, name: &str) -> bool {
+        self.states.iter().any(|state| state.name == name)
+    }
+
+    /// Returns the name of the last state selected.
+    pub fn current_state(&self) -> Option<&str> {
+        let current = *self.current.lock();
+        current.map(|index| self.states[index].name.as_str())
+    }
+
+    /// Applies every configuration of the state `name`.
+    ///
+    /// Fails with [`ENODEV`] if there is no such state and with [`EPROBE_DEFER`] if a pin
+    /// controller of the state is not registered yet.
+    pub fn select_state(&self, name: &str) -> Result {
+        let index = self
+            .states
+            .iter()
+            .position(|state| state.name == name)
+            .ok_or(ENODEV)?;
+        let state = &self.states[index];
+        // Find every controller before touching any pin.
+        let mut ops = Vec::new();
+        for config in state.configs.iter() {
+            ops.try_reserve(1)?;
+            ops.push(pinctrl_find(*config)?);
+        }
+        for (config, ops) in state.configs.iter().zip(ops) {
+            ops.apply(*config)?;
+        }
+        *self.current.lock() = Some(index);
+        Ok(())
+    }
+}
@@ -3,15 +3,18 @@ use alloc::collections::VecDeque;
 
 use crate::bus::BusType;
 use crate::device::DeviceOps;
-use crate::error::*;
-use crate::of::DeviceId::Compatible;
-use crate::pr_info;
+use crate::error::{code::{ENODEV, EPROBE_DEFER}, *};
+use crate::of::DeviceId::{self, Compatible};
 use crate::prelude::Vec;
 use crate::sync::{Arc, Mutex};
+use crate::{pr_err, pr_info, pr_warn};
+use core::sync::atomic::{AtomicBool, Ordering};
 
 pub struct PlatformBus {
     devices: VecDeque<Arc<Mutex<PlatformDevice>>>,
     drivers: VecDeque<Arc<Mutex<PlatformDriver>>>,
+    // Devices whose probe asked to be retried later, with the driver that matched them.
+    deferred: VecDeque<(Arc<Mutex<PlatformDriver>>, Arc<Mutex<PlatformDevice>>)>,
 }
 
 impl PlatformBus {
@@ -19,6 +22,7 @@ impl PlatformBus {
         PlatformBus {
             devices: VecDeque::new(),
             drivers: VecDeque::new(),
+            deferred: VecDeque::new(),
         }
     }
 }
@@ -38,21 +42,42 @@ impl BusType for PlatformBus {
             .id_table()
             .expect("platform driver not define Compatible Table");
         for dev in self.devices.iter() {
-            for id in table {
-                match id {
-                    Compatible(id) => {
-                        if dev.lock().compatible_match(id) {
-                            pr_info!("driver : {} device matched", id);
-                            matched_pdev.push(dev.clone());
-                        }
-                    }
-                    _ => panic!("invalid id table"),
-                }
+            let pdev = dev.lock();
+            if pdev.is_bound() {
+                continue;
+            }
+            if let Some((index, _)) = of_match_device(table, &pdev) {
+                pr_info!("driver : {:?} device {} matched", table[index], pdev.name());
+                matched_pdev.push(dev.clone());
             }
         }
         matched_pdev
     }
 
+    fn bus_device_match(&self, dev: Self::Device) -> Option<Self::Driver> {
+        let pdev = dev.lock();
+        if pdev.is_bound() {
+            return None;
+        }
+        // (driver, table index, score) of the best match so far
+        let mut best: Option<(&Self::Driver, usize, usize)> = None;
+        for drv in self.drivers.iter() {
+            let table = drv
+                .lock()
+                .id_table()
+                .expect("platform driver not define Compatible Table");
+            if let Some((index, score)) = of_match_device(table, &pdev) {
+                // On a tie the driver registered first wins.
+                if best.map_or(true, |(_, _, best_score)| score < best_score) {
+                    best = Some((drv, index, score));
+                }
+            }
+        }
+        let (drv, _, _) = best?;
+        pr_info!("device : {} driver matched", pdev.name());
+        Some(drv.clone())
+    }
+
     fn add_device(&mut self, device: Self::Device) -> Result {
         self.devices.push_back(device);
         Ok(())
@@ -62,12 +87,58 @@ impl BusType for PlatformBus {
         self.drivers.push_back(driver);
         Ok(())
     }
+
+    fn remove_device(&mut self, device: Self::Device) -> Result {
+        let len = self.devices.len();
+        self.devices.retain(|dev| !Arc::ptr_eq(dev, &device));
+        self.deferred.retain(|(_, dev)| !Arc::ptr_eq(dev, &device));
+        if self.devices.len() == len {
+            return Err(ENODEV);
+        }
+        Ok(())
+    }
+
+    fn remove_driver(&mut self, driver: Self::Driver) -> Result {
+        let len = self.drivers.len();
+        self.drivers.retain(|drv| !Arc::ptr_eq(drv, &driver));
+        self.deferred.retain(|(drv, _)| !Arc::ptr_eq(drv, &driver));
+        if self.drivers.len() == len {
+            return Err(ENODEV);
+        }
+        Ok(())
+    }
+}
+
+/// Finds the entry of `table` that matches `pdev` best.
+///
+/// Like Linux `of_match_node()`, the score of an entry is the position of its compatible
+/// string in the compatible list of the device, so the more specific strings listed first
+/// win over generic fallbacks. Returns the index of the entry and its score, lower is better.
+fn of_match_device(table: &[DeviceId], pdev: &PlatformDevice) -> Option<(usize, usize)> {
+    table
+        .iter()
+        .enumerate()
+        .filter_map(|(index, id)| match id {
+            Compatible(id) => pdev.compatible_index(id).map(|score| (index, score)),
+        })
+        .min_by_key(|&(_, score)| score)
 }
 
 static PLATFORM_BUS: Mutex<PlatformBus> = Mutex::new(PlatformBus::new());
 
+/// Adds a platform device to the bus and probes it with the first matching driver.
+///
+/// Drivers registered before the device are matched here, drivers registered after it find
+/// it in [`platform_driver_register`], so binding does not depend on the registration order.
 pub fn platform_device_register(device: <PlatformBus as BusType>::Device) -> Result {
-    PLATFORM_BUS.lock().add_device(device)?;
+    let mut bus = PLATFORM_BUS.lock();
+    bus.add_device(device.clone())?;
+    let matched_pdrv = bus.bus_device_match(device.clone());
+    // before probe, unlock bus
+    drop(bus);
+    if let Some(pdrv) = matched_pdrv {
+        driver_probe_device(&pdrv, device);
+    }
     Ok(())
 }
 
@@ -77,11 +148,157 @@ pub fn platform_driver_register(pdrv: <PlatformBus as BusType>::Driver) -> Resul
     let matchde_pdev = bus.bus_driver_match(pdrv.clone());
     // before probe, unlock bus
     drop(bus);
+    // A device failing to probe does not fail the registration of the driver.
     for pdev in matchde_pdev {
-        match pdrv.lock().probe {
-            Some(fn_probe) => fn_probe(pdev)?,
-            None => panic!("pdev not have probe call back"),
+        driver_probe_device(&pdrv, pdev);
+    }
+    Ok(())
+}
+
+/// Probes `pdev` with `pdrv`.
+///
+/// A device whose probe returns `EPROBE_DEFER` is parked on the deferred list, and every
+/// successful probe retries the parked devices, the resource they waited for may just have
+/// shown up. Returns `true` if the device is bound.
+fn driver_probe_device(
+    pdrv: &<PlatformBus as BusType>::Driver,
+    pdev: <PlatformBus as BusType>::Device,
+) -> bool {
+    let (probe, table) = {
+        let drv = pdrv.lock();
+        let table = drv
+            .id_table()
+            .expect("platform driver not define Compatible Table");
+        (drv.probe.expect("pdev not have probe call back"), table)
+    };
+    {
+        let mut dev = pdev.lock();
+        if dev.is_bound() {
+            return false;
+        }
+        // The index is recomputed for `pdrv` on every probe, the device may have been
+        // matched against another driver since it was deferred.
+        match of_match_device(table, &dev) {
+            Some((index, _)) => dev.set_matched_id_index(Some(index)),
+            None => return false,
+        }
+    }
+    // The pins are set up before the probe, like Linux `pinctrl_bind_pins()`.
+    let ret = pdev.lock().pinctrl_bind_pins();
+    match ret.and_then(|()| probe(pdev.clone())) {
+        Ok(()) => {
+            let mut dev = pdev.lock();
+            if let Err(e) = dev.pinctrl_init_done() {
+                pr_warn!("platform {}: cannot select the default pin state: {:?}", dev.name(), e);
+            }
+            dev.bind_driver(pdrv.clone());
+            drop(dev);
+            driver_deferred_probe_trigger();
+            true
+        }
+        Err(e) => {
+            // The release actions run unlocked, they may lock the device.
+            let devres = {
+                let mut dev = pdev.lock();
+                dev.set_matched_id_index(None);
+                dev.pinctrl_unbind_pins();
+                dev.devres_take_all()
+            };
+            devres.release();
+            if e == EPROBE_DEFER {
+                pr_info!("platform {}: probe deferred", pdev.lock().name());
+                PLATFORM_BUS.lock().deferred.push_back((pdrv.clone(), pdev));
+            } else {
+                pr_err!("platform {}: probe failed with error {:?}", pdev.lock().name(), e);
+            }
+            false
+        }
+    }
+}
+
+/// Unbinds `pdev` from its driver.
+///
+/// Calls the remove callback of the driver, which runs `Driver::remove` and
+/// `DeviceRemoval::device_remove`, then drops the driver data and, once the device is
+/// unlocked, releases the managed resources.
+fn device_release_driver(pdev: &<PlatformBus as BusType>::Device) {
+    let mut dev = pdev.lock();
+    let pdrv = match dev.take_driver() {
+        Some(pdrv) => pdrv,
+        None => return,
+    };
+    let remove = pdrv.lock().remove;
+    if let Some(fn_remove) = remove {
+        if let Err(e) = fn_remove(&mut dev) {
+            pr_warn!("platform {}: remove failed with error {:?}", dev.name(), e);
         }
     }
+    dev.clear_drv_data();
+    dev.set_matched_id_index(None);
+    let devres = dev.devres_take_all();
+    drop(dev);
+    devres.release();
+}
+
+/// Removes a platform device from the bus, unbinding it from its driver first.
+pub fn platform_device_unregister(device: <PlatformBus as BusType>::Device) -> Result {
+    PLATFORM_BUS.lock().remove_device(device.clone())?;
+    device_release_driver(&device);
     Ok(())
 }
+
+/// Removes a platform driver from the bus, unbinding every device bound to it.
+pub fn platform_driver_unregister(pdrv: <PlatformBus as BusType>::Driver) {
+    let mut bus = PLATFORM_BUS.lock();
+    if bus.remove_driver(pdrv.clone()).is_err() {
+        return;
+    }
+    let bound: Vec<_> = bus
+        .devices
+        .iter()
+        .filter(|dev| dev.lock().is_bound_to(&pdrv))
+        .cloned()
+        .collect();
+    // before remove, unlock bus
+    drop(bus);
+    for pdev in bound {
+        device_release_driver(&pdev);
+    }
+}
+
+// Set when a device bound since the deferred list was last retried.
+static DEFERRED_TRIGGER: AtomicBool = AtomicBool::new(false);
+// Set while the deferred list is being retried.
+static DEFERRED_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Retries every device on the deferred list.
+fn driver_deferred_probe_trigger() {
+    DEFERRED_TRIGGER.store(true, Ordering::Release);
+    // Retried devices that bind trigger again; the outermost caller loops instead of recursing.
+    if DEFERRED_ACTIVE.swap(true, Ordering::AcqRel) {
+        return;
+    }
+    while DEFERRED_TRIGGER.swap(false, Ordering::AcqRel) {
+        let pending: Vec<_> = PLATFORM_BUS.lock().deferred.drain(..).collect();
+        for (pdrv, pdev) in pending {
+            driver_probe_device(&pdrv, pdev);
+        }
+    }
+    DEFERRED_ACTIVE.store(false, Ordering::Release);
+}
+
+/// Reports the devices still waiting on the deferred list, once every driver is registered.
+///
+/// Returns the devices that never probed.
+pub fn platform_deferred_probe_report() -> Vec<<PlatformBus as BusType>::Device> {
+    let pending: Vec<_> = PLATFORM_BUS
+        .lock()
+        .deferred
+        .iter()
+        .map(|(_, pdev)| pdev.clone())
+        .collect();
+    for pdev in pending.iter() {
+        pr_warn!("platform {}: deferred probe pending", pdev.lock().name());
+    }
+    pending
+}
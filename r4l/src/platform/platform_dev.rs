@@ -1,7 +1,13 @@
 // SPDX-License-Identifier: GPL-2.0
 
 //! A platform device.
-use crate::device;
+use super::PlatformDriver;
+use crate::device::{self, DevresActions};
+use crate::io::Resource;
+use crate::irq::Flags;
+use crate::property::FwNode;
+use crate::sync::{Arc, Mutex};
+use alloc::sync::Weak;
 use core::any::Any;
 use of::OfNode;
 
@@ -9,21 +15,125 @@ use crate::error::Result;
 
 pub struct PlatformDevice {
     device: device::Device,
+    // The driver bound to the device.
+    driver: Option<Arc<Mutex<PlatformDriver>>>,
+    // The device of the bus the device sits on, if it is a platform device.
+    parent: Option<Weak<Mutex<PlatformDevice>>>,
 }
 
 impl PlatformDevice {
     pub const fn new(of_node: OfNode<'static>) -> Self {
         PlatformDevice {
             device: device::Device::new(of_node),
+            driver: None,
+            parent: None,
         }
     }
+
+    /// Returns the parent device, the platform device of the bus of the device.
+    pub fn parent(&self) -> Option<Arc<Mutex<PlatformDevice>>> {
+        self.parent.as_ref()?.upgrade()
+    }
+
+    pub(crate) fn set_parent(&mut self, parent: &Arc<Mutex<PlatformDevice>>) {
+        self.parent = Some(Arc::downgrade(parent));
+    }
+
+    /// Returns true if a driver is bound to the device.
+    pub fn is_bound(&self) -> bool {
+        self.driver.is_some()
+    }
+
+    pub(crate) fn is_bound_to(&self, pdrv: &Arc<Mutex<PlatformDriver>>) -> bool {
+        self.driver.as_ref().is_some_and(|drv| Arc::ptr_eq(drv, pdrv))
+    }
+
+    pub(crate) fn bind_driver(&mut self, pdrv: Arc<Mutex<PlatformDriver>>) {
+        self.driver = Some(pdrv);
+    }
+
+    /// Returns the index of the entry of the driver id table that matched the device.
+    pub fn matched_id_index(&self) -> Option<usize> {
+        self.device.drv_matched()
+    }
+
+    pub(crate) fn set_matched_id_index(&mut self, index: Option<usize>) {
+        self.device.set_drv_matched(index);
+    }
+
+    pub(crate) fn take_driver(&mut self) -> Option<Arc<Mutex<PlatformDriver>>> {
+        self.driver.take()
+    }
 }
 
 impl PlatformDevice {
+    /// Returns the name of the platform device.
+    pub fn name(&self) -> &'static str {
+        self.device.name()
+    }
+
+    /// Returns the generic device of the platform device.
+    pub fn device(&self) -> &device::Device {
+        &self.device
+    }
+
+    /// Returns the firmware node of the platform device, to read its properties.
+    pub fn fwnode(&self) -> FwNode {
+        self.device.fwnode()
+    }
+
+    /// Returns the position of `compatible` in the compatible list of the device.
+    pub fn compatible_index(&self, compatible: &str) -> Option<usize> {
+        self.device.compatible_index(compatible)
+    }
+
+    /// Returns the register window `index` of the platform device.
+    ///
+    /// The address is a CPU physical address, ready for [`crate::io::IoMem::try_new`].
+    pub fn io_resource(&self, index: usize) -> Result<Resource> {
+        self.device.io_resource(index)
+    }
+
+    /// Sets the DMA addresses the platform device can reach, see [`crate::dma::dma_bit_mask`].
+    pub fn dma_set_mask(&mut self, mask: u64) -> Result {
+        self.device.dma_set_mask(mask)
+    }
+
+    /// Selects the pin state `name` of the platform device, like `sleep` or `default`.
+    pub fn pinctrl_select_state(&self, name: &str) -> Result {
+        self.device.pinctrl_select_state(name)
+    }
+
+    pub(crate) fn pinctrl_bind_pins(&mut self) -> Result {
+        self.device.pinctrl_bind_pins()
+    }
+
+    pub(crate) fn pinctrl_init_done(&self) -> Result {
+        self.device.pinctrl_init_done()
+    }
+
+    pub(crate) fn pinctrl_unbind_pins(&mut self) {
+        self.device.pinctrl_unbind_pins()
+    }
+
+    pub(crate) fn devres_take_all(&self) -> DevresActions {
+        self.device.devres_take_all()
+    }
+
     /// Returns irq of the platform device.
     pub fn irq_resource(&self, index: usize) -> Result<u32> {
         self.device.irq_resource(index)
     }
+
+    /// Returns the irq named `name` in the `interrupt-names` of the platform device.
+    pub fn irq_by_name(&self, name: &str) -> Result<u32> {
+        self.device.irq_by_name(name)
+    }
+
+    /// Returns irq of the platform device along with its trigger flags.
+    pub fn irq_resource_flags(&self, index: usize) -> Result<(u32, Flags)> {
+        self.device.irq_resource_flags(index)
+    }
 }
 
 impl device::DeviceOps for PlatformDevice {
@@ -35,6 +145,10 @@ impl device::DeviceOps for PlatformDevice {
         self.device.get_drv_data::<T>()
     }
 
+    fn clear_drv_data(&mut self) {
+        self.device.clear_drv_data();
+    }
+
     fn compatible_match(&self, compatible: &'static str) -> bool {
         self.device.compatible_match(compatible)
     }
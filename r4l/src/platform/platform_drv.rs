@@ -1,6 +1,6 @@
 // SPDX-License-Identifier: GPL-2.0
 
-use super::{platform_driver_register, PlatformDevice};
+use super::{platform_driver_register, platform_driver_unregister, PlatformDevice};
 use crate::{
     device::DeviceOps, driver, driver::IdArray, driver::IdTable, error::*, of, prelude::*,
     sync::Arc, sync::Mutex,
@@ -11,7 +11,7 @@ type PlatformIdTable = &'static [of::DeviceId];
 pub struct PlatformDriver {
     driver: driver::DeviceDriver,
     pub probe: Option<fn(dev: Arc<Mutex<PlatformDevice>>) -> Result>,
-    remove: Option<fn(dev: &mut PlatformDevice) -> Result>,
+    pub remove: Option<fn(dev: &mut PlatformDevice) -> Result>,
     id_table: Option<PlatformIdTable>,
 }
 
@@ -68,6 +68,8 @@ where
     const OF_DEVICE_ID_TABLE_SIZE: usize = 0;
     /// The table of device ids supported by the driver.
     const OF_DEVICE_ID_TABLE: Option<&'static [of::DeviceId]> = None;
+    /// The context data of each entry of [`Driver::OF_DEVICE_ID_TABLE`].
+    const OF_DEVICE_ID_INFO_TABLE: Option<&'static [Option<Self::IdInfo>]> = None;
 
     /// Platform driver probe.
     ///
@@ -108,12 +110,15 @@ impl<T: Driver> driver::DriverOps for Adapter<T> {
         Ok(())
     }
 
-    fn unregister(pdrv: &mut Self::RegType) {}
+    fn unregister(pdrv: &mut Self::RegType) {
+        platform_driver_unregister(pdrv.clone());
+    }
 }
 
 impl<T: Driver> Adapter<T> {
     fn get_id_info(pdev: &PlatformDevice) -> Option<&'static T::IdInfo> {
-        None
+        let index = pdev.matched_id_index()?;
+        T::OF_DEVICE_ID_INFO_TABLE?.get(index)?.as_ref()
     }
 
     fn probe_callback(pdev: Arc<Mutex<PlatformDevice>>) -> Result {
@@ -125,11 +130,11 @@ impl<T: Driver> Adapter<T> {
     }
 
     fn remove_callback(pdev: &mut PlatformDevice) -> Result {
-        let data = pdev.get_drv_data::<T::Data>().unwrap();
+        let data = pdev.get_drv_data::<T::Data>().ok_or(EINVAL)?;
         let ret = T::remove(data);
         <T::Data as driver::DeviceRemoval>::device_remove(data);
-        ret?;
-        Ok(())
+        pdev.clear_drv_data();
+        ret
     }
 }
 
@@ -2,11 +2,12 @@
 
 //! Defines the R4L print.
 //!
+//! Messages are handed to the OS through [`OsInterface::log`].
 //!
-//!Every OS should provide log_print! macro
-//!
-//!
+//! [`OsInterface::log`]: crate::os::OsInterface::log
+
 /// Log Level
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum LogLevel {
     /// The "EMERG" level
     Emerge,
@@ -18,6 +19,8 @@ pub enum LogLevel {
     Error,
     /// The "warn" level.
     Warn,
+    /// The "notice" level.
+    Notice,
     /// The "info" level.
     Info,
     /// The "debug" level.
@@ -26,39 +29,13 @@ pub enum LogLevel {
     Cont,
 }
 
-#[cfg(feature = "starry")]
-pub mod log {
-    pub use axlog;
-    #[doc(hidden)]
-    #[allow(clippy::crate_in_macro_def)]
-    #[macro_export]
-    macro_rules! log_print (
-        ($crate::print::LogLevel::Emerge, $($arg:tt)*) => (
-            $crate::print::log::axlog::error!($($arg)*)
-        );
-        ($crate::print::LogLevel::Alert, $($arg:tt)*) => (
-            $crate::print::log::axlog::error!($($arg)*)
-        );
-        ($crate::print::LogLevel::Crit, $($arg:tt)*) => (
-            $crate::print::log::axlog::error!($($arg)*)
-        );
-        ($crate::print::LogLevel::Error, $($arg:tt)*) => (
-            $crate::print::log::axlog::error!($($arg)*)
-        );
-        ($crate::print::LogLevel::Warn, $($arg:tt)*) => (
-            $crate::print::log::axlog::warn!($($arg)*)
-        );
-        ($crate::print::LogLevel::Info, $($arg:tt)*) => (
-            $crate::print::log::axlog::info!($($arg)*)
-        );
-        ($crate::print::LogLevel::Debug, $($arg:tt)*) => (
-            $crate::print::log::axlog::debug!($($arg)*)
-        );
-        ($crate::print::LogLevel::Cont, $($arg:tt)*) => (
-            $crate::print::log::axlog::info!($($arg)*)
-        );
+#[doc(hidden)]
+#[macro_export]
+macro_rules! log_print (
+    ($level:expr, $($arg:tt)*) => (
+        <$crate::os::Os as $crate::os::OsInterface>::log($level, format_args!($($arg)*))
     );
-}
+);
 
 /// Prints an emergency-level message (level 0).
 ///
@@ -0,0 +1,162 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Unified device property interface.
+//!
+//! C header: [`include/linux/property.h`](../../../../include/linux/property.h)
+//!
+//! The accessors follow Linux `fwnode_property_*()`: a missing property is [`ENOENT`], a
+//! property whose value does not fit the requested type is [`EINVAL`], and an array larger
+//! than the property is [`EOVERFLOW`].
+
+use crate::of::{of_get_parent, of_get_property, of_node_name, of_property_strings};
+use crate::prelude::*;
+use of::OfNode;
+
+/// A firmware node, the description of a device by the firmware.
+#[derive(Clone, Copy)]
+pub struct FwNode(OfNode<'static>);
+
+impl FwNode {
+    /// Wraps a device tree node.
+    pub const fn from_of_node(node: OfNode<'static>) -> Self {
+        Self(node)
+    }
+
+    /// Returns the device tree node.
+    pub fn of_node(&self) -> OfNode<'static> {
+        self.0
+    }
+
+    /// Returns the full name of the node, e.g. `pl061@9030000`.
+    pub fn name(&self) -> &'static str {
+        of_node_name(self.0)
+    }
+
+    /// Returns the parent node, `None` for the root.
+    pub fn parent(&self) -> Option<FwNode> {
+        of_get_parent(self.0).map(Self)
+    }
+
+    fn property(&self, name: &str) -> Result<&'static [u8]> {
+        of_get_property(self.0, name).ok_or(ENOENT)
+    }
+
+    /// Returns true if the node has the property `name`.
+    pub fn property_present(&self, name: &str) -> bool {
+        of_get_property(self.0, name).is_some()
+    }
+
+    /// Reads a boolean property, true if it is present.
+    pub fn property_read_bool(&self, name: &str) -> bool {
+        self.property_present(name)
+    }
+
+    /// Returns the number of elements of `elem_size` bytes of the property `name`.
+    ///
+    /// Fails with [`EINVAL`] if the size of the property is not a multiple of `elem_size`.
+    pub fn property_count_elems_of_size(&self, name: &str, elem_size: usize) -> Result<usize> {
+        let value = self.property(name)?;
+        if elem_size == 0 || value.len() % elem_size != 0 {
+            return Err(EINVAL);
+        }
+        Ok(value.len() / elem_size)
+    }
+
+    /// Returns the number of `u32` cells of the property `name`.
+    pub fn property_count_u32(&self, name: &str) -> Result<usize> {
+        self.property_count_elems_of_size(name, 4)
+    }
+
+    /// Returns the number of `u64` values of the property `name`.
+    pub fn property_count_u64(&self, name: &str) -> Result<usize> {
+        self.property_count_elems_of_size(name, 8)
+    }
+
+    /// Reads the first `out.len()` cells of the property `name`.
+    pub fn property_read_u32_array(&self, name: &str, out: &mut [u32]) -> Result {
+        let value = self.property(name)?;
+        if value.is_empty() {
+            return Err(EINVAL);
+        }
+        if value.len() < out.len() * 4 {
+            return Err(EOVERFLOW);
+        }
+        for (v, chunk) in out.iter_mut().zip(value.chunks_exact(4)) {
+            *v = u32::from_be_bytes(core::array::from_fn(|i| chunk[i]));
+        }
+        Ok(())
+    }
+
+    /// Reads the first `out.len()` 64-bit values of the property `name`.
+    pub fn property_read_u64_array(&self, name: &str, out: &mut [u64]) -> Result {
+        let value = self.property(name)?;
+        if value.is_empty() {
+            return Err(EINVAL);
+        }
+        if value.len() < out.len() * 8 {
+            return Err(EOVERFLOW);
+        }
+        for (v, chunk) in out.iter_mut().zip(value.chunks_exact(8)) {
+            *v = u64::from_be_bytes(core::array::from_fn(|i| chunk[i]));
+        }
+        Ok(())
+    }
+
+    /// Reads the first cell of the property `name`.
+    pub fn property_read_u32(&self, name: &str) -> Result<u32> {
+        let mut v = [0];
+        self.property_read_u32_array(name, &mut v)?;
+        Ok(v[0])
+    }
+
+    /// Reads the first 64-bit value of the property `name`.
+    pub fn property_read_u64(&self, name: &str) -> Result<u64> {
+        let mut v = [0];
+        self.property_read_u64_array(name, &mut v)?;
+        Ok(v[0])
+    }
+
+    /// Reads the first string of the property `name`.
+    ///
+    /// Fails with [`EINVAL`] if the property is not a NUL terminated UTF-8 string.
+    pub fn property_read_string(&self, name: &str) -> Result<&'static str> {
+        let value = self.property(name)?;
+        let end = value.iter().position(|b| *b == 0).ok_or(EINVAL)?;
+        Ok(core::str::from_utf8(&value[..end])?)
+    }
+
+    /// Reads all the strings of the string list property `name`.
+    pub fn property_read_string_array(&self, name: &str) -> Result<Vec<&'static str>> {
+        self.property(name)?;
+        let mut strings = Vec::new();
+        for s in of_property_strings(self.0, name) {
+            strings.try_reserve(1)?;
+            strings.push(s);
+        }
+        Ok(strings)
+    }
+
+    /// Returns the index of `string` in the string list property `name`.
+    ///
+    /// Fails with [`ENODATA`] if the list does not contain `string`.
+    pub fn property_match_string(&self, name: &str, string: &str) -> Result<usize> {
+        self.property(name)?;
+        of_property_strings(self.0, name)
+            .position(|s| s == string)
+            .ok_or(ENODATA)
+    }
+
+    /// Returns the available child nodes.
+    pub fn child_nodes(&self) -> impl Iterator<Item = FwNode> {
+        self.0
+            .children()
+            .filter(|child| of::of_device_is_available(*child))
+            .map(Self)
+    }
+
+    /// Returns the available child node named `name`, unit address aside.
+    pub fn get_named_child(&self, name: &str) -> Option<FwNode> {
+        self.child_nodes()
+            .find(|child| child.name().split('@').next() == Some(name))
+    }
+}
@@ -0,0 +1,361 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Voltage and current regulators.
+//!
+//! C header: [`include/linux/regulator/consumer.h`](../../../../include/linux/regulator/consumer.h)
+//! and [`include/linux/regulator/driver.h`](../../../../include/linux/regulator/driver.h)
+//!
+//! Regulator drivers implement [`RegulatorOps`] and register it for their device tree node
+//! with [`regulator_register`], the constraints come from the `regulator-*` properties of
+//! the node. Consumers get a [`Regulator`] from the `<supply>-supply` properties of their
+//! device.
+//!
+//! `regulator-fixed` nodes are registered by [`of_regulator_init`], during
+//! [`crate::init::driver_framework_init`]. Their enable GPIO is not handled: the rail is
+//! taken as on.
+
+use crate::device::Device;
+use crate::of::{
+    of_get_phandle, of_node_name, of_property_present, of_property_read_cell,
+    of_property_strings, parse_phandle,
+};
+use crate::prelude::*;
+use crate::sync::{Arc, Mutex};
+use alloc::format;
+use of::OfNode;
+
+/// The operations of a regulator, implemented by regulator drivers.
+///
+/// Voltages are in microvolts.
+pub trait RegulatorOps: Send + Sync {
+    /// Turns the output on.
+    fn enable(&self) -> Result {
+        Ok(())
+    }
+
+    /// Turns the output off.
+    fn disable(&self) -> Result {
+        Ok(())
+    }
+
+    /// Returns the output voltage.
+    fn get_voltage(&self) -> Result<i32> {
+        Err(ENOTSUPP)
+    }
+
+    /// Sets the output voltage within `[min_uv, max_uv]`.
+    fn set_voltage(&self, _min_uv: i32, _max_uv: i32) -> Result {
+        Err(ENOTSUPP)
+    }
+}
+
+/// The limits of a regulator, from the `regulator-*` properties of its node.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Constraints {
+    /// Minimum voltage consumers may set.
+    pub min_uv: Option<i32>,
+    /// Maximum voltage consumers may set.
+    pub max_uv: Option<i32>,
+    /// The regulator is never turned off.
+    pub always_on: bool,
+    /// The regulator was turned on by the boot loader.
+    pub boot_on: bool,
+}
+
+impl Constraints {
+    fn from_of_node(node: OfNode<'static>) -> Self {
+        let read_uv = |name| of_property_read_cell(node, name, 0).map(|uv| uv as i32);
+        Self {
+            min_uv: read_uv("regulator-min-microvolt"),
+            max_uv: read_uv("regulator-max-microvolt"),
+            always_on: of_property_present(node, "regulator-always-on"),
+            boot_on: of_property_present(node, "regulator-boot-on"),
+        }
+    }
+}
+
+/// A registered regulator.
+pub struct RegulatorDev {
+    name: &'static str,
+    ops: Box<dyn RegulatorOps>,
+    constraints: Constraints,
+    // The regulator feeding this one, from `vin-supply`.
+    supply: Option<Arc<RegulatorDev>>,
+    // Number of consumers that enabled the regulator.
+    use_count: Mutex<usize>,
+}
+
+impl RegulatorDev {
+    fn enable(&self) -> Result {
+        let mut use_count = self.use_count.lock();
+        if *use_count == 0 {
+            if let Some(supply) = &self.supply {
+                supply.enable()?;
+            }
+            if let Err(e) = self.ops.enable() {
+                if let Some(supply) = &self.supply {
+                    let _ = supply.disable();
+                }
+                return Err(e);
+            }
+        }
+        *use_count += 1;
+        Ok(())
+    }
+
+    fn disable(&self) -> Result {
+        match self.put()? {
+            Some(supply) => supply.disable(),
+            None => Ok(()),
+        }
+    }
+
+    // Drops a use of the regulator. Returns the supply to disable once the regulator is off,
+    // the use is gone whatever the supply does.
+    fn put(&self) -> Result<Option<&Arc<RegulatorDev>>> {
+        let mut use_count = self.use_count.lock();
+        if *use_count == 0 {
+            pr_warn!("unbalanced disable of regulator {}", self.name);
+            return Err(EIO);
+        }
+        if *use_count == 1 && !self.constraints.always_on {
+            self.ops.disable()?;
+            *use_count -= 1;
+            return Ok(self.supply.as_ref());
+        }
+        *use_count -= 1;
+        Ok(None)
+    }
+}
+
+// Registered regulators, keyed by the phandle of their node.
+static REGULATORS: Mutex<Vec<(u32, Arc<RegulatorDev>)>> = Mutex::new(Vec::new());
+
+fn regulator_find(phandle: u32) -> Option<Arc<RegulatorDev>> {
+    REGULATORS
+        .lock()
+        .iter()
+        .find(|(ph, _)| *ph == phandle)
+        .map(|(_, rdev)| rdev.clone())
+}
+
+/// Registers `ops` as the regulator of `node`.
+///
+/// Fails with [`EPROBE_DEFER`] if the `vin-supply` of the node is not registered yet.
+pub fn regulator_register(node: OfNode<'static>, ops: Box<dyn RegulatorOps>) -> Result {
+    let phandle = of_get_phandle(node).ok_or(EINVAL)?;
+    let supply = match parse_phandle(node, "vin-supply", 0) {
+        Ok(np) => Some(regulator_find(of_get_phandle(np).ok_or(EINVAL)?).ok_or(EPROBE_DEFER)?),
+        Err(ENOENT) => None,
+        Err(e) => return Err(e),
+    };
+    let name = of_property_strings(node, "regulator-name")
+        .next()
+        .unwrap_or_else(|| of_node_name(node));
+    let constraints = Constraints::from_of_node(node);
+    let rdev = Arc::new(RegulatorDev {
+        name,
+        ops,
+        constraints,
+        supply,
+        use_count: Mutex::new(0),
+    });
+    {
+        let mut regulators = REGULATORS.lock();
+        if regulators.iter().any(|(ph, _)| *ph == phandle) {
+            return Err(EEXIST);
+        }
+        regulators.try_reserve(1)?;
+        regulators.push((phandle, rdev.clone()));
+    }
+    // Enabled once published, unlocked: enabling may sleep.
+    if constraints.always_on || constraints.boot_on {
+        if let Err(e) = rdev.enable() {
+            REGULATORS.lock().retain(|(_, r)| !Arc::ptr_eq(r, &rdev));
+            return Err(e);
+        }
+    }
+    pr_debug!("regulator {} registered", name);
+    Ok(())
+}
+
+/// A regulator with a fixed output voltage.
+pub struct FixedRegulator {
+    /// The output voltage, if the node gives one.
+    pub microvolts: Option<i32>,
+}
+
+impl RegulatorOps for FixedRegulator {
+    fn get_voltage(&self) -> Result<i32> {
+        self.microvolts.ok_or(EINVAL)
+    }
+
+    fn set_voltage(&self, min_uv: i32, max_uv: i32) -> Result {
+        match self.microvolts {
+            Some(uv) if (min_uv..=max_uv).contains(&uv) => Ok(()),
+            _ => Err(EINVAL),
+        }
+    }
+}
+
+/// Registers the regulators of the `regulator-fixed` nodes.
+///
+/// Fixed regulators may feed each other in any order, so they are set up in passes until
+/// every one found its supply.
+pub fn of_regulator_init() -> Result {
+    let mut pending: Vec<OfNode<'static>> = of::find_compatible_node(&["regulator-fixed"])
+        .filter(|node| of::of_device_is_available(*node))
+        .collect();
+    while !pending.is_empty() {
+        let before = pending.len();
+        pending.retain(|node| {
+            let microvolts = of_property_read_cell(*node, "regulator-min-microvolt", 0).map(|uv| uv as i32);
+            match regulator_register(*node, Box::new(FixedRegulator { microvolts })) {
+                Ok(()) => false,
+                Err(EPROBE_DEFER) => true,
+                Err(e) => {
+                    pr_err!("fixed regulator {} setup failed: {:?}", of_node_name(*node), e);
+                    false
+                }
+            }
+        });
+        if pending.len() == before {
+            for node in pending.iter() {
+                pr_warn!("fixed regulator {} has no supply", of_node_name(*node));
+            }
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// A supply used by a device.
+///
+/// Every consumer keeps its own enable count, an enabled supply is disabled when its
+/// `Regulator` is dropped.
+///
+/// # Examples
+///
+/// ```ignore
+/// # use kernel::{platform, prelude::*, regulator::Regulator};
+/// fn probe(pdev: &mut platform::Device) -> Result {
+///     // `vdd-supply = <&vcc_3v3>;`
+///     let vdd = Regulator::get(pdev.device(), "vdd")?;
+///     vdd.set_voltage(3_300_000, 3_300_000)?;
+///     vdd.enable()?;
+///     Ok(())
+/// }
+/// ```
+pub struct Regulator {
+    // `None` for the dummy regulator of a device without the supply.
+    rdev: Option<Arc<RegulatorDev>>,
+    enable_count: Mutex<usize>,
+}
+
+impl Regulator {
+    fn lookup(dev: &Device, id: &str) -> Result<Arc<RegulatorDev>> {
+        let prop = format!("{}-supply", id);
+        let np = parse_phandle(dev.of_node(), &prop, 0)?;
+        regulator_find(of_get_phandle(np).ok_or(EINVAL)?).ok_or(EPROBE_DEFER)
+    }
+
+    /// Gets the supply `id` of `dev`, from its `<id>-supply` property.
+    ///
+    /// A device without the property gets a dummy regulator that is always on, like Linux
+    /// does for supplies missing from the device tree. Fails with [`EPROBE_DEFER`] if the
+    /// regulator is not registered yet.
+    pub fn get(dev: &Device, id: &str) -> Result<Self> {
+        let rdev = match Self::lookup(dev, id) {
+            Ok(rdev) => Some(rdev),
+            Err(ENOENT) => {
+                pr_debug!("{}: {}-supply not found, using dummy regulator", dev.name(), id);
+                None
+            }
+            Err(e) => return Err(e),
+        };
+        Ok(Self {
+            rdev,
+            enable_count: Mutex::new(0),
+        })
+    }
+
+    /// Like [`Regulator::get`], but returns `None` instead of a dummy regulator.
+    pub fn get_optional(dev: &Device, id: &str) -> Result<Option<Self>> {
+        match Self::lookup(dev, id) {
+            Ok(rdev) => Ok(Some(Self {
+                rdev: Some(rdev),
+                enable_count: Mutex::new(0),
+            })),
+            Err(ENOENT) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Turns the supply on, if no other consumer did.
+    pub fn enable(&self) -> Result {
+        let mut enable_count = self.enable_count.lock();
+        if let Some(rdev) = &self.rdev {
+            rdev.enable()?;
+        }
+        *enable_count += 1;
+        Ok(())
+    }
+
+    /// Undoes a [`Regulator::enable`], the supply is turned off once no consumer uses it.
+    ///
+    /// An error of the regulator feeding the supply is returned, but the supply is off and
+    /// this consumer no longer enables it.
+    pub fn disable(&self) -> Result {
+        let mut enable_count = self.enable_count.lock();
+        if *enable_count == 0 {
+            return Err(EIO);
+        }
+        let supply = match &self.rdev {
+            Some(rdev) => rdev.put()?,
+            None => None,
+        };
+        *enable_count -= 1;
+        drop(enable_count);
+        match supply {
+            Some(supply) => supply.disable(),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns true if this consumer enabled the supply.
+    pub fn is_enabled(&self) -> bool {
+        *self.enable_count.lock() > 0
+    }
+
+    /// Returns the voltage of the supply, in microvolts.
+    pub fn get_voltage(&self) -> Result<i32> {
+        self.rdev.as_ref().ok_or(EINVAL)?.ops.get_voltage()
+    }
+
+    /// Sets the voltage of the supply within `[min_uv, max_uv]`, in microvolts.
+    ///
+    /// The range is narrowed to the constraints of the regulator, [`EINVAL`] if nothing is
+    /// left.
+    pub fn set_voltage(&self, min_uv: i32, max_uv: i32) -> Result {
+        let Some(rdev) = &self.rdev else {
+            return Ok(());
+        };
+        let min_uv = rdev.constraints.min_uv.map_or(min_uv, |min| min_uv.max(min));
+        let max_uv = rdev.constraints.max_uv.map_or(max_uv, |max| max_uv.min(max));
+        if min_uv > max_uv {
+            return Err(EINVAL);
+        }
+        rdev.ops.set_voltage(min_uv, max_uv)
+    }
+}
+
+impl Drop for Regulator {
+    fn drop(&mut self) {
+        let enable_count = *self.enable_count.lock();
+        if let Some(rdev) = &self.rdev {
+            for _ in 0..enable_count {
+                let _ = rdev.disable();
+            }
+        }
+    }
+}
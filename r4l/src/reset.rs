@@ -0,0 +1,294 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Reset controllers.
+//!
+//! C header: [`include/linux/reset.h`](../../../../include/linux/reset.h) and
+//! [`include/linux/reset-controller.h`](../../../../include/linux/reset-controller.h)
+//!
+//! Reset controller drivers implement [`ResetControllerOps`] and register it for their
+//! device tree node with [`reset_controller_register`]. Consumers get a [`ResetControl`]
+//! from the `resets`/`reset-names` properties of their device.
+//!
+//! An exclusive control is the only handle of its line and asserts it at will. Shared
+//! controls count deasserts: the line is deasserted by the first deassert and asserted
+//! again when every consumer that deasserted it asserted it back.
+
+use crate::device::Device;
+use crate::of::{of_get_phandle, of_node_name, of_property_match_string, parse_phandle_with_args};
+use crate::prelude::*;
+use crate::sync::{Arc, Mutex};
+use of::OfNode;
+
+/// The operations of a reset controller, on the line `id`.
+pub trait ResetControllerOps: Send + Sync {
+    /// Returns the number of reset lines of the controller.
+    fn nr_resets(&self) -> u32;
+
+    /// Asserts the reset line.
+    fn assert(&self, _id: u32) -> Result {
+        Err(ENOTSUPP)
+    }
+
+    /// Deasserts the reset line.
+    fn deassert(&self, _id: u32) -> Result {
+        Err(ENOTSUPP)
+    }
+
+    /// Pulses the reset line, for self-deasserting resets.
+    fn reset(&self, _id: u32) -> Result {
+        Err(ENOTSUPP)
+    }
+
+    /// Returns true if the reset line is asserted.
+    fn status(&self, _id: u32) -> Result<bool> {
+        Err(ENOTSUPP)
+    }
+
+    /// Translates the argument cells of a `resets` entry to the line id.
+    ///
+    /// The default handles the usual one cell specifier.
+    fn xlate(&self, args: &[u32]) -> Result<u32> {
+        match args {
+            [id, ..] if *id < self.nr_resets() => Ok(*id),
+            _ => Err(EINVAL),
+        }
+    }
+}
+
+// A line with consumers.
+struct ResetLine {
+    id: u32,
+    shared: bool,
+    users: usize,
+    deassert_count: usize,
+    triggered: bool,
+}
+
+struct ResetController {
+    phandle: u32,
+    ops: Arc<dyn ResetControllerOps>,
+    lines: Vec<ResetLine>,
+}
+
+static RESET_CONTROLLERS: Mutex<Vec<ResetController>> = Mutex::new(Vec::new());
+
+/// Registers `ops` as the reset controller of `node`.
+pub fn reset_controller_register(node: OfNode<'static>, ops: Arc<dyn ResetControllerOps>) -> Result {
+    let phandle = of_get_phandle(node).ok_or(EINVAL)?;
+    let mut controllers = RESET_CONTROLLERS.lock();
+    if controllers.iter().any(|rcdev| rcdev.phandle == phandle) {
+        return Err(EEXIST);
+    }
+    controllers.try_reserve(1)?;
+    controllers.push(ResetController {
+        phandle,
+        ops,
+        lines: Vec::new(),
+    });
+    pr_debug!("reset controller {} registered", of_node_name(node));
+    Ok(())
+}
+
+/// Removes the reset controller of `node`.
+///
+/// Fails with [`EBUSY`] while consumers still hold some of its lines.
+pub fn reset_controller_unregister(node: OfNode<'static>) -> Result {
+    let phandle = of_get_phandle(node).ok_or(EINVAL)?;
+    let mut controllers = RESET_CONTROLLERS.lock();
+    let index = controllers
+        .iter()
+        .position(|rcdev| rcdev.phandle == phandle)
+        .ok_or(ENODEV)?;
+    if !controllers[index].lines.is_empty() {
+        return Err(EBUSY);
+    }
+    controllers.remove(index);
+    Ok(())
+}
+
+/// A reset line used by a device.
+///
+/// # Examples
+///
+/// ```ignore
+/// # use kernel::{platform, prelude::*, reset::ResetControl};
+/// fn probe(pdev: &mut platform::Device) -> Result {
+///     if let Some(rst) = ResetControl::get_optional_exclusive(pdev.device(), None)? {
+///         rst.deassert()?;
+///     }
+///     Ok(())
+/// }
+/// ```
+pub struct ResetControl {
+    phandle: u32,
+    id: u32,
+    shared: bool,
+}
+
+impl ResetControl {
+    fn get(dev: &Device, name: Option<&str>, shared: bool) -> Result<Self> {
+        let node = dev.of_node();
+        let index = match name {
+            Some(name) => of_property_match_string(node, "reset-names", name).ok_or(ENOENT)?,
+            None => 0,
+        };
+        let spec = parse_phandle_with_args(node, "resets", "#reset-cells", index)?;
+        let phandle = of_get_phandle(spec.np).ok_or(EINVAL)?;
+
+        let mut controllers = RESET_CONTROLLERS.lock();
+        let rcdev = controllers
+            .iter_mut()
+            .find(|rcdev| rcdev.phandle == phandle)
+            .ok_or(EPROBE_DEFER)?;
+        let id = rcdev.ops.xlate(spec.args())?;
+        match rcdev.lines.iter_mut().find(|line| line.id == id) {
+            // An exclusive line has a single user, a shared one only shared users.
+            Some(line) if !shared || !line.shared => return Err(EBUSY),
+            Some(line) => line.users += 1,
+            None => {
+                rcdev.lines.try_reserve(1)?;
+                rcdev.lines.push(ResetLine {
+                    id,
+                    shared,
+                    users: 1,
+                    deassert_count: 0,
+                    triggered: false,
+                });
+            }
+        }
+        Ok(Self { phandle, id, shared })
+    }
+
+    /// Gets the exclusive control of the reset line named `name` of `dev`, or of its first
+    /// line if `name` is `None`.
+    ///
+    /// Fails with [`EBUSY`] if another consumer already uses the line.
+    pub fn get_exclusive(dev: &Device, name: Option<&str>) -> Result<Self> {
+        Self::get(dev, name, false)
+    }
+
+    /// Gets a shared control of the reset line named `name` of `dev`.
+    ///
+    /// Fails with [`EBUSY`] if another consumer controls the line exclusively.
+    pub fn get_shared(dev: &Device, name: Option<&str>) -> Result<Self> {
+        Self::get(dev, name, true)
+    }
+
+    /// Like [`ResetControl::get_exclusive`], but returns `None` if the device has no such
+    /// reset line.
+    pub fn get_optional_exclusive(dev: &Device, name: Option<&str>) -> Result<Option<Self>> {
+        optional(Self::get(dev, name, false))
+    }
+
+    /// Like [`ResetControl::get_shared`], but returns `None` if the device has no such
+    /// reset line.
+    pub fn get_optional_shared(dev: &Device, name: Option<&str>) -> Result<Option<Self>> {
+        optional(Self::get(dev, name, true))
+    }
+
+    // Runs `f` on the line and the operations of its controller.
+    fn with_line<R>(&self, f: impl FnOnce(&mut ResetLine, &dyn ResetControllerOps) -> Result<R>) -> Result<R> {
+        let mut controllers = RESET_CONTROLLERS.lock();
+        let rcdev = controllers
+            .iter_mut()
+            .find(|rcdev| rcdev.phandle == self.phandle)
+            .ok_or(ENODEV)?;
+        let ops = rcdev.ops.clone();
+        let line = rcdev
+            .lines
+            .iter_mut()
+            .find(|line| line.id == self.id)
+            .ok_or(ENODEV)?;
+        f(line, &*ops)
+    }
+
+    /// Asserts the reset line.
+    ///
+    /// A shared line is asserted once every deassert has been balanced by an assert.
+    pub fn assert(&self) -> Result {
+        self.with_line(|line, ops| {
+            if !self.shared {
+                return ops.assert(line.id);
+            }
+            if line.deassert_count == 0 {
+                pr_warn!("unbalanced assert of shared reset {}", line.id);
+                return Err(EINVAL);
+            }
+            line.deassert_count -= 1;
+            if line.deassert_count > 0 {
+                return Ok(());
+            }
+            // Controllers without assert only have self-deasserting resets.
+            match ops.assert(line.id) {
+                Err(ENOTSUPP) => Ok(()),
+                ret => ret,
+            }
+        })
+    }
+
+    /// Deasserts the reset line.
+    pub fn deassert(&self) -> Result {
+        self.with_line(|line, ops| {
+            if self.shared {
+                line.deassert_count += 1;
+                if line.deassert_count > 1 {
+                    return Ok(());
+                }
+            }
+            // Controllers without deassert only have self-deasserting resets.
+            match ops.deassert(line.id) {
+                Err(ENOTSUPP) => Ok(()),
+                Err(e) => {
+                    if self.shared {
+                        line.deassert_count -= 1;
+                    }
+                    Err(e)
+                }
+                Ok(()) => Ok(()),
+            }
+        })
+    }
+
+    /// Pulses the reset line.
+    ///
+    /// A shared line is pulsed by the first call only, and only while it is not deasserted.
+    pub fn reset(&self) -> Result {
+        self.with_line(|line, ops| {
+            if self.shared {
+                if line.deassert_count != 0 {
+                    return Err(EINVAL);
+                }
+                if line.triggered {
+                    return Ok(());
+                }
+                line.triggered = true;
+            }
+            ops.reset(line.id)
+        })
+    }
+
+    /// Returns true if the reset line is asserted.
+    pub fn status(&self) -> Result<bool> {
+        self.with_line(|line, ops| ops.status(line.id))
+    }
+}
+
+fn optional(ret: Result<ResetControl>) -> Result<Option<ResetControl>> {
+    match ret {
+        Ok(rstc) => Ok(Some(rstc)),
+        Err(ENOENT) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+impl Drop for ResetControl {
+    fn drop(&mut self) {
+        let mut controllers = RESET_CONTROLLERS.lock();
+        if let Some(rcdev) = controllers.iter_mut().find(|rcdev| rcdev.phandle == self.phandle) {
+            if let Some(line) = rcdev.lines.iter_mut().find(|line| line.id == self.id) {
+                line.users -= 1;
+            }
+            rcdev.lines.retain(|line| line.users > 0);
+        }
+    }
+}
@@ -1,15 +1,135 @@
 //! Defines the R4L sync.
 //!
-//! Every OS should provides:
+//! Provides:
 //! - Arc
 //! - Mutex
-//! - SpinLock
+//! - SpinNoIrq
+//! - WaitQueue
+//!
+//! The locks are built here on top of the raw locks of the OS, see [`RawLock`].
+
+pub use alloc::sync::Arc;
+
+use crate::os::{Os, OsInterface, RawLock, RawWaitQueue};
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+
+/// A lock protecting data of type `T` with the raw lock `R`.
+pub struct Lock<R: RawLock, T: ?Sized> {
+    raw: R,
+    data: UnsafeCell<T>,
+}
+
+// SAFETY: The raw lock serializes every access to `data`.
+unsafe impl<R: RawLock + Send, T: ?Sized + Send> Send for Lock<R, T> {}
+unsafe impl<R: RawLock + Sync, T: ?Sized + Send> Sync for Lock<R, T> {}
+
+impl<R: RawLock, T> Lock<R, T> {
+    /// Creates a new unlocked lock.
+    pub const fn new(data: T) -> Self {
+        Self {
+            raw: R::INIT,
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    /// Consumes the lock, returning the protected data.
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<R: RawLock, T: ?Sized> Lock<R, T> {
+    /// Acquires the lock, waiting until it is available.
+    pub fn lock(&self) -> LockGuard<'_, R, T> {
+        self.raw.lock();
+        LockGuard {
+            lock: self,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Tries to acquire the lock without waiting.
+    pub fn try_lock(&self) -> Option<LockGuard<'_, R, T>> {
+        if self.raw.try_lock() {
+            Some(LockGuard {
+                lock: self,
+                _not_send: PhantomData,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+/// Gives access to the data of a [`Lock`], releases it when dropped.
+///
+/// Like `std::sync::MutexGuard`, the guard is not `Send`: it is released on the CPU that took
+/// the lock, where `SpinNoIrq` restores the interrupt state.
+pub struct LockGuard<'a, R: RawLock, T: ?Sized> {
+    lock: &'a Lock<R, T>,
+    _not_send: PhantomData<*const ()>,
+}
 
-#[cfg(feature = "starry")]
-mod sync {
-    pub use alloc::sync::Arc;
-    pub use axsync::spin::SpinNoIrq;
-    pub use axsync::Mutex;
+// SAFETY: A shared guard only gives `&T` out, which is fine to share when `T` is `Sync`.
+unsafe impl<R: RawLock, T: ?Sized + Sync> Sync for LockGuard<'_, R, T> {}
+
+impl<R: RawLock, T: ?Sized> Deref for LockGuard<'_, R, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: The guard holds the lock.
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<R: RawLock, T: ?Sized> DerefMut for LockGuard<'_, R, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: The guard holds the lock.
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<R: RawLock, T: ?Sized> Drop for LockGuard<'_, R, T> {
+    fn drop(&mut self) {
+        // SAFETY: The guard holds the lock.
+        unsafe { self.lock.raw.unlock() }
+    }
+}
+
+/// A lock that may sleep while waiting, only usable in task context.
+pub type Mutex<T> = Lock<<Os as OsInterface>::RawMutex, T>;
+
+/// A spin lock that masks local interrupts while held.
+pub type SpinNoIrq<T> = Lock<<Os as OsInterface>::RawSpinNoIrq, T>;
+
+/// A queue of tasks waiting for a condition.
+///
+/// The condition is state shared with the waker, usually atomics: the waker updates it,
+/// then calls [`WaitQueue::notify_all`].
+pub struct WaitQueue(<Os as OsInterface>::RawWaitQueue);
+
+impl WaitQueue {
+    /// Creates an empty queue.
+    pub const fn new() -> Self {
+        Self(<<Os as OsInterface>::RawWaitQueue as RawWaitQueue>::INIT)
+    }
+
+    /// Puts the current task to sleep until `condition` returns true, only usable in task
+    /// context.
+    pub fn wait_until(&self, condition: impl Fn() -> bool) {
+        self.0.wait_until(&condition);
+    }
+
+    /// Wakes the waiting tasks up, they check their condition again.
+    pub fn notify_all(&self) {
+        self.0.notify_all();
+    }
 }
 
-pub use sync::*;
+impl Default for WaitQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
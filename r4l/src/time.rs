@@ -0,0 +1,28 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Time keeping and delays.
+//!
+//! C header: [`include/linux/delay.h`](../../../../include/linux/delay.h)
+
+use crate::os::{Os, OsInterface};
+use core::time::Duration;
+
+/// Returns the monotonic time since boot.
+pub fn ktime_get() -> Duration {
+    Os::current_time()
+}
+
+/// Spins for `us` microseconds, usable in atomic context.
+pub fn udelay(us: u64) {
+    Os::busy_wait(Duration::from_micros(us));
+}
+
+/// Spins for `ms` milliseconds, usable in atomic context.
+pub fn mdelay(ms: u64) {
+    Os::busy_wait(Duration::from_millis(ms));
+}
+
+/// Sleeps for at least `ms` milliseconds.
+pub fn msleep(ms: u64) {
+    Os::sleep(Duration::from_millis(ms));
+}
@@ -0,0 +1,125 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Checks the clock consumer API against fixed clocks and a gate provider.
+
+#![cfg(feature = "hosted")]
+
+// The macros of r4l name the crate `kernel`, as the drivers depend on it.
+extern crate r4l as kernel;
+
+mod common;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use kernel::clk::{self, Clk, ClkCore, ClkOps};
+use kernel::os::hosted::fdt::FdtBuilder;
+use kernel::sync::Arc;
+use kernel::{of, platform, prelude::*};
+
+const OSC: u32 = 2;
+const DIV2: u32 = 3;
+const GATES: u32 = 4;
+const LATE: u32 = 5;
+const DEV: u32 = 6;
+const LATE_DEV: u32 = 7;
+
+// The number of times the gates were ungated and gated in hardware.
+static GATE_ENABLES: [AtomicUsize; 2] = [AtomicUsize::new(0), AtomicUsize::new(0)];
+static GATE_DISABLES: [AtomicUsize; 2] = [AtomicUsize::new(0), AtomicUsize::new(0)];
+
+struct GateClk(usize);
+
+impl ClkOps for GateClk {
+    fn enable(&self) -> Result {
+        GATE_ENABLES[self.0].fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn disable(&self) {
+        GATE_DISABLES[self.0].fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn recalc_rate(&self, parent_rate: u64) -> u64 {
+        parent_rate
+    }
+}
+
+fn device(phandle: u32) -> platform::PlatformDevice {
+    platform::PlatformDevice::new(of::of_find_node_by_phandle(phandle).unwrap())
+}
+
+#[test]
+fn clocks() {
+    let mut fdt = FdtBuilder::new();
+    common::begin_root(&mut fdt)
+        .begin_node("osc")
+        .property_strings("compatible", &["fixed-clock"])
+        .property_u32("phandle", OSC)
+        .property_u32("#clock-cells", 0)
+        .property_u32("clock-frequency", 24_000_000)
+        .property_strings("clock-output-names", &["osc24m"])
+        .end_node()
+        .begin_node("div2")
+        .property_strings("compatible", &["fixed-factor-clock"])
+        .property_u32("phandle", DIV2)
+        .property_u32("#clock-cells", 0)
+        .property_u32("clocks", OSC)
+        .property_u32("clock-mult", 1)
+        .property_u32("clock-div", 2)
+        .end_node()
+        .begin_node("gates")
+        .property_u32("phandle", GATES)
+        .property_u32("#clock-cells", 1)
+        .property_u32("clocks", OSC)
+        .end_node()
+        // The provider of this one never registers.
+        .begin_node("late")
+        .property_u32("phandle", LATE)
+        .property_u32("#clock-cells", 0)
+        .end_node()
+        .begin_node("dev")
+        .property_u32("phandle", DEV)
+        .property_cells("clocks", &[DIV2, GATES, 1])
+        .property_strings("clock-names", &["baud", "bus"])
+        .end_node()
+        .begin_node("late-dev")
+        .property_u32("phandle", LATE_DEV)
+        .property_u32("clocks", LATE)
+        .end_node()
+        .end_node();
+    common::boot(&mut fdt);
+
+    // The second gate is fed by the first one.
+    let gates_node = of::of_find_node_by_phandle(GATES).unwrap();
+    let osc = clk::of_clk_get(gates_node, 0).unwrap();
+    let gate0 = Arc::new(ClkCore::new("gate0", Box::new(GateClk(0)), Some(osc)));
+    let gate1 = Arc::new(ClkCore::new("gate1", Box::new(GateClk(1)), Some(gate0.clone())));
+    clk::of_clk_add_provider(gates_node, Arc::new(clk::OneCellClkProvider(vec![gate0, gate1])))
+        .unwrap();
+
+    let dev = device(DEV);
+    let baud = Clk::get(dev.device(), Some("baud")).unwrap();
+    assert_eq!(baud.name(), "div2");
+    assert_eq!(baud.get_rate(), 12_000_000);
+
+    let bus = Clk::get(dev.device(), Some("bus")).unwrap();
+    assert_eq!(bus.name(), "gate1");
+    assert_eq!(bus.get_rate(), 24_000_000);
+    let first = Clk::get(dev.device(), None).unwrap();
+    assert_eq!(first.name(), "div2");
+    assert!(Clk::get_optional(dev.device(), Some("ref")).unwrap().is_none());
+
+    // Enabling a clock enables its parent, the hardware is only touched on the first enable
+    // and the last disable.
+    bus.prepare_enable().unwrap();
+    bus.prepare_enable().unwrap();
+    assert_eq!(GATE_ENABLES[0].load(Ordering::SeqCst), 1);
+    assert_eq!(GATE_ENABLES[1].load(Ordering::SeqCst), 1);
+    bus.disable_unprepare();
+    assert_eq!(GATE_DISABLES[1].load(Ordering::SeqCst), 0);
+    bus.disable_unprepare();
+    assert_eq!(GATE_DISABLES[0].load(Ordering::SeqCst), 1);
+    assert_eq!(GATE_DISABLES[1].load(Ordering::SeqCst), 1);
+
+    // The provider is not there yet.
+    assert!(matches!(Clk::get(device(LATE_DEV).device(), None), Err(EPROBE_DEFER)));
+}
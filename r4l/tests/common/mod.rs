@@ -0,0 +1,62 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Helpers shared by the hosted tests of r4l.
+//!
+//! Every test file is its own process with its own device tree: the tree is loaded once, by
+//! [`boot`], so a file only holds the tests that share it.
+
+#![allow(dead_code)]
+
+use kernel::init::driver_framework_init;
+use kernel::os::hosted::{self, fdt::FdtBuilder, take_log_records};
+use kernel::ThisModule;
+
+pub static THIS_MODULE: ThisModule = ThisModule();
+
+/// The phandle of the root interrupt controller added by [`begin_root`].
+pub const INTC: u32 = 1;
+
+/// Opens the root node, with 64-bit addresses and sizes and an interrupt controller with
+/// one cell per interrupt as the default interrupt parent.
+pub fn begin_root(fdt: &mut FdtBuilder) -> &mut FdtBuilder {
+    fdt.begin_node("")
+        .property_u32("#address-cells", 2)
+        .property_u32("#size-cells", 2)
+        .property_u32("interrupt-parent", INTC)
+        .begin_node("intc")
+        .property_u32("phandle", INTC)
+        .property_empty("interrupt-controller")
+        .property_u32("#interrupt-cells", 1)
+        .end_node()
+}
+
+/// Returns the cells of a `reg` entry, for a parent with 64-bit addresses and sizes.
+pub fn reg(start: usize, size: usize) -> [u32; 4] {
+    [(start >> 32) as u32, start as u32, (size >> 32) as u32, size as u32]
+}
+
+/// Returns the address of `size` leaked bytes aligned on a page, the hosted `ioremap` maps
+/// registers at their own address.
+pub fn leak_regs(size: usize) -> usize {
+    let layout = std::alloc::Layout::from_size_align(size, 4096).unwrap();
+    // SAFETY: The layout is not empty.
+    let regs = unsafe { std::alloc::alloc_zeroed(layout) };
+    assert!(!regs.is_null());
+    regs as usize
+}
+
+/// Loads the device tree and creates its devices.
+pub fn boot(fdt: &mut FdtBuilder) {
+    hosted::load_device_tree(fdt.finish());
+    driver_framework_init();
+}
+
+/// Returns the messages logged since the last call.
+pub fn take_messages() -> Vec<String> {
+    take_log_records().into_iter().map(|r| r.message).collect()
+}
+
+/// Returns true if one of `messages` contains `text`.
+pub fn logged(messages: &[String], text: &str) -> bool {
+    messages.iter().any(|m| m.contains(text))
+}
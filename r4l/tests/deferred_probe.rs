@@ -0,0 +1,80 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Checks that a probe returning `EPROBE_DEFER` is retried once another device binds.
+
+#![cfg(feature = "hosted")]
+
+// The macros of r4l name the crate `kernel`, as the drivers depend on it.
+extern crate r4l as kernel;
+
+mod common;
+
+use common::THIS_MODULE;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use kernel::os::hosted::fdt::FdtBuilder;
+use kernel::{c_str, define_of_id_table, of, platform, prelude::*};
+
+// Set once the provider is bound, the resource the consumer waits for.
+static PROVIDER_READY: AtomicBool = AtomicBool::new(false);
+static CONSUMER_PROBES: AtomicUsize = AtomicUsize::new(0);
+
+struct ProviderDriver;
+
+define_of_id_table! {PROVIDER_OF_MATCH_TABLE, (), [
+    (of::DeviceId::Compatible("test,provider"), None),
+]}
+
+impl platform::Driver for ProviderDriver {
+    type Data = ();
+    kernel::driver_of_id_table!(PROVIDER_OF_MATCH_TABLE);
+
+    fn probe(_pdev: &mut platform::Device, _id_info: Option<&Self::IdInfo>) -> Result {
+        PROVIDER_READY.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+struct ConsumerDriver;
+
+define_of_id_table! {CONSUMER_OF_MATCH_TABLE, (), [
+    (of::DeviceId::Compatible("test,consumer"), None),
+]}
+
+impl platform::Driver for ConsumerDriver {
+    type Data = ();
+    kernel::driver_of_id_table!(CONSUMER_OF_MATCH_TABLE);
+
+    fn probe(_pdev: &mut platform::Device, _id_info: Option<&Self::IdInfo>) -> Result {
+        CONSUMER_PROBES.fetch_add(1, Ordering::SeqCst);
+        if !PROVIDER_READY.load(Ordering::SeqCst) {
+            return Err(EPROBE_DEFER);
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn deferred_probe_is_retried_after_its_provider_binds() {
+    let mut fdt = FdtBuilder::new();
+    common::begin_root(&mut fdt)
+        .begin_node("consumer")
+        .property_strings("compatible", &["test,consumer"])
+        .end_node()
+        .begin_node("provider")
+        .property_strings("compatible", &["test,provider"])
+        .end_node()
+        .end_node();
+    common::boot(&mut fdt);
+
+    let _consumer =
+        platform::Registration::<ConsumerDriver>::new_pinned(c_str!("consumer"), &THIS_MODULE)
+            .unwrap();
+    assert_eq!(CONSUMER_PROBES.load(Ordering::SeqCst), 1);
+    assert_eq!(platform::platform_deferred_probe_report().len(), 1);
+
+    let _provider =
+        platform::Registration::<ProviderDriver>::new_pinned(c_str!("provider"), &THIS_MODULE)
+            .unwrap();
+    assert_eq!(CONSUMER_PROBES.load(Ordering::SeqCst), 2);
+    assert!(platform::platform_deferred_probe_report().is_empty());
+}
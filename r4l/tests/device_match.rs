@@ -0,0 +1,68 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Checks that devices registered after their driver are matched on registration.
+
+#![cfg(feature = "hosted")]
+
+// The macros of r4l name the crate `kernel`, as the drivers depend on it.
+extern crate r4l as kernel;
+
+mod common;
+
+use common::THIS_MODULE;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use kernel::os::hosted::fdt::FdtBuilder;
+use kernel::sync::{Arc, Mutex};
+use kernel::{c_str, define_of_id_table, of, platform, prelude::*};
+
+const LATE_PHANDLE: u32 = 2;
+
+static PROBES: AtomicUsize = AtomicUsize::new(0);
+
+struct UartDriver;
+
+define_of_id_table! {UART_OF_MATCH_TABLE, (), [
+    (of::DeviceId::Compatible("test,uart"), None),
+]}
+
+impl platform::Driver for UartDriver {
+    type Data = ();
+    kernel::driver_of_id_table!(UART_OF_MATCH_TABLE);
+
+    fn probe(_pdev: &mut platform::Device, _id_info: Option<&Self::IdInfo>) -> Result {
+        PROBES.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+#[test]
+fn devices_registered_after_the_driver_are_probed() {
+    // The driver comes first, before any device exists.
+    let _uart = platform::Registration::<UartDriver>::new_pinned(c_str!("uart"), &THIS_MODULE)
+        .unwrap();
+    assert_eq!(PROBES.load(Ordering::SeqCst), 0);
+
+    let mut fdt = FdtBuilder::new();
+    common::begin_root(&mut fdt)
+        .begin_node("uart@0")
+        .property_strings("compatible", &["test,uart"])
+        .end_node()
+        // Not a bus, its children are left to its own driver.
+        .begin_node("board")
+        .property_strings("compatible", &["test,board"])
+        .begin_node("uart@1")
+        .property_strings("compatible", &["test,uart"])
+        .property_u32("phandle", LATE_PHANDLE)
+        .end_node()
+        .end_node()
+        .end_node();
+    common::boot(&mut fdt);
+    assert_eq!(PROBES.load(Ordering::SeqCst), 1);
+
+    // A device created by hand later is matched too.
+    let node = of::of_find_node_by_phandle(LATE_PHANDLE).unwrap();
+    let pdev = Arc::new(Mutex::new(platform::PlatformDevice::new(node)));
+    platform::platform_device_register(pdev.clone()).unwrap();
+    assert_eq!(PROBES.load(Ordering::SeqCst), 2);
+    assert!(pdev.lock().is_bound());
+}
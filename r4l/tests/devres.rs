@@ -0,0 +1,111 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Checks that managed resources are released in reverse order, on unbind and when the probe
+//! fails.
+
+#![cfg(feature = "hosted")]
+
+// The macros of r4l name the crate `kernel`, as the drivers depend on it.
+extern crate r4l as kernel;
+
+mod common;
+
+use common::THIS_MODULE;
+use kernel::os::hosted::fdt::FdtBuilder;
+use kernel::sync::{Arc, Mutex};
+use kernel::{c_str, define_of_id_table, devres, driver, of, platform, prelude::*};
+
+const GOOD: u32 = 2;
+const BAD: u32 = 3;
+
+// What was released, in order.
+static EVENTS: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+fn take_events() -> Vec<&'static str> {
+    core::mem::take(&mut *EVENTS.lock())
+}
+
+// Logs its name once dropped.
+struct Tracker(&'static str);
+
+impl Drop for Tracker {
+    fn drop(&mut self) {
+        EVENTS.lock().push(self.0);
+    }
+}
+
+impl driver::DeviceRemoval for Tracker {
+    fn device_remove(&self) {
+        EVENTS.lock().push("device_remove");
+    }
+}
+
+// Manages three resources, then fails its probe if the device has `fail`.
+struct ManagedDriver;
+
+define_of_id_table! {MANAGED_OF_MATCH_TABLE, (), [
+    (of::DeviceId::Compatible("test,managed"), None),
+]}
+
+impl platform::Driver for ManagedDriver {
+    type Data = Arc<Tracker>;
+    kernel::driver_of_id_table!(MANAGED_OF_MATCH_TABLE);
+
+    fn probe(pdev: &mut platform::Device, _id_info: Option<&Self::IdInfo>) -> Result<Self::Data> {
+        let dev = pdev.device();
+        dev.devm_add_action(|| EVENTS.lock().push("first"))?;
+        let _alloc = devres::devm_alloc(dev, Tracker("alloc"))?;
+        dev.devm_add_action(|| EVENTS.lock().push("last"))?;
+        if dev.property_read_bool("fail") {
+            return Err(EIO);
+        }
+        Ok(Arc::new(Tracker("data")))
+    }
+}
+
+fn register_device(phandle: u32) -> Arc<Mutex<platform::PlatformDevice>> {
+    let node = of::of_find_node_by_phandle(phandle).unwrap();
+    let pdev = Arc::new(Mutex::new(platform::PlatformDevice::new(node)));
+    platform::platform_device_register(pdev.clone()).unwrap();
+    pdev
+}
+
+#[test]
+fn resources_are_released_in_reverse() {
+    let mut fdt = FdtBuilder::new();
+    common::begin_root(&mut fdt)
+        // Not a bus, the test registers its children by hand.
+        .begin_node("board")
+        .property_strings("compatible", &["test,board"])
+        .begin_node("good")
+        .property_u32("phandle", GOOD)
+        .property_strings("compatible", &["test,managed"])
+        .end_node()
+        .begin_node("bad")
+        .property_u32("phandle", BAD)
+        .property_strings("compatible", &["test,managed"])
+        .property_empty("fail")
+        .end_node()
+        .end_node()
+        .end_node();
+    common::boot(&mut fdt);
+
+    let _managed =
+        platform::Registration::<ManagedDriver>::new_pinned(c_str!("managed"), &THIS_MODULE)
+            .unwrap();
+
+    // A failed probe releases what it got right away.
+    let bad = register_device(BAD);
+    assert!(!bad.lock().is_bound());
+    assert_eq!(take_events(), ["last", "alloc", "first"]);
+
+    // The resources of a bound device live until it is unbound, after its driver data.
+    let good = register_device(GOOD);
+    assert!(good.lock().is_bound());
+    assert!(take_events().is_empty());
+    platform::platform_device_unregister(good).unwrap();
+    assert_eq!(
+        take_events(),
+        ["device_remove", "data", "last", "alloc", "first"]
+    );
+}
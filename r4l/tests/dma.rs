@@ -0,0 +1,110 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Checks the DMA addresses of devices behind `dma-ranges`, their masks and the buffers
+//! allocated and mapped for them.
+
+#![cfg(feature = "hosted")]
+
+// The macros of r4l name the crate `kernel`, as the drivers depend on it.
+extern crate r4l as kernel;
+
+mod common;
+
+use kernel::dma::{self, CoherentAllocation, DataDirection};
+use kernel::os::hosted::fdt::FdtBuilder;
+use kernel::{of, platform, prelude::*};
+
+const NIC: u32 = 2;
+const UART: u32 = 3;
+
+// The CPU sees the DMA address 0 of the bus at this address.
+const DMA_OFFSET: u64 = 0x1000;
+
+fn device(phandle: u32) -> platform::PlatformDevice {
+    platform::PlatformDevice::new(of::of_find_node_by_phandle(phandle).unwrap())
+}
+
+#[test]
+fn dma_follows_the_bus() {
+    let mut fdt = FdtBuilder::new();
+    common::begin_root(&mut fdt)
+        .begin_node("soc")
+        .property_u32("#address-cells", 2)
+        .property_u32("#size-cells", 2)
+        .property_empty("ranges")
+        .property_cells(
+            "dma-ranges",
+            &[0, 0, 0, DMA_OFFSET as u32, 0xffff_ffff, 0xffff_f000],
+        )
+        .property_empty("dma-coherent")
+        .begin_node("nic")
+        .property_u32("phandle", NIC)
+        .end_node()
+        .end_node()
+        .begin_node("uart")
+        .property_u32("phandle", UART)
+        .end_node()
+        .end_node();
+    common::boot(&mut fdt);
+
+    assert_eq!(dma::dma_bit_mask(32), 0xffff_ffff);
+    assert_eq!(dma::dma_bit_mask(64), u64::MAX);
+
+    // `dma-coherent` and `dma-ranges` apply to the devices of the bus only.
+    let mut nic = device(NIC);
+    assert!(nic.device().dma_coherent());
+    assert_eq!(nic.device().phys_to_dma(0x8000), Ok(0x8000 - DMA_OFFSET));
+    assert_eq!(nic.device().dma_to_phys(0x8000 - DMA_OFFSET), Ok(0x8000));
+    let uart = device(UART);
+    assert!(!uart.device().dma_coherent());
+    assert_eq!(uart.device().phys_to_dma(0x8000), Ok(0x8000));
+
+    // The buffers of the test are above 16 MiB, out of reach of a 24-bit device.
+    assert_eq!(nic.dma_set_mask(dma::dma_bit_mask(16)), Err(EIO));
+    nic.dma_set_mask(dma::dma_bit_mask(24)).unwrap();
+    assert!(matches!(
+        CoherentAllocation::<u32>::alloc_coherent(nic.device(), 16),
+        Err(ENOMEM)
+    ));
+    let mut buf = vec![0u8; 64];
+    // SAFETY: The mapping fails, the buffer is not handed to any device.
+    let mapped = unsafe {
+        dma::map_single(
+            nic.device(),
+            buf.as_mut_ptr(),
+            buf.len(),
+            DataDirection::ToDevice,
+        )
+    };
+    assert_eq!(mapped, Err(EIO));
+
+    nic.dma_set_mask(dma::dma_bit_mask(64)).unwrap();
+    let ring = CoherentAllocation::<u32>::alloc_coherent(nic.device(), 16).unwrap();
+    assert_eq!(ring.count(), 16);
+    assert_eq!(ring.read(15), Ok(0));
+    ring.write(15, 0xdead_beef).unwrap();
+    assert_eq!(ring.read(15), Ok(0xdead_beef));
+    assert_eq!(ring.read(16), Err(EINVAL));
+    assert_eq!(ring.write(16, 0), Err(EINVAL));
+    // The handle is the DMA address of the page of the array.
+    let paddr = nic.device().dma_to_phys(ring.dma_handle()).unwrap();
+    assert_eq!(paddr % 4096, 0);
+    assert!(matches!(
+        CoherentAllocation::<u32>::alloc_coherent(nic.device(), 0),
+        Err(EINVAL)
+    ));
+
+    // SAFETY: The buffer outlives the mapping and is only accessed after the unmap.
+    let dma_addr = unsafe {
+        dma::map_single(
+            nic.device(),
+            buf.as_mut_ptr(),
+            buf.len(),
+            DataDirection::FromDevice,
+        )
+    }
+    .unwrap();
+    assert_eq!(dma_addr, buf.as_ptr() as u64 - DMA_OFFSET);
+    // SAFETY: The address, size and direction are the ones of the mapping.
+    unsafe { dma::unmap_single(nic.device(), dma_addr, buf.len(), DataDirection::FromDevice) };
+}
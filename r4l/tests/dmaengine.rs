@@ -0,0 +1,139 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Checks the slave channels of a fake DMA controller: requests, and transfers run one at a
+//! time in submission order.
+
+#![cfg(feature = "hosted")]
+
+// The macros of r4l name the crate `kernel`, as the drivers depend on it.
+extern crate r4l as kernel;
+
+mod common;
+
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use kernel::dmaengine::{self, *};
+use kernel::os::hosted::fdt::FdtBuilder;
+use kernel::sync::{Arc, Mutex};
+use kernel::{of, platform, prelude::*};
+
+const DMAC: u32 = 2;
+const DEV: u32 = 3;
+
+// The transfers started, by channel and cookie.
+static STARTED: Mutex<Vec<(u32, Cookie)>> = Mutex::new(Vec::new());
+// The completion callbacks called, by transfer and outcome.
+static DONE: Mutex<Vec<(&'static str, Result)>> = Mutex::new(Vec::new());
+static FAIL_START: AtomicBool = AtomicBool::new(false);
+static TERMINATES: AtomicUsize = AtomicUsize::new(0);
+
+struct FakeDmac;
+
+impl DmaControllerOps for FakeDmac {
+    fn nr_channels(&self) -> u32 {
+        2
+    }
+
+    fn config(&self, _chan: u32, _config: &DmaSlaveConfig) -> Result {
+        Ok(())
+    }
+
+    fn start_transfer(&self, chan: u32, tx: &SlaveTransfer) -> Result {
+        if FAIL_START.load(Ordering::SeqCst) {
+            return Err(EIO);
+        }
+        STARTED.lock().push((chan, tx.cookie));
+        Ok(())
+    }
+
+    fn terminate_all(&self, _chan: u32) -> Result {
+        TERMINATES.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+fn take<T>(events: &Mutex<Vec<T>>) -> Vec<T> {
+    core::mem::take(&mut *events.lock())
+}
+
+fn submit(chan: &DmaChan, name: &'static str) -> Cookie {
+    let sgl = [SgEntry {
+        dma_addr: 0x1000,
+        len: 64,
+    }];
+    let callback: DmaCallback = Box::new(move |ret| DONE.lock().push((name, ret)));
+    chan.prep_slave_sg(&sgl, TransferDirection::MemToDev, Some(callback))
+        .unwrap()
+        .submit()
+        .unwrap()
+}
+
+#[test]
+fn transfers_run_in_order() {
+    let mut fdt = FdtBuilder::new();
+    common::begin_root(&mut fdt)
+        .begin_node("dmac")
+        .property_u32("phandle", DMAC)
+        .property_u32("#dma-cells", 1)
+        .end_node()
+        .begin_node("uart")
+        .property_u32("phandle", DEV)
+        .property_cells("dmas", &[DMAC, 0, DMAC, 1])
+        .property_strings("dma-names", &["tx", "rx"])
+        .end_node()
+        .end_node();
+    common::boot(&mut fdt);
+
+    let pdev = platform::PlatformDevice::new(of::of_find_node_by_phandle(DEV).unwrap());
+    let dev = pdev.device();
+    let dmac = of::of_find_node_by_phandle(DMAC).unwrap();
+
+    // The channels wait for their controller.
+    assert!(matches!(DmaChan::request(dev, "tx"), Err(EPROBE_DEFER)));
+    dmaengine::dma_controller_register(dmac, Arc::new(FakeDmac)).unwrap();
+    let tx = DmaChan::request(dev, "tx").unwrap();
+    assert!(matches!(DmaChan::request(dev, "tx"), Err(EBUSY)));
+    assert!(matches!(DmaChan::request(dev, "foo"), Err(ENOENT)));
+    assert!(DmaChan::request_optional(dev, "foo").unwrap().is_none());
+    let rx = DmaChan::request(dev, "rx").unwrap();
+    tx.slave_config(&DmaSlaveConfig::default()).unwrap();
+    assert!(matches!(
+        tx.prep_slave_sg(&[], TransferDirection::MemToDev, None),
+        Err(EINVAL)
+    ));
+
+    // Submitted transfers wait for `issue_pending`, then run one at a time.
+    let first = submit(&tx, "first");
+    let second = submit(&tx, "second");
+    assert_eq!((first, second), (1, 2));
+    assert!(take(&STARTED).is_empty());
+    assert_eq!(tx.tx_status(first), Ok(DmaStatus::InProgress));
+    tx.issue_pending();
+    assert_eq!(take(&STARTED), [(0, first)]);
+    dmaengine::dma_chan_complete(dmac, 0, Ok(()));
+    assert_eq!(take(&DONE), [("first", Ok(()))]);
+    assert_eq!(take(&STARTED), [(0, second)]);
+    assert_eq!(tx.tx_status(first), Ok(DmaStatus::Complete));
+    assert_eq!(tx.tx_status(second), Ok(DmaStatus::InProgress));
+    dmaengine::dma_chan_complete(dmac, 0, Err(EIO));
+    assert_eq!(take(&DONE), [("second", Err(EIO))]);
+    assert_eq!(tx.tx_status(second), Ok(DmaStatus::Complete));
+
+    // A transfer that cannot start completes with the error.
+    FAIL_START.store(true, Ordering::SeqCst);
+    let failed = submit(&tx, "failed");
+    tx.issue_pending();
+    assert_eq!(take(&DONE), [("failed", Err(EIO))]);
+    assert_eq!(tx.tx_status(failed), Ok(DmaStatus::Complete));
+    FAIL_START.store(false, Ordering::SeqCst);
+
+    // The transfers of a released channel are dropped, their callbacks are not called.
+    let dropped = submit(&rx, "dropped");
+    rx.issue_pending();
+    assert_eq!(take(&STARTED), [(1, dropped)]);
+    assert_eq!(dmaengine::dma_controller_unregister(dmac), Err(EBUSY));
+    drop(rx);
+    drop(tx);
+    assert_eq!(TERMINATES.load(Ordering::SeqCst), 2);
+    assert!(take(&DONE).is_empty());
+    dmaengine::dma_controller_unregister(dmac).unwrap();
+}
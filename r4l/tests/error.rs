@@ -0,0 +1,52 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Checks the errno table of `error::code` on the hosted backend.
+
+#![cfg(feature = "hosted")]
+
+// The macros of r4l name the crate `kernel`, as the drivers depend on it.
+extern crate r4l as kernel;
+
+use kernel::error::{code::*, Error};
+
+#[test]
+fn errnos_are_the_linux_ones() {
+    for (err, errno, name) in [
+        (EPERM, 1, "EPERM"),
+        (ENOENT, 2, "ENOENT"),
+        (EBUSY, 16, "EBUSY"),
+        (EINVAL, 22, "EINVAL"),
+        (EHWPOISON, 133, "EHWPOISON"),
+        (EPROBE_DEFER, 517, "EPROBE_DEFER"),
+        (ENOTSUPP, 524, "ENOTSUPP"),
+    ] {
+        assert_eq!(err.to_errno(), -errno);
+        assert_eq!(Error::from_errno(-errno), err);
+        assert_eq!(err.name(), Some(name));
+        assert_eq!(format!("{:?}", err), name);
+    }
+}
+
+#[test]
+fn every_errno_round_trips() {
+    for errno in 1..4096 {
+        let err = Error::from_errno(-errno);
+        assert_eq!(err.to_errno(), -errno);
+        // The hosted native errors are host errnos.
+        assert_eq!(err.to_native(), errno);
+        assert_eq!(Error::from_native(errno), err);
+    }
+}
+
+#[test]
+fn unknown_errnos() {
+    // Out of range values are not errnos.
+    assert_eq!(Error::from_errno(0), EINVAL);
+    assert_eq!(Error::from_errno(1), EINVAL);
+    assert_eq!(Error::from_errno(-4096), EINVAL);
+
+    // Errnos Linux leaves undefined keep their number.
+    let err = Error::from_errno(-600);
+    assert_eq!(err.name(), None);
+    assert_eq!(format!("{:?}", err), "Error(600)");
+}
@@ -0,0 +1,120 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Checks the GPIO consumer descriptors against a fake controller.
+
+#![cfg(feature = "hosted")]
+
+// The macros of r4l name the crate `kernel`, as the drivers depend on it.
+extern crate r4l as kernel;
+
+mod common;
+
+use kernel::gpio::{self, Direction, GpioChip, GpioDesc};
+use kernel::os::hosted::fdt::FdtBuilder;
+use kernel::sync::{Arc, Mutex};
+use kernel::{of, platform, prelude::*};
+
+const GPIO: u32 = 2;
+const LATE_GPIO: u32 = 3;
+const DEV: u32 = 4;
+
+const NGPIO: usize = 8;
+
+// A controller whose lines are plain memory: an output reads back what it drives.
+struct FakeChip {
+    lines: Mutex<[(Direction, bool); NGPIO]>,
+}
+
+impl GpioChip for FakeChip {
+    fn ngpio(&self) -> u32 {
+        NGPIO as u32
+    }
+
+    fn get_direction(&self, offset: u32) -> Result<Direction> {
+        Ok(self.lines.lock()[offset as usize].0)
+    }
+
+    fn direction_input(&self, offset: u32) -> Result {
+        self.lines.lock()[offset as usize].0 = Direction::In;
+        Ok(())
+    }
+
+    fn direction_output(&self, offset: u32, value: bool) -> Result {
+        self.lines.lock()[offset as usize] = (Direction::Out, value);
+        Ok(())
+    }
+
+    fn get(&self, offset: u32) -> Result<bool> {
+        Ok(self.lines.lock()[offset as usize].1)
+    }
+
+    fn set(&self, offset: u32, value: bool) {
+        self.lines.lock()[offset as usize].1 = value;
+    }
+}
+
+#[test]
+fn gpio_descriptors() {
+    let mut fdt = FdtBuilder::new();
+    common::begin_root(&mut fdt)
+        .begin_node("gpio")
+        .property_u32("phandle", GPIO)
+        .property_empty("gpio-controller")
+        .property_u32("#gpio-cells", 2)
+        .end_node()
+        // Its driver never shows up.
+        .begin_node("late-gpio")
+        .property_u32("phandle", LATE_GPIO)
+        .property_empty("gpio-controller")
+        .property_u32("#gpio-cells", 2)
+        .end_node()
+        .begin_node("dev")
+        .property_u32("phandle", DEV)
+        // Active low.
+        .property_cells("reset-gpios", &[GPIO, 3, 1])
+        .property_cells("enable-gpio", &[GPIO, 4, 0])
+        .property_cells("wake-gpios", &[LATE_GPIO, 0, 0])
+        .property_cells("bad-gpios", &[GPIO, NGPIO as u32, 0])
+        .end_node()
+        .end_node();
+    common::boot(&mut fdt);
+
+    let chip = Arc::new(FakeChip {
+        lines: Mutex::new([(Direction::In, false); NGPIO]),
+    });
+    let gpio_node = of::of_find_node_by_phandle(GPIO).unwrap();
+    gpio::gpiochip_add(gpio_node, chip.clone()).unwrap();
+    assert_eq!(gpio::gpiochip_add(gpio_node, chip.clone()), Err(EEXIST));
+
+    let pdev = platform::PlatformDevice::new(of::of_find_node_by_phandle(DEV).unwrap());
+    let dev = pdev.device();
+
+    // Logical values follow the polarity of the line.
+    let reset = GpioDesc::get(dev, "reset", gpio::Flags::OUT_HIGH).unwrap();
+    assert_eq!(reset.offset(), 3);
+    assert!(reset.is_active_low());
+    assert_eq!(chip.lines.lock()[3], (Direction::Out, false));
+    assert_eq!(reset.get_value(), Ok(true));
+    reset.set_value(false);
+    assert_eq!(reset.get_raw_value(), Ok(true));
+
+    // A line has a single owner.
+    assert!(matches!(GpioDesc::get(dev, "reset", gpio::Flags::AS_IS), Err(EBUSY)));
+    assert_eq!(gpio::gpiochip_remove(gpio_node), Err(EBUSY));
+    drop(reset);
+    let reset = GpioDesc::get(dev, "reset", gpio::Flags::AS_IS).unwrap();
+
+    // The deprecated `-gpio` suffix.
+    chip.lines.lock()[4] = (Direction::Out, true);
+    let enable = GpioDesc::get(dev, "enable", gpio::Flags::IN).unwrap();
+    assert_eq!(enable.get_direction(), Ok(Direction::In));
+    assert_eq!(enable.get_value(), Ok(true));
+
+    assert!(GpioDesc::get_optional(dev, "power", gpio::Flags::AS_IS).unwrap().is_none());
+    assert!(matches!(GpioDesc::get(dev, "wake", gpio::Flags::AS_IS), Err(EPROBE_DEFER)));
+    assert!(matches!(GpioDesc::get(dev, "bad", gpio::Flags::AS_IS), Err(EINVAL)));
+
+    drop(reset);
+    drop(enable);
+    assert_eq!(gpio::gpiochip_remove(gpio_node), Ok(()));
+}
@@ -0,0 +1,64 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Checks that a probe gets the `IdInfo` of the id table entry that matched its device.
+
+#![cfg(feature = "hosted")]
+
+// The macros of r4l name the crate `kernel`, as the drivers depend on it.
+extern crate r4l as kernel;
+
+mod common;
+
+use common::THIS_MODULE;
+use kernel::os::hosted::fdt::FdtBuilder;
+use kernel::sync::Mutex;
+use kernel::{c_str, define_of_id_table, of, platform, prelude::*};
+
+// The name of every probed device, with the FIFO depth its probe got.
+static PROBED: Mutex<Vec<(&'static str, Option<u32>)>> = Mutex::new(Vec::new());
+
+struct UartDriver;
+
+// The FIFO depth of each variant.
+define_of_id_table! {UART_OF_MATCH_TABLE, u32, [
+    (of::DeviceId::Compatible("test,uart-v1"), Some(16)),
+    (of::DeviceId::Compatible("test,uart-v2"), Some(64)),
+    (of::DeviceId::Compatible("test,uart"), None),
+]}
+
+impl platform::Driver for UartDriver {
+    type Data = ();
+    type IdInfo = u32;
+    kernel::driver_of_id_table!(UART_OF_MATCH_TABLE);
+
+    fn probe(pdev: &mut platform::Device, id_info: Option<&Self::IdInfo>) -> Result {
+        PROBED.lock().push((pdev.name(), id_info.copied()));
+        Ok(())
+    }
+}
+
+#[test]
+fn probe_gets_the_info_of_the_matched_entry() {
+    let mut fdt = FdtBuilder::new();
+    common::begin_root(&mut fdt)
+        .begin_node("uart@0")
+        .property_strings("compatible", &["test,uart-v2", "test,uart"])
+        .end_node()
+        .begin_node("uart@1")
+        .property_strings("compatible", &["test,uart-v1", "test,uart"])
+        .end_node()
+        .begin_node("uart@2")
+        .property_strings("compatible", &["test,uart"])
+        .end_node()
+        .end_node();
+    common::boot(&mut fdt);
+
+    let _uart = platform::Registration::<UartDriver>::new_pinned(c_str!("uart"), &THIS_MODULE)
+        .unwrap();
+    let mut probed = PROBED.lock().clone();
+    probed.sort();
+    assert_eq!(
+        probed,
+        [("uart@0", Some(64)), ("uart@1", Some(16)), ("uart@2", None)]
+    );
+}
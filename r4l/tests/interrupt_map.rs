@@ -0,0 +1,62 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Checks the translation of interrupt specifiers through an `interrupt-map`.
+
+#![cfg(feature = "hosted")]
+
+// The macros of r4l name the crate `kernel`, as the drivers depend on it.
+extern crate r4l as kernel;
+
+mod common;
+
+use common::INTC;
+use kernel::os::hosted::fdt::FdtBuilder;
+use kernel::{of, platform, prelude::*};
+
+const BRIDGE: u32 = 2;
+
+fn irq(phandle: u32, index: usize) -> Result<u32> {
+    platform::PlatformDevice::new(of::of_find_node_by_phandle(phandle).unwrap()).irq_resource(index)
+}
+
+fn device<'a>(fdt: &'a mut FdtBuilder, addr: u32, interrupts: &[u32]) -> &'a mut FdtBuilder {
+    fdt.begin_node(&format!("dev@{:x}", addr))
+        .property_u32("phandle", addr)
+        .property_cells("reg", &[addr, 0x10])
+        .property_u32("interrupt-parent", BRIDGE)
+        .property_cells("interrupts", interrupts)
+        .end_node()
+}
+
+#[test]
+fn interrupt_map_routes_to_the_parent_controller() {
+    let mut fdt = FdtBuilder::new();
+    common::begin_root(&mut fdt)
+        .begin_node("bridge")
+        .property_u32("phandle", BRIDGE)
+        .property_u32("#address-cells", 1)
+        .property_u32("#size-cells", 1)
+        .property_u32("#interrupt-cells", 1)
+        // The low byte of the unit address does not select the entry.
+        .property_cells("interrupt-map-mask", &[0xff00, 0x7])
+        .property_cells(
+            "interrupt-map",
+            &[
+                0x100, 1, INTC, 40, //
+                0x100, 2, INTC, 41, //
+                0x200, 1, INTC, 42,
+            ],
+        );
+    device(&mut fdt, 0x100, &[1, 2]);
+    device(&mut fdt, 0x280, &[1]);
+    device(&mut fdt, 0x300, &[1]);
+    fdt.end_node().end_node();
+    common::boot(&mut fdt);
+
+    assert_eq!(irq(0x100, 0), Ok(40));
+    assert_eq!(irq(0x100, 1), Ok(41));
+    // Matched through the mask.
+    assert_eq!(irq(0x280, 0), Ok(42));
+    // No entry for the unit address.
+    assert_eq!(irq(0x300, 0), Err(EINVAL));
+}
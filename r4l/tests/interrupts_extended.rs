@@ -0,0 +1,62 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Checks `interrupts-extended` and the lookup of interrupts by `interrupt-names`.
+
+#![cfg(feature = "hosted")]
+
+// The macros of r4l name the crate `kernel`, as the drivers depend on it.
+extern crate r4l as kernel;
+
+mod common;
+
+use common::INTC;
+use kernel::os::hosted::fdt::FdtBuilder;
+use kernel::{irq, of, platform, prelude::*};
+
+// A root controller with a trigger type cell.
+const INTC2: u32 = 2;
+const BRIDGE: u32 = 3;
+const DEV: u32 = 4;
+
+#[test]
+fn interrupts_extended_and_names() {
+    let mut fdt = FdtBuilder::new();
+    common::begin_root(&mut fdt)
+        .begin_node("intc2")
+        .property_u32("phandle", INTC2)
+        .property_empty("interrupt-controller")
+        .property_u32("#interrupt-cells", 2)
+        .property_u32("interrupt-parent", INTC2)
+        .end_node()
+        .begin_node("bridge")
+        .property_u32("phandle", BRIDGE)
+        .property_u32("#address-cells", 1)
+        .property_u32("#size-cells", 1)
+        .property_u32("#interrupt-cells", 1)
+        .property_cells("interrupt-map-mask", &[0xffff, 0x7])
+        .property_cells("interrupt-map", &[0x100, 1, INTC, 50, 0x200, 1, INTC, 51])
+        .begin_node("dev@200")
+        .property_u32("phandle", DEV)
+        .property_cells("reg", &[0x200, 0x10])
+        // Ignored, `interrupts-extended` wins.
+        .property_u32("interrupts", 99)
+        // High level on the second controller.
+        .property_cells("interrupts-extended", &[INTC, 7, INTC2, 9, 4, BRIDGE, 1])
+        .property_strings("interrupt-names", &["tx", "rx", "wake"])
+        .end_node()
+        .end_node()
+        .end_node();
+    common::boot(&mut fdt);
+
+    let pdev = platform::PlatformDevice::new(of::of_find_node_by_phandle(DEV).unwrap());
+    assert_eq!(pdev.irq_resource(0), Ok(7));
+    assert_eq!(pdev.irq_resource_flags(1), Ok((9, irq::Flags::TRIGGER_HIGH)));
+    // Through the map, with the unit address of the device.
+    assert_eq!(pdev.irq_resource(2), Ok(51));
+    assert_eq!(pdev.irq_resource(3), Err(ENOENT));
+
+    assert_eq!(pdev.irq_by_name("tx"), Ok(7));
+    assert_eq!(pdev.irq_by_name("rx"), Ok(9));
+    assert_eq!(pdev.irq_by_name("wake"), Ok(51));
+    assert_eq!(pdev.irq_by_name("err"), Err(EINVAL));
+}
@@ -0,0 +1,57 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Checks the `reg` windows of a platform device and their mapping through `IoMem`.
+
+#![cfg(feature = "hosted")]
+
+// The macros of r4l name the crate `kernel`, as the drivers depend on it.
+extern crate r4l as kernel;
+
+mod common;
+
+use kernel::io::{IoMem, Resource};
+use kernel::os::hosted::fdt::FdtBuilder;
+use kernel::{of, platform, prelude::*};
+
+const DEV_PHANDLE: u32 = 2;
+
+#[test]
+fn reg_windows_map_to_io_mem() {
+    let base = common::leak_regs(0x1000);
+    let mut fdt = FdtBuilder::new();
+    common::begin_root(&mut fdt)
+        .begin_node(&format!("dev@{:x}", base))
+        .property_u32("phandle", DEV_PHANDLE)
+        .property_cells("reg", &[common::reg(base, 0x100), common::reg(base + 0x100, 0)].concat())
+        .end_node()
+        .end_node();
+    common::boot(&mut fdt);
+
+    let pdev = platform::PlatformDevice::new(of::of_find_node_by_phandle(DEV_PHANDLE).unwrap());
+    let res = pdev.io_resource(0).unwrap();
+    assert_eq!(res, Resource::new(base, 0x100));
+    assert_eq!(res.end(), Some(base + 0xff));
+    // An empty window is rejected, a missing one too.
+    assert_eq!(pdev.io_resource(1), Err(EINVAL));
+    assert_eq!(pdev.io_resource(2), Err(EINVAL));
+
+    // The window is too small for the mapping.
+    // SAFETY: Nothing else accesses the registers.
+    assert!(unsafe { IoMem::<0x200>::try_new(res) }.is_err());
+    // SAFETY: Nothing else accesses the registers.
+    let mem = unsafe { IoMem::<0x100>::try_new(res) }.unwrap();
+    mem.try_writel(0x1234_5678, 0x10).unwrap();
+    // SAFETY: The registers are leaked, the mapping only accesses them volatile too.
+    assert_eq!(unsafe { ((base + 0x10) as *const u32).read_volatile() }, 0x1234_5678);
+    assert_eq!(mem.try_readw(0x12), Ok(0x1234));
+    // Out of bounds and misaligned accesses.
+    assert_eq!(mem.try_readl(0x100), Err(EINVAL));
+    assert_eq!(mem.try_readl(0x2), Err(EINVAL));
+}
+
+#[test]
+fn empty_resource_has_no_end() {
+    assert_eq!(Resource::new(0x1000, 0).end(), None);
+    assert_eq!(Resource::new(usize::MAX, 2).end(), None);
+    assert_eq!(Resource::new(0x1000, 1).end(), Some(0x1000));
+}
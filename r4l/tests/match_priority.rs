@@ -0,0 +1,76 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Checks that a device binds to the driver of its most specific compatible string.
+
+#![cfg(feature = "hosted")]
+
+// The macros of r4l name the crate `kernel`, as the drivers depend on it.
+extern crate r4l as kernel;
+
+mod common;
+
+use common::THIS_MODULE;
+use kernel::os::hosted::fdt::FdtBuilder;
+use kernel::sync::Mutex;
+use kernel::{c_str, define_of_id_table, of, platform, prelude::*};
+
+// The name of every probed device, with the driver that probed it.
+static PROBED: Mutex<Vec<(&'static str, &'static str)>> = Mutex::new(Vec::new());
+
+struct GenericDriver;
+
+define_of_id_table! {GENERIC_OF_MATCH_TABLE, (), [
+    (of::DeviceId::Compatible("test,uart"), None),
+]}
+
+impl platform::Driver for GenericDriver {
+    type Data = ();
+    kernel::driver_of_id_table!(GENERIC_OF_MATCH_TABLE);
+
+    fn probe(pdev: &mut platform::Device, _id_info: Option<&Self::IdInfo>) -> Result {
+        PROBED.lock().push((pdev.name(), "generic"));
+        Ok(())
+    }
+}
+
+struct SpecificDriver;
+
+define_of_id_table! {SPECIFIC_OF_MATCH_TABLE, (), [
+    (of::DeviceId::Compatible("test,uart-v2"), None),
+]}
+
+impl platform::Driver for SpecificDriver {
+    type Data = ();
+    kernel::driver_of_id_table!(SPECIFIC_OF_MATCH_TABLE);
+
+    fn probe(pdev: &mut platform::Device, _id_info: Option<&Self::IdInfo>) -> Result {
+        PROBED.lock().push((pdev.name(), "specific"));
+        Ok(())
+    }
+}
+
+#[test]
+fn more_specific_compatible_wins() {
+    // The generic driver is registered first, it would win on registration order.
+    let _generic =
+        platform::Registration::<GenericDriver>::new_pinned(c_str!("generic"), &THIS_MODULE)
+            .unwrap();
+    let _specific =
+        platform::Registration::<SpecificDriver>::new_pinned(c_str!("specific"), &THIS_MODULE)
+            .unwrap();
+
+    let mut fdt = FdtBuilder::new();
+    common::begin_root(&mut fdt)
+        .begin_node("uart@0")
+        .property_strings("compatible", &["test,uart-v2", "test,uart"])
+        .end_node()
+        .begin_node("uart@1")
+        .property_strings("compatible", &["test,uart"])
+        .end_node()
+        .end_node();
+    common::boot(&mut fdt);
+
+    let mut probed = PROBED.lock().clone();
+    probed.sort();
+    assert_eq!(probed, [("uart@0", "specific"), ("uart@1", "generic")]);
+}
@@ -0,0 +1,67 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Checks the parsing of phandle lists with `#*-cells` arguments.
+
+#![cfg(feature = "hosted")]
+
+// The macros of r4l name the crate `kernel`, as the drivers depend on it.
+extern crate r4l as kernel;
+
+mod common;
+
+use kernel::os::hosted::fdt::FdtBuilder;
+use kernel::{of, prelude::*};
+
+const CLK0: u32 = 2;
+const CLK1: u32 = 3;
+const NO_CELLS: u32 = 4;
+const DEV: u32 = 5;
+
+#[test]
+fn phandle_lists() {
+    let mut fdt = FdtBuilder::new();
+    common::begin_root(&mut fdt)
+        .begin_node("clk0")
+        .property_u32("phandle", CLK0)
+        .property_u32("#clock-cells", 0)
+        .end_node()
+        .begin_node("clk1")
+        .property_u32("phandle", CLK1)
+        .property_u32("#clock-cells", 2)
+        .end_node()
+        .begin_node("nocells")
+        .property_u32("phandle", NO_CELLS)
+        .end_node()
+        .begin_node("dev")
+        .property_u32("phandle", DEV)
+        // A reference without arguments, an empty entry, then one with two arguments.
+        .property_cells("clocks", &[CLK0, 0, CLK1, 7, 8])
+        .property_cells("bad-clocks", &[CLK1, 7])
+        .property_cells("orphan-clocks", &[NO_CELLS])
+        .property_cells("companions", &[CLK0, CLK1])
+        .end_node()
+        .end_node();
+    common::boot(&mut fdt);
+
+    let dev = of::of_find_node_by_phandle(DEV).unwrap();
+    assert_eq!(of::count_phandle_with_args(dev, "clocks", "#clock-cells"), Ok(3));
+
+    let clk = of::parse_phandle_with_args(dev, "clocks", "#clock-cells", 0).unwrap();
+    assert_eq!(of::of_get_phandle(clk.np), Some(CLK0));
+    assert!(clk.args().is_empty());
+    assert!(matches!(of::parse_phandle_with_args(dev, "clocks", "#clock-cells", 1), Err(ENOENT)));
+    let clk = of::parse_phandle_with_args(dev, "clocks", "#clock-cells", 2).unwrap();
+    assert_eq!(of::of_get_phandle(clk.np), Some(CLK1));
+    assert_eq!(clk.args(), [7, 8]);
+    assert!(matches!(of::parse_phandle_with_args(dev, "clocks", "#clock-cells", 3), Err(ENOENT)));
+    assert!(matches!(of::parse_phandle_with_args(dev, "dmas", "#dma-cells", 0), Err(ENOENT)));
+
+    // The list ends in the middle of the arguments of its entry.
+    assert_eq!(of::count_phandle_with_args(dev, "bad-clocks", "#clock-cells"), Err(EINVAL));
+    // The target has no `#clock-cells`.
+    assert_eq!(of::count_phandle_with_args(dev, "orphan-clocks", "#clock-cells"), Err(EINVAL));
+
+    // Plain phandle lists.
+    let companion = of::parse_phandle(dev, "companions", 1).unwrap();
+    assert_eq!(of::of_get_phandle(companion), Some(CLK1));
+}
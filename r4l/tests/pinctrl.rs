@@ -0,0 +1,138 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Checks the pin states selected by the platform bus around the probe of a device.
+
+#![cfg(feature = "hosted")]
+
+// The macros of r4l name the crate `kernel`, as the drivers depend on it.
+extern crate r4l as kernel;
+
+mod common;
+
+use common::THIS_MODULE;
+use kernel::os::hosted::fdt::FdtBuilder;
+use kernel::pinctrl::{self, PinctrlOps};
+use kernel::sync::{Arc, Mutex};
+use kernel::{c_str, define_of_id_table, of, platform, prelude::*};
+
+const PINCTRL: u32 = 2;
+const UART_INIT: u32 = 3;
+const UART_DEFAULT: u32 = 4;
+const SPI_DEFAULT: u32 = 5;
+const UART: u32 = 6;
+const SPI: u32 = 7;
+
+// The configuration nodes applied and the probes, in order.
+static EVENTS: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+fn take_events() -> Vec<&'static str> {
+    core::mem::take(&mut *EVENTS.lock())
+}
+
+struct FakePinctrl;
+
+impl PinctrlOps for FakePinctrl {
+    fn apply(&self, config: ::of::OfNode<'static>) -> Result {
+        EVENTS.lock().push(of::of_node_name(config));
+        Ok(())
+    }
+}
+
+struct UartDriver;
+
+define_of_id_table! {UART_OF_MATCH_TABLE, (), [
+    (of::DeviceId::Compatible("test,uart"), None),
+]}
+
+impl platform::Driver for UartDriver {
+    type Data = ();
+    kernel::driver_of_id_table!(UART_OF_MATCH_TABLE);
+
+    fn probe(_pdev: &mut platform::Device, _id_info: Option<&Self::IdInfo>) -> Result {
+        EVENTS.lock().push("probe uart");
+        Ok(())
+    }
+}
+
+// Fails its probe, after its pins were set up.
+struct SpiDriver;
+
+define_of_id_table! {SPI_OF_MATCH_TABLE, (), [
+    (of::DeviceId::Compatible("test,spi"), None),
+]}
+
+impl platform::Driver for SpiDriver {
+    type Data = ();
+    kernel::driver_of_id_table!(SPI_OF_MATCH_TABLE);
+
+    fn probe(_pdev: &mut platform::Device, _id_info: Option<&Self::IdInfo>) -> Result {
+        EVENTS.lock().push("probe spi");
+        Err(EIO)
+    }
+}
+
+fn register_device(phandle: u32) -> Arc<Mutex<platform::PlatformDevice>> {
+    let node = of::of_find_node_by_phandle(phandle).unwrap();
+    let pdev = Arc::new(Mutex::new(platform::PlatformDevice::new(node)));
+    platform::platform_device_register(pdev.clone()).unwrap();
+    pdev
+}
+
+#[test]
+fn pin_states_follow_the_probe() {
+    let mut fdt = FdtBuilder::new();
+    common::begin_root(&mut fdt)
+        .begin_node("pinctrl")
+        .property_u32("phandle", PINCTRL)
+        .begin_node("uart0-init")
+        .property_u32("phandle", UART_INIT)
+        .end_node()
+        .begin_node("uart0-default")
+        .property_u32("phandle", UART_DEFAULT)
+        .end_node()
+        .begin_node("spi0-default")
+        .property_u32("phandle", SPI_DEFAULT)
+        .end_node()
+        .end_node()
+        // Not a bus, the test registers its children by hand.
+        .begin_node("board")
+        .property_strings("compatible", &["test,board"])
+        .begin_node("uart@0")
+        .property_u32("phandle", UART)
+        .property_strings("compatible", &["test,uart"])
+        .property_strings("pinctrl-names", &["init", "default"])
+        .property_u32("pinctrl-0", UART_INIT)
+        .property_u32("pinctrl-1", UART_DEFAULT)
+        .end_node()
+        .begin_node("spi@0")
+        .property_u32("phandle", SPI)
+        .property_strings("compatible", &["test,spi"])
+        .property_strings("pinctrl-names", &["default"])
+        .property_u32("pinctrl-0", SPI_DEFAULT)
+        .end_node()
+        .end_node()
+        .end_node();
+    common::boot(&mut fdt);
+
+    let node = of::of_find_node_by_phandle(PINCTRL).unwrap();
+    pinctrl::pinctrl_register(node, Arc::new(FakePinctrl)).unwrap();
+    let _uart =
+        platform::Registration::<UartDriver>::new_pinned(c_str!("uart"), &THIS_MODULE).unwrap();
+    let _spi =
+        platform::Registration::<SpiDriver>::new_pinned(c_str!("spi"), &THIS_MODULE).unwrap();
+
+    // The probe runs in the `init` state, `default` is selected once it succeeded.
+    let uart = register_device(UART);
+    assert!(uart.lock().is_bound());
+    assert_eq!(take_events(), ["uart0-init", "probe uart", "uart0-default"]);
+    assert_eq!(uart.lock().pinctrl_select_state("sleep"), Err(ENODEV));
+    uart.lock().pinctrl_select_state("init").unwrap();
+    assert_eq!(take_events(), ["uart0-init"]);
+
+    // The pin states of a device that failed its probe are dropped.
+    let spi = register_device(SPI);
+    assert!(!spi.lock().is_bound());
+    assert_eq!(take_events(), ["spi0-default", "probe spi"]);
+    assert_eq!(spi.lock().pinctrl_select_state("default"), Err(ENODEV));
+    assert!(take_events().is_empty());
+}
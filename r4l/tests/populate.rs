@@ -0,0 +1,75 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Checks the recursive creation of platform devices through nested buses.
+
+#![cfg(feature = "hosted")]
+
+// The macros of r4l name the crate `kernel`, as the drivers depend on it.
+extern crate r4l as kernel;
+
+mod common;
+
+use common::THIS_MODULE;
+use kernel::os::hosted::fdt::FdtBuilder;
+use kernel::sync::Mutex;
+use kernel::{c_str, define_of_id_table, of, platform, prelude::*};
+
+// The name of every probed device, with the name of its parent device.
+static PROBED: Mutex<Vec<(&'static str, Option<&'static str>)>> = Mutex::new(Vec::new());
+
+struct LeafDriver;
+
+define_of_id_table! {LEAF_OF_MATCH_TABLE, (), [
+    (of::DeviceId::Compatible("test,leaf"), None),
+]}
+
+impl platform::Driver for LeafDriver {
+    type Data = ();
+    kernel::driver_of_id_table!(LEAF_OF_MATCH_TABLE);
+
+    fn probe(pdev: &mut platform::Device, _id_info: Option<&Self::IdInfo>) -> Result {
+        let parent = pdev.parent().map(|parent| parent.lock().name());
+        PROBED.lock().push((pdev.name(), parent));
+        Ok(())
+    }
+}
+
+fn leaf<'a>(fdt: &'a mut FdtBuilder, name: &str) -> &'a mut FdtBuilder {
+    fdt.begin_node(name)
+        .property_strings("compatible", &["test,leaf"])
+        .end_node()
+}
+
+#[test]
+fn nested_buses_are_populated() {
+    let mut fdt = FdtBuilder::new();
+    common::begin_root(&mut fdt);
+    leaf(&mut fdt, "leaf@0");
+    fdt.begin_node("soc")
+        .property_strings("compatible", &["simple-bus"])
+        .begin_node("mfd")
+        .property_strings("compatible", &["vendor,pmic", "simple-mfd"]);
+    leaf(&mut fdt, "leaf@1");
+    fdt.end_node();
+    leaf(&mut fdt, "leaf@2");
+    fdt.begin_node("leaf@3")
+        .property_strings("compatible", &["test,leaf"])
+        .property_strings("status", &["disabled"])
+        .end_node()
+        .end_node()
+        // Not a bus, its children are left to its own driver.
+        .begin_node("board")
+        .property_strings("compatible", &["test,board"]);
+    leaf(&mut fdt, "leaf@4");
+    fdt.end_node().end_node();
+    common::boot(&mut fdt);
+
+    let _leaf = platform::Registration::<LeafDriver>::new_pinned(c_str!("leaf"), &THIS_MODULE)
+        .unwrap();
+    let mut probed = PROBED.lock().clone();
+    probed.sort();
+    assert_eq!(
+        probed,
+        [("leaf@0", None), ("leaf@1", Some("mfd")), ("leaf@2", Some("soc"))]
+    );
+}
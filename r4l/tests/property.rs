@@ -0,0 +1,75 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Checks the unified property API of devices and firmware nodes.
+
+#![cfg(feature = "hosted")]
+
+// The macros of r4l name the crate `kernel`, as the drivers depend on it.
+extern crate r4l as kernel;
+
+mod common;
+
+use kernel::os::hosted::fdt::FdtBuilder;
+use kernel::{of, platform, prelude::*};
+
+const DEV: u32 = 2;
+
+#[test]
+fn device_properties() {
+    let mut fdt = FdtBuilder::new();
+    common::begin_root(&mut fdt)
+        .begin_node("dev@0")
+        .property_u32("phandle", DEV)
+        .property_empty("wakeup-source")
+        .property_u32("clock-frequency", 400_000)
+        .property_cells("fifo-depths", &[16, 32, 64])
+        .property_cells("timeout-ns", &[0x1, 0x2])
+        .property_strings("label", &["console"])
+        .property_strings("modes", &["slow", "fast", "turbo"])
+        .property("odd", &[0, 0, 0, 1, 2])
+        .property("unterminated", b"abc")
+        .begin_node("port@0")
+        .end_node()
+        .begin_node("port@1")
+        .property_strings("status", &["disabled"])
+        .end_node()
+        .begin_node("led")
+        .end_node()
+        .end_node()
+        .end_node();
+    common::boot(&mut fdt);
+
+    let pdev = platform::PlatformDevice::new(of::of_find_node_by_phandle(DEV).unwrap());
+    let dev = pdev.device();
+
+    assert!(dev.property_read_bool("wakeup-source"));
+    assert!(!dev.property_read_bool("dma-coherent"));
+    assert_eq!(dev.property_read_u32("clock-frequency"), Ok(400_000));
+    assert_eq!(dev.property_read_u32("missing"), Err(ENOENT));
+    // An empty property has no value.
+    assert_eq!(dev.property_read_u32("wakeup-source"), Err(EINVAL));
+    assert_eq!(dev.property_read_u64("timeout-ns"), Ok(0x1_0000_0002));
+
+    let mut depths = [0; 3];
+    dev.property_read_u32_array("fifo-depths", &mut depths).unwrap();
+    assert_eq!(depths, [16, 32, 64]);
+    let mut too_many = [0; 4];
+    assert_eq!(dev.property_read_u32_array("fifo-depths", &mut too_many), Err(EOVERFLOW));
+    assert_eq!(dev.property_count_elems_of_size("fifo-depths", 4), Ok(3));
+    assert_eq!(dev.property_count_elems_of_size("odd", 4), Err(EINVAL));
+
+    assert_eq!(dev.property_read_string("label"), Ok("console"));
+    assert_eq!(dev.property_read_string("unterminated"), Err(EINVAL));
+    assert_eq!(dev.property_read_string_array("modes"), Ok(vec!["slow", "fast", "turbo"]));
+    assert_eq!(dev.property_match_string("modes", "fast"), Ok(1));
+    assert_eq!(dev.property_match_string("modes", "eco"), Err(ENODATA));
+    assert_eq!(dev.property_match_string("names", "fast"), Err(ENOENT));
+
+    // Disabled children are skipped.
+    let fwnode = pdev.fwnode();
+    let children: Vec<_> = fwnode.child_nodes().map(|child| child.name()).collect();
+    assert_eq!(children, ["port@0", "led"]);
+    assert_eq!(fwnode.get_named_child("port").map(|child| child.name()), Some("port@0"));
+    assert!(fwnode.get_named_child("button").is_none());
+    assert_eq!(fwnode.get_named_child("led").unwrap().parent().unwrap().name(), "dev@0");
+}
@@ -0,0 +1,99 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Checks the translation of `reg` addresses through the `ranges` of nested buses.
+
+#![cfg(feature = "hosted")]
+
+// The macros of r4l name the crate `kernel`, as the drivers depend on it.
+extern crate r4l as kernel;
+
+mod common;
+
+use kernel::io::Resource;
+use kernel::os::hosted::fdt::FdtBuilder;
+use kernel::{of, platform, prelude::*};
+
+const SOC_DEV: u32 = 2;
+const NESTED_DEV: u32 = 3;
+const OUT_OF_RANGE_DEV: u32 = 4;
+const IDENTITY_DEV: u32 = 5;
+const UNMAPPED_DEV: u32 = 6;
+const MALFORMED_DEV: u32 = 7;
+
+fn resource(phandle: u32) -> Result<Resource> {
+    platform::PlatformDevice::new(of::of_find_node_by_phandle(phandle).unwrap()).io_resource(0)
+}
+
+#[test]
+fn reg_is_translated_through_ranges() {
+    let mut fdt = FdtBuilder::new();
+    common::begin_root(&mut fdt)
+        .begin_node("soc")
+        .property_strings("compatible", &["simple-bus"])
+        .property_u32("#address-cells", 1)
+        .property_u32("#size-cells", 1)
+        // 64 KiB at 0x4000_0000.
+        .property_cells("ranges", &[0, 0, 0x4000_0000, 0x1_0000])
+        .begin_node("dev@100")
+        .property_u32("phandle", SOC_DEV)
+        .property_cells("reg", &[0x100, 0x10])
+        .end_node()
+        .begin_node("bus@8000")
+        .property_strings("compatible", &["simple-bus"])
+        .property_u32("#address-cells", 1)
+        .property_u32("#size-cells", 1)
+        // 4 KiB at 0x8000 of the soc.
+        .property_cells("ranges", &[0, 0x8000, 0x1000])
+        .begin_node("dev@20")
+        .property_u32("phandle", NESTED_DEV)
+        .property_cells("reg", &[0x20, 0x10])
+        .end_node()
+        .begin_node("dev@2000")
+        .property_u32("phandle", OUT_OF_RANGE_DEV)
+        .property_cells("reg", &[0x2000, 0x10])
+        .end_node()
+        .end_node()
+        .begin_node("bus@1")
+        .property_strings("compatible", &["simple-bus"])
+        .property_u32("#address-cells", 1)
+        .property_u32("#size-cells", 1)
+        .property_empty("ranges")
+        .begin_node("dev@300")
+        .property_u32("phandle", IDENTITY_DEV)
+        .property_cells("reg", &[0x300, 0x10])
+        .end_node()
+        .end_node()
+        .begin_node("bus@2")
+        .property_u32("#address-cells", 1)
+        .property_u32("#size-cells", 1)
+        .begin_node("dev@400")
+        .property_u32("phandle", UNMAPPED_DEV)
+        .property_cells("reg", &[0x400, 0x10])
+        .end_node()
+        .end_node()
+        .begin_node("bus@3")
+        .property_strings("compatible", &["simple-bus"])
+        .property_u32("#address-cells", 1)
+        .property_u32("#size-cells", 1)
+        // Entries are 3 cells long.
+        .property_cells("ranges", &[0, 0x9000])
+        .begin_node("dev@500")
+        .property_u32("phandle", MALFORMED_DEV)
+        .property_cells("reg", &[0x500, 0x10])
+        .end_node()
+        .end_node()
+        .end_node()
+        .end_node();
+    common::boot(&mut fdt);
+
+    assert_eq!(resource(SOC_DEV), Ok(Resource::new(0x4000_0100, 0x10)));
+    assert_eq!(resource(NESTED_DEV), Ok(Resource::new(0x4000_8020, 0x10)));
+    // Past the length of the range of its bus.
+    assert_eq!(resource(OUT_OF_RANGE_DEV), Err(EINVAL));
+    // An empty `ranges` maps 1:1.
+    assert_eq!(resource(IDENTITY_DEV), Ok(Resource::new(0x4000_0300, 0x10)));
+    // A bus without `ranges` cannot be translated through.
+    assert_eq!(resource(UNMAPPED_DEV), Err(EINVAL));
+    // A `ranges` that is not made of whole entries.
+    assert_eq!(resource(MALFORMED_DEV), Err(EINVAL));
+}
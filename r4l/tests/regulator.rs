@@ -0,0 +1,143 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Checks the use counts of regulators and their supplies, when the supplies fail too.
+
+#![cfg(feature = "hosted")]
+
+// The macros of r4l name the crate `kernel`, as the drivers depend on it.
+extern crate r4l as kernel;
+
+mod common;
+
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use kernel::os::hosted::fdt::FdtBuilder;
+use kernel::regulator::{self, Regulator, RegulatorOps};
+use kernel::{of, platform, prelude::*};
+
+const VIN: u32 = 2;
+const LDO: u32 = 3;
+const BOOT_ON: u32 = 4;
+const DEV: u32 = 5;
+
+// Per regulator: whether its operations fail, and how many times the output was switched.
+static FAIL_ENABLE: [AtomicBool; 3] = [
+    AtomicBool::new(false),
+    AtomicBool::new(false),
+    AtomicBool::new(false),
+];
+static FAIL_DISABLE: [AtomicBool; 3] = [
+    AtomicBool::new(false),
+    AtomicBool::new(false),
+    AtomicBool::new(false),
+];
+static ENABLES: [AtomicUsize; 3] = [
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+];
+static DISABLES: [AtomicUsize; 3] = [
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+];
+
+struct FakeRegulator(usize);
+
+impl RegulatorOps for FakeRegulator {
+    fn enable(&self) -> Result {
+        if FAIL_ENABLE[self.0].load(Ordering::SeqCst) {
+            return Err(EIO);
+        }
+        ENABLES[self.0].fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn disable(&self) -> Result {
+        if FAIL_DISABLE[self.0].load(Ordering::SeqCst) {
+            return Err(EIO);
+        }
+        DISABLES[self.0].fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+fn counts(counters: &[AtomicUsize; 3]) -> [usize; 2] {
+    [
+        counters[0].load(Ordering::SeqCst),
+        counters[1].load(Ordering::SeqCst),
+    ]
+}
+
+fn register(phandle: u32, index: usize) -> Result {
+    let node = of::of_find_node_by_phandle(phandle).unwrap();
+    regulator::regulator_register(node, Box::new(FakeRegulator(index)))
+}
+
+#[test]
+fn use_counts_survive_supply_failures() {
+    let mut fdt = FdtBuilder::new();
+    common::begin_root(&mut fdt)
+        .begin_node("vin")
+        .property_u32("phandle", VIN)
+        .end_node()
+        .begin_node("ldo")
+        .property_u32("phandle", LDO)
+        .property_u32("vin-supply", VIN)
+        .end_node()
+        .begin_node("boot-on")
+        .property_u32("phandle", BOOT_ON)
+        .property_u32("vin-supply", VIN)
+        .property_empty("regulator-boot-on")
+        .end_node()
+        .begin_node("dev")
+        .property_u32("phandle", DEV)
+        .property_u32("vdd-supply", LDO)
+        .property_u32("vbat-supply", BOOT_ON)
+        .end_node()
+        .end_node();
+    common::boot(&mut fdt);
+
+    register(VIN, 0).unwrap();
+    register(LDO, 1).unwrap();
+    let pdev = platform::PlatformDevice::new(of::of_find_node_by_phandle(DEV).unwrap());
+    let dev = pdev.device();
+
+    // A regulator that cannot be turned on at registration is not published.
+    FAIL_ENABLE[0].store(true, Ordering::SeqCst);
+    assert_eq!(register(BOOT_ON, 2), Err(EIO));
+    assert!(matches!(Regulator::get(dev, "vbat"), Err(EPROBE_DEFER)));
+
+    // The supply failed, the regulator stays off and unused.
+    let vdd = Regulator::get(dev, "vdd").unwrap();
+    assert_eq!(vdd.enable(), Err(EIO));
+    assert!(!vdd.is_enabled());
+    FAIL_ENABLE[0].store(false, Ordering::SeqCst);
+    vdd.enable().unwrap();
+    vdd.enable().unwrap();
+    assert!(vdd.is_enabled());
+    assert_eq!(counts(&ENABLES), [1, 1]);
+
+    // The supply cannot be turned off: the regulator is off and unused anyway, the supply
+    // stays on and used.
+    FAIL_DISABLE[0].store(true, Ordering::SeqCst);
+    vdd.disable().unwrap();
+    assert_eq!(vdd.disable(), Err(EIO));
+    assert!(!vdd.is_enabled());
+    assert_eq!(counts(&DISABLES), [0, 1]);
+    assert_eq!(vdd.disable(), Err(EIO));
+    FAIL_DISABLE[0].store(false, Ordering::SeqCst);
+
+    // Turning the regulator on again only switches the regulator.
+    vdd.enable().unwrap();
+    assert_eq!(counts(&ENABLES), [1, 2]);
+    // Dropping the consumer disables what it enabled, the supply keeps the use it failed to
+    // drop.
+    drop(vdd);
+    assert_eq!(counts(&DISABLES), [0, 2]);
+
+    // A device without the supply gets a dummy regulator.
+    let vio = Regulator::get(dev, "vio").unwrap();
+    vio.enable().unwrap();
+    assert_eq!(vio.get_voltage(), Err(EINVAL));
+    assert!(Regulator::get_optional(dev, "vio").unwrap().is_none());
+}
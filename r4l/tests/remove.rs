@@ -0,0 +1,89 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Checks that unbinding a device runs the remove path of its driver.
+
+#![cfg(feature = "hosted")]
+
+// The macros of r4l name the crate `kernel`, as the drivers depend on it.
+extern crate r4l as kernel;
+
+mod common;
+
+use common::THIS_MODULE;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use kernel::os::hosted::fdt::FdtBuilder;
+use kernel::sync::{Arc, Mutex};
+use kernel::{c_str, define_of_id_table, driver, of, platform, prelude::*};
+
+const LATE_PHANDLE: u32 = 2;
+
+static REMOVES: AtomicUsize = AtomicUsize::new(0);
+static DEVICE_REMOVES: AtomicUsize = AtomicUsize::new(0);
+
+struct LedData;
+
+impl driver::DeviceRemoval for LedData {
+    fn device_remove(&self) {
+        DEVICE_REMOVES.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+struct LedDriver;
+
+define_of_id_table! {LED_OF_MATCH_TABLE, (), [
+    (of::DeviceId::Compatible("test,led"), None),
+]}
+
+impl platform::Driver for LedDriver {
+    type Data = Arc<LedData>;
+    kernel::driver_of_id_table!(LED_OF_MATCH_TABLE);
+
+    fn probe(_pdev: &mut platform::Device, _id_info: Option<&Self::IdInfo>) -> Result<Self::Data> {
+        Ok(Arc::new(LedData))
+    }
+
+    fn remove(_data: &Self::Data) -> Result {
+        REMOVES.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+#[test]
+fn unbind_runs_remove() {
+    let mut fdt = FdtBuilder::new();
+    common::begin_root(&mut fdt)
+        .begin_node("led@0")
+        .property_strings("compatible", &["test,led"])
+        .end_node()
+        // Not a bus, the test registers its child by hand.
+        .begin_node("board")
+        .property_strings("compatible", &["test,board"])
+        .begin_node("led@1")
+        .property_strings("compatible", &["test,led"])
+        .property_u32("phandle", LATE_PHANDLE)
+        .end_node()
+        .end_node()
+        .end_node();
+    common::boot(&mut fdt);
+
+    let led = platform::Registration::<LedDriver>::new_pinned(c_str!("led"), &THIS_MODULE)
+        .unwrap();
+    let node = of::of_find_node_by_phandle(LATE_PHANDLE).unwrap();
+    let pdev = Arc::new(Mutex::new(platform::PlatformDevice::new(node)));
+    platform::platform_device_register(pdev.clone()).unwrap();
+    assert!(pdev.lock().is_bound());
+    assert_eq!(REMOVES.load(Ordering::SeqCst), 0);
+
+    // Removing a device unbinds it.
+    platform::platform_device_unregister(pdev.clone()).unwrap();
+    assert!(!pdev.lock().is_bound());
+    assert_eq!(REMOVES.load(Ordering::SeqCst), 1);
+    assert_eq!(DEVICE_REMOVES.load(Ordering::SeqCst), 1);
+    // It is no longer on the bus.
+    assert_eq!(platform::platform_device_unregister(pdev), Err(ENODEV));
+
+    // Unregistering the driver unbinds the other device.
+    drop(led);
+    assert_eq!(REMOVES.load(Ordering::SeqCst), 2);
+    assert_eq!(DEVICE_REMOVES.load(Ordering::SeqCst), 2);
+}
@@ -0,0 +1,142 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Checks exclusive and shared reset controls against a fake reset controller.
+
+#![cfg(feature = "hosted")]
+
+// The macros of r4l name the crate `kernel`, as the drivers depend on it.
+extern crate r4l as kernel;
+
+mod common;
+
+use common::THIS_MODULE;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use kernel::os::hosted::fdt::FdtBuilder;
+use kernel::reset::{self, ResetControl, ResetControllerOps};
+use kernel::sync::{Arc, Mutex};
+use kernel::{c_str, define_of_id_table, devres, of, platform, prelude::*};
+
+const RST: u32 = 2;
+const DEV0: u32 = 3;
+const DEV1: u32 = 4;
+
+const NR_RESETS: usize = 4;
+
+// A controller that records the state of its lines and counts the hardware accesses.
+struct FakeReset {
+    asserted: Mutex<[bool; NR_RESETS]>,
+    accesses: AtomicUsize,
+}
+
+impl FakeReset {
+    fn set(&self, id: u32, asserted: bool) -> Result {
+        self.asserted.lock()[id as usize] = asserted;
+        self.accesses.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+impl ResetControllerOps for FakeReset {
+    fn nr_resets(&self) -> u32 {
+        NR_RESETS as u32
+    }
+
+    fn assert(&self, id: u32) -> Result {
+        self.set(id, true)
+    }
+
+    fn deassert(&self, id: u32) -> Result {
+        self.set(id, false)
+    }
+
+    fn status(&self, id: u32) -> Result<bool> {
+        Ok(self.asserted.lock()[id as usize])
+    }
+}
+
+// Takes its reset out of reset for as long as it is bound.
+struct ConsumerDriver;
+
+define_of_id_table! {CONSUMER_OF_MATCH_TABLE, (), [
+    (of::DeviceId::Compatible("test,reset-consumer"), None),
+]}
+
+impl platform::Driver for ConsumerDriver {
+    type Data = ();
+    kernel::driver_of_id_table!(CONSUMER_OF_MATCH_TABLE);
+
+    fn probe(pdev: &mut platform::Device, _id_info: Option<&Self::IdInfo>) -> Result {
+        let rst = devres::devm_reset_control_get_shared(pdev.device(), None)?;
+        rst.deassert()
+    }
+}
+
+fn device(phandle: u32) -> platform::PlatformDevice {
+    platform::PlatformDevice::new(of::of_find_node_by_phandle(phandle).unwrap())
+}
+
+#[test]
+fn shared_deasserts_are_counted() {
+    let mut fdt = FdtBuilder::new();
+    common::begin_root(&mut fdt)
+        .begin_node("reset")
+        .property_u32("phandle", RST)
+        .property_u32("#reset-cells", 1)
+        .end_node()
+        .begin_node("dev0")
+        .property_u32("phandle", DEV0)
+        .property_cells("resets", &[RST, 1, RST, 2])
+        .property_strings("reset-names", &["bus", "phy"])
+        .end_node()
+        .begin_node("dev1")
+        .property_u32("phandle", DEV1)
+        .property_cells("resets", &[RST, 1])
+        .end_node()
+        .begin_node("dev2")
+        .property_strings("compatible", &["test,reset-consumer"])
+        .property_cells("resets", &[RST, 3])
+        .end_node()
+        .end_node();
+    common::boot(&mut fdt);
+
+    let rcdev = Arc::new(FakeReset {
+        asserted: Mutex::new([true; NR_RESETS]),
+        accesses: AtomicUsize::new(0),
+    });
+    let rst_node = of::of_find_node_by_phandle(RST).unwrap();
+    reset::reset_controller_register(rst_node, rcdev.clone()).unwrap();
+    let (dev0, dev1) = (device(DEV0), device(DEV1));
+
+    // The line is deasserted by the first deassert and asserted by the last assert.
+    let bus0 = ResetControl::get_shared(dev0.device(), Some("bus")).unwrap();
+    let bus1 = ResetControl::get_shared(dev1.device(), None).unwrap();
+    bus0.deassert().unwrap();
+    bus1.deassert().unwrap();
+    assert_eq!(rcdev.accesses.load(Ordering::SeqCst), 1);
+    assert_eq!(bus1.status(), Ok(false));
+    bus0.assert().unwrap();
+    assert_eq!(bus1.status(), Ok(false));
+    bus1.assert().unwrap();
+    assert_eq!(bus1.status(), Ok(true));
+    assert_eq!(rcdev.accesses.load(Ordering::SeqCst), 2);
+    // Every assert must balance a deassert.
+    assert_eq!(bus0.assert(), Err(EINVAL));
+
+    // A shared line cannot be taken exclusively, an exclusive line not at all.
+    assert!(matches!(ResetControl::get_exclusive(dev1.device(), None), Err(EBUSY)));
+    let phy = ResetControl::get_exclusive(dev0.device(), Some("phy")).unwrap();
+    assert!(matches!(ResetControl::get_shared(dev0.device(), Some("phy")), Err(EBUSY)));
+    phy.deassert().unwrap();
+    assert_eq!(phy.status(), Ok(false));
+    assert!(ResetControl::get_optional_exclusive(dev0.device(), Some("pcie")).unwrap().is_none());
+
+    // The managed control of a bound driver is released on unbind.
+    let consumer =
+        platform::Registration::<ConsumerDriver>::new_pinned(c_str!("consumer"), &THIS_MODULE)
+            .unwrap();
+    assert!(!rcdev.asserted.lock()[3]);
+    drop((bus0, bus1, phy));
+    assert_eq!(reset::reset_controller_unregister(rst_node), Err(EBUSY));
+    drop(consumer);
+    assert_eq!(reset::reset_controller_unregister(rst_node), Ok(()));
+}
@@ -0,0 +1,82 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Checks that a oneshot threaded handler keeps its line masked while its thread runs.
+
+#![cfg(feature = "hosted")]
+
+// The macros of r4l name the crate `kernel`, as the drivers depend on it.
+extern crate r4l as kernel;
+
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use kernel::irq;
+use kernel::os::hosted::trigger_irq;
+use kernel::sync::Arc;
+use std::time::{Duration, Instant};
+
+const ONESHOT_IRQ: u32 = 40;
+const PLAIN_IRQ: u32 = 41;
+
+// Holds the thread handler until it is opened.
+#[derive(Default)]
+struct Gate {
+    entered: AtomicUsize,
+    open: AtomicBool,
+}
+
+struct GatedHandler;
+
+impl irq::ThreadedHandler for GatedHandler {
+    type Data = Arc<Gate>;
+
+    fn thread_fn(gate: &Arc<Gate>) -> irq::Return {
+        gate.entered.fetch_add(1, Ordering::SeqCst);
+        while !gate.open.load(Ordering::SeqCst) {
+            std::thread::yield_now();
+        }
+        irq::Return::Handled
+    }
+}
+
+// The thread handler runs in its own thread, give it some time.
+fn wait_for(cond: impl Fn() -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !cond() {
+        assert!(Instant::now() < deadline, "timed out");
+        std::thread::yield_now();
+    }
+}
+
+fn register(irq: u32, flags: irq::Flags) -> (Arc<Gate>, irq::ThreadedRegistration) {
+    let gate = Arc::new(Gate::default());
+    let reg = irq::ThreadedRegistration::try_new::<GatedHandler>(
+        irq,
+        gate.clone(),
+        flags,
+        format_args!("gated"),
+    )
+    .unwrap();
+    (gate, reg)
+}
+
+#[test]
+fn oneshot_masks_until_the_thread_is_done() {
+    let (gate, _reg) = register(ONESHOT_IRQ, irq::Flags::ONESHOT);
+    assert!(trigger_irq(ONESHOT_IRQ));
+    wait_for(|| gate.entered.load(Ordering::SeqCst) == 1);
+    // Masked while the thread handler runs.
+    assert!(!trigger_irq(ONESHOT_IRQ));
+    gate.open.store(true, Ordering::SeqCst);
+    // Unmasked once it returned.
+    wait_for(|| trigger_irq(ONESHOT_IRQ));
+    wait_for(|| gate.entered.load(Ordering::SeqCst) == 2);
+}
+
+#[test]
+fn line_stays_unmasked_without_oneshot() {
+    let (gate, _reg) = register(PLAIN_IRQ, irq::Flags::empty());
+    assert!(trigger_irq(PLAIN_IRQ));
+    wait_for(|| gate.entered.load(Ordering::SeqCst) == 1);
+    assert!(trigger_irq(PLAIN_IRQ));
+    gate.open.store(true, Ordering::SeqCst);
+    wait_for(|| gate.entered.load(Ordering::SeqCst) == 2);
+}